@@ -1,77 +1,457 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Order,
-    Response, StdError, StdResult, Uint128, WasmMsg,
+    from_binary, to_binary, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty, Env, IbcMsg,
+    MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, SubMsg, Timestamp, Uint128,
+    WasmMsg,
 };
 use cw2::set_contract_version;
-use cw20::Cw20ReceiveMsg;
+use cw20::{
+    BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse,
+};
 use cw721::Cw721ReceiveMsg;
+use cw_storage_plus::{Bound, Map};
+use cw_utils::parse_reply_instantiate_data;
 
 use crate::error::ContractError;
+use crate::events::MarketplaceEvent;
+use crate::ibc::{
+    reply_instantiate_voucher, NonFungibleTokenPacketData, INSTANTIATE_VOUCHER_REPLY_ID,
+};
 use crate::msg::{
-    Cw20DepositResponse, Cw20HookMsg, Cw721DepositResponse, Cw721HookMsg, DepositResponse,
-    ExecuteMsg, InstantiateMsg, QueryMsg,
+    AllowlistProof, AskUpdate, AsksResponse, AssetSolvency, BundlesResponse,
+    CollectionStatsResponse, CollectionVolume, CollectionsResponse, ConfigResponse,
+    CurrentUserResponse, Cw20DepositResponse, Cw20HookMsg, Cw721DepositResponse, Cw721HookMsg,
+    DepositResponse, ExecuteMsg, InstantiateMsg, LoansResponse, MintAllowanceResponse,
+    MintPhasesResponse, MintVoucher, NonceUsedResponse, PaymentTokenStatsResponse,
+    PendingAdminResponse, QueryMsg, ReceiptTokenResponse, ReferralEarningsResponse, Referrer,
+    ReferrersResponse, RoyaltiesResponse, SalesResponse, SellOrder, SigningKeyResponse,
+    SolvencyResponse, SudoMsg, SwapResponse, SwapsResponse, TopCollectionsResponse, VaultsResponse,
+    VoucherContractResponse, Withdrawal,
 };
 use crate::state::{
-    Cw20Deposit, Cw721Deposit, Deposit, Offer, ASKS, CW20_DEPOSITS, CW721_DEPOSITS, DEPOSITS,
+    AdminProposal, Asset, AssetInfo, Bundle, Buyout, Collection, Config, Cw20Deposit, Cw721Deposit,
+    Deposit, Loan, MintPhase, NftItem, NftLock, Offer, Rental, Royalty, Sale, Swap, Vault,
+    ADMIN_PROPOSAL, ASKS, ASK_PRICES, BUNDLES, BUNDLE_COUNT, COLLECTIONS, COLLECTION_COUNT,
+    COLLECTION_STATS, CONFIG, CW20_DEPOSITS, CW721_DEPOSITS, DEPOSITS, ESCROWED_NFTS,
+    HOURLY_VOLUMES, IBC_CHANNELS, IBC_ESCROW, LIABILITIES, LOANS, LOAN_COUNT, MINT_PHASES,
+    MIN_ORDER_NONCES, ORDER_NONCES, PAYMENT_TOKEN_STATS, PENDING_COLLECTION, PENDING_RECEIPT_DENOM,
+    PENDING_VAULT, PHASE_MINTS, RECEIPT_DENOMS, RECEIPT_TOKENS, REFERRAL_EARNINGS, REFERRERS,
+    RENTALS, ROYALTIES, SALES, SALES_BY_ADDRESS, SALES_BY_COLLECTION, SALES_BY_TOKEN, SALE_COUNT,
+    SIGNING_KEYS, SWAPS, SWAP_COUNT, SWAP_FILLS, SWAP_FILL_STARTED, USED_NONCES, VAULTS,
+    VAULT_COUNT, VOLUME_RANKING, VOUCHER_CLASS_IDS, VOUCHER_CONTRACTS,
 };
 
 use nft;
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
 
 const CONTRACT_NAME: &str = "deposit-cw20-example";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const DEFAULT_MAX_BATCH_SIZE: u32 = 20;
+
+// settings for pagination
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+// largest page of operators cw721-base returns
+const MAX_OPERATORS_PAGE: u32 = 100;
+
+const SECONDS_PER_HOUR: u64 = 3600;
+
+const INSTANTIATE_SHARE_TOKEN_REPLY_ID: u64 = 1;
+const INSTANTIATE_RECEIPT_TOKEN_REPLY_ID: u64 = 2;
+const INSTANTIATE_COLLECTION_REPLY_ID: u64 = 4;
+
+const DEFAULT_IBC_TIMEOUT_SECONDS: u64 = 600;
+
+const MAX_FEE_BPS: u64 = 10_000;
+const DEFAULT_MAX_ROYALTY_BPS: u64 = 1_000;
+
+const MAX_ADMIN_PROPOSAL_SECONDS: u64 = 14 * 24 * SECONDS_PER_HOUR;
+
+// how long a partial swap fill keeps other takers out
+const SWAP_FILL_TIMEOUT_SECONDS: u64 = 24 * SECONDS_PER_HOUR;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    Ok(Response::new().add_attribute("method", "instantiate"))
+
+    let config = Config {
+        admin: info.sender.to_string(),
+        max_batch_size: msg.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+        cw20_code_id: msg.cw20_code_id,
+        nft_code_id: msg.nft_code_id,
+        fee_bps: 0,
+        fee_recipient: info.sender.to_string(),
+        paused: false,
+        max_royalty_bps: DEFAULT_MAX_ROYALTY_BPS,
+        deposit_receipts: msg.deposit_receipts.unwrap_or_default(),
+    };
+    if config.deposit_receipts && config.cw20_code_id.is_none() {
+        return Err(ContractError::Cw20CodeIdNotSet {});
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate")
+        .add_attribute("admin", config.admin)
+        .add_attribute("max_batch_size", config.max_batch_size.to_string()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Receive(cw20_msg) => receive_cw20(deps, info, cw20_msg),
-        ExecuteMsg::WithdrawCw20 { owner, amount } => try_withdraw_cw20(deps, info, owner, amount),
-        ExecuteMsg::Deposit {} => try_deposit(deps, info),
+        ExecuteMsg::Receive(cw20_msg) => receive_cw20(deps, env, info, cw20_msg),
+        ExecuteMsg::WithdrawCw20 {
+            cw20_contract,
+            amount,
+        } => try_withdraw_cw20(deps, info, cw20_contract, amount),
+        ExecuteMsg::Deposit {} => try_deposit(deps, env, info),
         ExecuteMsg::Withdraw { amount, denom } => try_withdraw_deposit(deps, info, amount, denom),
-        ExecuteMsg::ReceiveNft(cw721_msg) => receive_cw721(deps, info, cw721_msg),
+        ExecuteMsg::ReceiveNft(cw721_msg) => receive_cw721(deps, env, info, cw721_msg),
         ExecuteMsg::WithdrawNft {
             cw721_contract,
             token_id,
         } => try_withdraw_cw721(deps, info, cw721_contract, token_id),
+        ExecuteMsg::BatchWithdrawNft { nfts } => try_batch_withdraw_cw721(deps, info, nfts),
+        ExecuteMsg::BatchWithdraw { withdrawals } => try_batch_withdraw(deps, info, withdrawals),
+        ExecuteMsg::BatchUpdateAsks { asks } => try_batch_update_asks(deps, info, asks),
+        ExecuteMsg::UpdateConfig {
+            max_batch_size,
+            cw20_code_id,
+            nft_code_id,
+            max_royalty_bps,
+            deposit_receipts,
+        } => try_update_config(
+            deps,
+            info,
+            max_batch_size,
+            cw20_code_id,
+            nft_code_id,
+            max_royalty_bps,
+            deposit_receipts,
+        ),
+        ExecuteMsg::SetRoyalties {
+            cw721_contract,
+            royalties,
+        } => try_set_royalties(deps, info, cw721_contract, royalties),
+        ExecuteMsg::ProposeNewAdmin { addr, expires_in } => {
+            try_propose_new_admin(deps, env, info, addr, expires_in)
+        }
+        ExecuteMsg::ClaimAdmin {} => try_claim_admin(deps, env, info),
+        ExecuteMsg::DropAdminProposal {} => try_drop_admin_proposal(deps, info),
+        ExecuteMsg::SetReferrer { address, share_bps } => {
+            try_set_referrer(deps, info, address, share_bps)
+        }
+        ExecuteMsg::RemoveReferrer { address } => try_remove_referrer(deps, info, address),
+        ExecuteMsg::CreateBundle { nfts, price } => try_create_bundle(deps, info, nfts, price),
+        ExecuteMsg::CancelBundle { bundle_id } => try_cancel_bundle(deps, info, bundle_id),
+        ExecuteMsg::CreateSwap {
+            offered,
+            wanted,
+            maker_top_up,
+            taker_top_up,
+        } => try_create_swap(deps, info, offered, wanted, maker_top_up, taker_top_up),
+        ExecuteMsg::CancelSwap { swap_id } => try_cancel_swap(deps, info, swap_id),
+        ExecuteMsg::WithdrawSwapFill { swap_id } => try_withdraw_swap_fill(deps, info, swap_id),
+        ExecuteMsg::PurchaseBundle {
+            bundle_id,
+            referrer,
+        } => {
+            let payment = native_payment(&info)?;
+            execute_purchase_bundle(
+                deps,
+                env,
+                bundle_id,
+                info.sender.to_string(),
+                payment,
+                referrer,
+            )
+        }
+        ExecuteMsg::Purchase {
+            cw721_contract,
+            token_id,
+            referrer,
+        } => {
+            let payment = native_payment(&info)?;
+            settle_purchase(
+                deps,
+                env,
+                cw721_contract,
+                token_id,
+                info.sender.to_string(),
+                payment,
+                referrer,
+            )
+        }
+        ExecuteMsg::RequestLoan {
+            cw721_contract,
+            token_id,
+            cw20_contract,
+            principal,
+            interest,
+            duration,
+        } => try_request_loan(
+            deps,
+            info,
+            NftItem {
+                cw721_contract,
+                token_id,
+            },
+            cw20_contract,
+            principal,
+            interest,
+            duration,
+        ),
+        ExecuteMsg::CancelLoan { loan_id } => try_cancel_loan(deps, info, loan_id),
+        ExecuteMsg::ClaimCollateral { loan_id } => try_claim_collateral(deps, env, info, loan_id),
+        ExecuteMsg::ListRental {
+            cw721_contract,
+            token_id,
+            cw20_contract,
+            price_per_period,
+            period,
+        } => try_list_rental(
+            deps,
+            info,
+            cw721_contract,
+            token_id,
+            cw20_contract,
+            price_per_period,
+            period,
+        ),
+        ExecuteMsg::ReclaimRental {
+            cw721_contract,
+            token_id,
+        } => try_reclaim_rental(deps, env, info, cw721_contract, token_id),
+        ExecuteMsg::Fractionalize {
+            cw721_contract,
+            token_id,
+            name,
+            symbol,
+            supply,
+            buyout_token,
+            buyout_price,
+        } => try_fractionalize(
+            deps,
+            env,
+            info,
+            NftItem {
+                cw721_contract,
+                token_id,
+            },
+            name,
+            symbol,
+            supply,
+            buyout_token,
+            buyout_price,
+        ),
+        ExecuteMsg::CreateCollection {
+            name,
+            symbol,
+            mint_price,
+            max_supply,
+            start_time,
+        } => try_create_collection(
+            deps, env, info, name, symbol, mint_price, max_supply, start_time,
+        ),
+        ExecuteMsg::SetMintPhases {
+            collection_id,
+            phases,
+        } => try_set_mint_phases(deps, info, collection_id, phases),
+        ExecuteMsg::Mint {
+            collection_id,
+            allowlist,
+        } => {
+            let payment = native_payment(&info)?;
+            execute_mint(
+                deps,
+                env,
+                collection_id,
+                info.sender.to_string(),
+                payment,
+                allowlist,
+            )
+        }
+        ExecuteMsg::SetSigningKey { pubkey } => try_set_signing_key(deps, info, pubkey),
+        ExecuteMsg::RedeemVoucher { voucher, signature } => {
+            let payment = native_payment(&info)?;
+            execute_redeem_voucher(
+                deps,
+                env,
+                *voucher,
+                signature,
+                info.sender.to_string(),
+                payment,
+            )
+        }
+        ExecuteMsg::FillOrder {
+            order,
+            signature,
+            referrer,
+        } => {
+            let payment = native_payment(&info)?;
+            execute_fill_order(
+                deps,
+                env,
+                *order,
+                signature,
+                info.sender.to_string(),
+                payment,
+                referrer,
+            )
+        }
+        ExecuteMsg::CancelOrder { nonce } => try_cancel_order(deps, info, nonce),
+        ExecuteMsg::CancelOrdersBelow { nonce } => try_cancel_orders_below(deps, info, nonce),
+        ExecuteMsg::IbcTransferNft {
+            cw721_contract,
+            token_id,
+            channel_id,
+            receiver,
+            timeout,
+        } => try_ibc_transfer_nft(
+            deps,
+            env,
+            info,
+            NftItem {
+                cw721_contract,
+                token_id,
+            },
+            channel_id,
+            receiver,
+            timeout,
+        ),
     }
 }
 
 pub fn receive_cw20(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     match from_binary(&cw20_msg.msg) {
         Ok(Cw20HookMsg::Deposit { owner, amount }) => {
+            // only the tokens actually sent may be credited, and only to their sender
+            if owner != cw20_msg.sender {
+                return Err(ContractError::InvalidOwner {});
+            }
+            if amount != cw20_msg.amount.u128() {
+                return Err(ContractError::InvalidDepositAmount {});
+            }
             execute_cw20_deposit(deps, info, owner, amount)
         }
         Ok(Cw20HookMsg::Purchase {
             token_id,
             cw721_contract,
-        }) => execute_purchase(deps, info, token_id, cw721_contract, cw20_msg),
-        Err(_) => todo!(),
+            referrer,
+        }) => execute_purchase(
+            deps,
+            env,
+            info,
+            token_id,
+            cw721_contract,
+            referrer,
+            cw20_msg,
+        ),
+        Ok(Cw20HookMsg::PurchaseBundle {
+            bundle_id,
+            referrer,
+        }) => {
+            let payment = Asset {
+                info: AssetInfo::Cw20 {
+                    contract: info.sender.to_string(),
+                },
+                amount: cw20_msg.amount.u128(),
+            };
+            execute_purchase_bundle(deps, env, bundle_id, cw20_msg.sender, payment, referrer)
+        }
+        Ok(Cw20HookMsg::FundLoan { loan_id }) => {
+            execute_fund_loan(deps, env, info, loan_id, cw20_msg)
+        }
+        Ok(Cw20HookMsg::RepayLoan { loan_id }) => {
+            execute_repay_loan(deps, env, info, loan_id, cw20_msg)
+        }
+        Ok(Cw20HookMsg::Rent {
+            cw721_contract,
+            token_id,
+            periods,
+        }) => execute_rent(deps, env, info, cw721_contract, token_id, periods, cw20_msg),
+        Ok(Cw20HookMsg::RedeemVault { vault_id }) => {
+            execute_redeem_vault(deps, info, vault_id, cw20_msg)
+        }
+        Ok(Cw20HookMsg::Buyout { vault_id }) => execute_buyout(deps, info, vault_id, cw20_msg),
+        Ok(Cw20HookMsg::ClaimProceeds { vault_id }) => {
+            execute_claim_proceeds(deps, info, vault_id, cw20_msg)
+        }
+        Ok(Cw20HookMsg::RedeemReceipt {}) => execute_redeem_receipt(deps, info, cw20_msg),
+        Ok(Cw20HookMsg::FillOrder {
+            order,
+            signature,
+            referrer,
+        }) => {
+            let payment = Asset {
+                info: AssetInfo::Cw20 {
+                    contract: info.sender.to_string(),
+                },
+                amount: cw20_msg.amount.u128(),
+            };
+            execute_fill_order(
+                deps,
+                env,
+                *order,
+                signature,
+                cw20_msg.sender,
+                payment,
+                referrer,
+            )
+        }
+        Ok(Cw20HookMsg::RedeemVoucher { voucher, signature }) => {
+            let payment = Asset {
+                info: AssetInfo::Cw20 {
+                    contract: info.sender.to_string(),
+                },
+                amount: cw20_msg.amount.u128(),
+            };
+            execute_redeem_voucher(deps, env, *voucher, signature, cw20_msg.sender, payment)
+        }
+        Ok(Cw20HookMsg::Mint {
+            collection_id,
+            allowlist,
+        }) => {
+            let payment = Asset {
+                info: AssetInfo::Cw20 {
+                    contract: info.sender.to_string(),
+                },
+                amount: cw20_msg.amount.u128(),
+            };
+            execute_mint(
+                deps,
+                env,
+                collection_id,
+                cw20_msg.sender,
+                payment,
+                allowlist,
+            )
+        }
+        Err(err) => Err(err.into()),
     }
 }
 
 pub fn receive_cw721(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     cw721_msg: Cw721ReceiveMsg,
 ) -> Result<Response, ContractError> {
@@ -81,11 +461,31 @@ pub fn receive_cw721(
             token_id,
             cw20_contract,
             amount,
-        }) => execute_cw721_deposit(deps, info, owner, token_id, cw20_contract, amount),
-        Err(_) => todo!(),
+            reserved_for,
+            accepted,
+        }) => execute_cw721_deposit(
+            deps,
+            info,
+            owner,
+            token_id,
+            cw20_contract,
+            amount,
+            reserved_for,
+            accepted,
+        ),
+        Ok(Cw721HookMsg::FillSwap { swap_id }) => execute_fill_swap(
+            deps,
+            env,
+            info,
+            swap_id,
+            cw721_msg.sender,
+            cw721_msg.token_id,
+        ),
+        Err(err) => Err(err.into()),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_cw721_deposit(
     deps: DepsMut,
     info: MessageInfo,
@@ -93,11 +493,22 @@ pub fn execute_cw721_deposit(
     token_id: String,
     cw20_contract: String,
     amount: u128,
+    reserved_for: Option<String>,
+    accepted: Vec<Asset>,
 ) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
     let contract_addr = info.sender.clone().to_string();
+    if is_soulbound(deps.as_ref(), &contract_addr, &token_id) {
+        return Err(ContractError::SoulboundToken {});
+    }
+    let reserved_for = reserved_for
+        .map(|buyer| deps.api.addr_validate(&buyer))
+        .transpose()?
+        .map(String::from);
+    validate_accepted(deps.as_ref(), &cw20_contract, &accepted)?;
 
     match CW721_DEPOSITS.load(deps.storage, (&owner, &contract_addr, &token_id)) {
-        Ok(_) => return Err(ContractError::Cw721AlreadyDeposited {}),
+        Ok(_) => Err(ContractError::Cw721AlreadyDeposited {}),
         Err(_) => {
             let deposit = Cw721Deposit {
                 owner: owner.clone(),
@@ -113,17 +524,24 @@ pub fn execute_cw721_deposit(
                 cw721_contract: contract_addr.clone().to_string(),
                 cw20_contract: cw20_contract.clone(),
                 amount,
+                reserved_for: reserved_for.clone(),
+                accepted: accepted.clone(),
             };
 
-            ASKS.save(deps.storage, (&contract_addr.to_string(), &token_id), &ask)?;
+            save_ask(deps.storage, &ask)?;
 
-            Ok(Response::new()
-                .add_attribute("execute", "deposit_cw721")
-                .add_attribute("owner", owner)
-                .add_attribute("cw721_contract", contract_addr)
-                .add_attribute("token_id", token_id)
-                .add_attribute("cw20_addr", cw20_contract)
-                .add_attribute("amount_requested", amount.to_string()))
+            Ok(Response::new().add_event(
+                MarketplaceEvent::List {
+                    collection: contract_addr,
+                    token_id,
+                    seller: owner,
+                    price: Uint128::new(amount),
+                    payment: cw20_contract,
+                    reserved_for,
+                    accepted,
+                }
+                .into(),
+            ))
         }
     }
 }
@@ -138,15 +556,21 @@ pub fn execute_cw20_deposit(
 
     match CW20_DEPOSITS.load(deps.storage, (&owner, &contract_addr)) {
         Ok(mut deposit) => {
-            deposit.amount = deposit.amount.checked_add(amount.clone()).unwrap();
-            deposit.count = deposit.count.checked_add(1).unwrap();
+            deposit.amount = deposit
+                .amount
+                .checked_add(amount)
+                .ok_or(ContractError::Overflow {})?;
+            deposit.count = deposit
+                .count
+                .checked_add(1)
+                .ok_or(ContractError::Overflow {})?;
 
             CW20_DEPOSITS.save(deps.storage, (&owner, &contract_addr), &deposit)?;
         }
         Err(_) => {
             let deposit = Cw20Deposit {
                 owner: owner.clone(),
-                amount: amount.clone(),
+                amount,
                 contract: contract_addr.clone(),
                 count: 1,
             };
@@ -154,47 +578,83 @@ pub fn execute_cw20_deposit(
             CW20_DEPOSITS.save(deps.storage, (&owner, &contract_addr), &deposit)?;
         }
     }
+    add_liability(
+        deps.storage,
+        &Asset {
+            info: AssetInfo::Cw20 {
+                contract: contract_addr.clone(),
+            },
+            amount,
+        },
+    )?;
 
-    Ok(Response::new()
-        .add_attribute("execute", "cw20_deposit")
-        .add_attribute("owner", owner)
-        .add_attribute("amount", amount.to_string())
-        .add_attribute("contract", contract_addr))
+    Ok(Response::new().add_event(
+        MarketplaceEvent::Deposit {
+            owner,
+            payment: contract_addr,
+            amount: Uint128::new(amount),
+        }
+        .into(),
+    ))
 }
 
+/// Withdraws `amount` of the sender's deposit of `cw20_contract` back to the sender.
 pub fn try_withdraw_cw20(
     deps: DepsMut,
     info: MessageInfo,
-    owner: String,
+    cw20_contract: String,
     amount: u128,
 ) -> Result<Response, ContractError> {
-    let contract_addr = info.sender.clone().to_string();
-
-    match CW20_DEPOSITS.load(deps.storage, (&owner, &contract_addr)) {
-        Ok(mut deposit) => {
-            deposit.count = deposit.count.checked_sub(1).unwrap();
-            deposit.amount = deposit.amount.checked_sub(amount.clone()).unwrap();
+    let owner = info.sender.to_string();
+    let asset = Asset {
+        info: AssetInfo::Cw20 {
+            contract: cw20_contract,
+        },
+        amount,
+    };
 
-            CW20_DEPOSITS.save(deps.storage, (&owner, &contract_addr), &deposit)?;
+    debit_deposit(deps.storage, &owner, &asset)?;
+    sub_liability(deps.storage, &asset)?;
 
-            Ok(Response::new()
-                .add_attribute("execute", "withdraw_cw20")
-                .add_attribute("amount", amount.to_string())
-                .add_attribute("from", contract_addr)
-                .add_attribute("to", owner))
-        }
-        Err(_) => Err(ContractError::NoCw20ToWithdraw {}),
-    }
+    Ok(Response::new()
+        .add_message(asset.transfer_msg(&owner)?)
+        .add_event(
+            MarketplaceEvent::Withdraw {
+                owner,
+                payment: asset.info.id().to_string(),
+                amount: Uint128::new(amount),
+            }
+            .into(),
+        ))
 }
 
-pub fn try_deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn try_deposit(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let sender = info.sender.clone().to_string();
-    let funds = info.funds[0].clone();
+    // coins beyond the first would be kept without being credited
+    let funds = match info.funds.as_slice() {
+        [coin] => coin.clone(),
+        _ => return Err(ContractError::InvalidCoin {}),
+    };
+
+    let config = CONFIG.load(deps.storage)?;
+    if config.deposit_receipts {
+        let code_id = config
+            .cw20_code_id
+            .ok_or(ContractError::Cw20CodeIdNotSet {})?;
+        return execute_mint_receipt(deps, env, code_id, sender, funds);
+    }
 
     match DEPOSITS.load(deps.storage, (&sender, &funds.denom)) {
         Ok(mut deposit) => {
-            deposit.amount.amount = deposit.amount.amount.checked_add(funds.amount).unwrap();
-            deposit.count = deposit.count.checked_add(1).unwrap();
+            deposit.amount.amount = deposit
+                .amount
+                .amount
+                .checked_add(funds.amount)
+                .map_err(|_| ContractError::Overflow {})?;
+            deposit.count = deposit
+                .count
+                .checked_add(1)
+                .ok_or(ContractError::Overflow {})?;
 
             DEPOSITS.save(deps.storage, (&sender, &funds.denom), &deposit)?;
         }
@@ -208,10 +668,16 @@ pub fn try_deposit(deps: DepsMut, info: MessageInfo) -> Result<Response, Contrac
             DEPOSITS.save(deps.storage, (&sender, &funds.denom), &deposit)?;
         }
     }
-    Ok(Response::new()
-        .add_attribute("execute", "deposit")
-        .add_attribute("amount", funds.amount.to_string())
-        .add_attribute("denom", funds.denom))
+    add_liability(deps.storage, &native_asset(&funds))?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::Deposit {
+            owner: sender,
+            payment: funds.denom,
+            amount: funds.amount,
+        }
+        .into(),
+    ))
 }
 
 pub fn try_withdraw_deposit(
@@ -220,35 +686,25 @@ pub fn try_withdraw_deposit(
     amount: u128,
     denom: String,
 ) -> Result<Response, ContractError> {
-    let sender = info.sender.clone().to_string();
+    let sender = info.sender.to_string();
+    let asset = Asset {
+        info: AssetInfo::Native { denom },
+        amount,
+    };
 
-    match DEPOSITS.load(deps.storage, (&sender, &denom)) {
-        Ok(mut deposit) => {
-            deposit.count = deposit.count.checked_sub(1).unwrap();
-            deposit.amount.amount = deposit
-                .amount
-                .amount
-                .checked_sub(Uint128::from(amount))
-                .unwrap();
-
-            let msg = BankMsg::Send {
-                to_address: sender.clone(),
-                amount: vec![Coin {
-                    denom: deposit.amount.denom.clone(),
-                    amount: Uint128::new(amount),
-                }],
-            };
+    debit_deposit(deps.storage, &sender, &asset)?;
+    sub_liability(deps.storage, &asset)?;
 
-            DEPOSITS.save(deps.storage, (&sender, &denom), &deposit)?;
-            Ok(Response::new()
-                .add_attribute("execute", "withdraw_deposit")
-                .add_attribute("amount", amount.to_string())
-                .add_attribute("denom", deposit.amount.denom.clone())
-                .add_attribute("to", sender)
-                .add_message(msg))
-        }
-        Err(_) => todo!(),
-    }
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Withdraw {
+                owner: sender.clone(),
+                payment: asset.info.id().to_string(),
+                amount: Uint128::new(amount),
+            }
+            .into(),
+        )
+        .add_message(asset.transfer_msg(&sender)?))
 }
 
 pub fn try_withdraw_cw721(
@@ -259,13 +715,16 @@ pub fn try_withdraw_cw721(
 ) -> Result<Response, ContractError> {
     match CW721_DEPOSITS.load(
         deps.storage,
-        (&info.sender.clone().to_string(), &cw721_contract, &token_id),
+        (info.sender.as_str(), &cw721_contract, &token_id),
     ) {
         Ok(_) => {
+            ensure_not_locked(deps.as_ref(), &cw721_contract, &token_id)?;
+
             CW721_DEPOSITS.remove(
                 deps.storage,
-                (&info.sender.clone().to_string(), &cw721_contract, &token_id),
+                (info.sender.as_str(), &cw721_contract, &token_id),
             );
+            remove_ask(deps.storage, &cw721_contract, &token_id)?;
 
             let exec_msg = nft::contract::ExecuteMsg::TransferNft {
                 recipient: info.sender.clone().to_string(),
@@ -279,142 +738,3558 @@ pub fn try_withdraw_cw721(
             };
 
             Ok(Response::new()
-                .add_attribute("execute", "cw721_withdraw")
-                .add_attribute("token_id", token_id)
-                .add_attribute("receiver_address", info.sender.to_string())
+                .add_event(
+                    MarketplaceEvent::Delist {
+                        collection: cw721_contract,
+                        token_id,
+                        seller: info.sender.to_string(),
+                    }
+                    .into(),
+                )
                 .add_message(msg))
         }
-        Err(_) => return Err(ContractError::NoCw721ToWithdraw {}),
+        Err(_) => Err(ContractError::NoCw721ToWithdraw {}),
     }
 }
 
-pub fn execute_purchase(
+pub fn try_update_config(
     deps: DepsMut,
-    _info: MessageInfo,
-    token_id: String,
-    cw721_contract: String,
-    cw20_msg: Cw20ReceiveMsg,
+    info: MessageInfo,
+    max_batch_size: Option<u32>,
+    cw20_code_id: Option<u64>,
+    nft_code_id: Option<u64>,
+    max_royalty_bps: Option<u64>,
+    deposit_receipts: Option<bool>,
 ) -> Result<Response, ContractError> {
-    //let buyer = info.sender.clone().to_string();
-
-    let original_owner: String;
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    match ASKS.load(deps.storage, (&cw721_contract, &token_id)) {
-        Ok(ask) => {
-            if Uint128::new(ask.amount) != cw20_msg.amount {
-                return Err(ContractError::InvalidBid {});
-            }
+    if let Some(max_batch_size) = max_batch_size {
+        config.max_batch_size = max_batch_size;
+    }
+    if cw20_code_id.is_some() {
+        config.cw20_code_id = cw20_code_id;
+    }
+    if nft_code_id.is_some() {
+        config.nft_code_id = nft_code_id;
+    }
+    if let Some(max_royalty_bps) = max_royalty_bps {
+        if max_royalty_bps > MAX_FEE_BPS {
+            return Err(ContractError::RoyaltyTooHigh { max: MAX_FEE_BPS });
+        }
+        config.max_royalty_bps = max_royalty_bps;
+    }
+    if let Some(deposit_receipts) = deposit_receipts {
+        config.deposit_receipts = deposit_receipts;
+    }
+    if config.deposit_receipts && config.cw20_code_id.is_none() {
+        return Err(ContractError::Cw20CodeIdNotSet {});
+    }
+    CONFIG.save(deps.storage, &config)?;
 
-            original_owner = ask.owner.clone();
+    Ok(Response::new().add_event(
+        MarketplaceEvent::UpdateConfig {
+            max_batch_size: config.max_batch_size,
+            max_royalty_bps: config.max_royalty_bps,
+            deposit_receipts: config.deposit_receipts,
+        }
+        .into(),
+    ))
+}
 
-            let exec_msg = nft::contract::ExecuteMsg::TransferNft {
-                recipient: cw20_msg.sender.clone(),
-                token_id: token_id.clone(),
-            };
-            let msg = WasmMsg::Execute {
-                contract_addr: cw721_contract.clone(),
-                msg: to_binary(&exec_msg)?,
-                funds: vec![],
-            };
-            CW721_DEPOSITS.remove(deps.storage, (&ask.owner, &cw721_contract, &token_id));
-            ASKS.remove(deps.storage, (&cw721_contract, &token_id));
+pub fn try_set_royalties(
+    deps: DepsMut,
+    info: MessageInfo,
+    cw721_contract: String,
+    royalties: Vec<Royalty>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        // collections without a minter, or that are not cw721 contracts, can only be set up by the
+        // admin
+        let minter: StdResult<nft::contract::MinterResponse> = deps
+            .querier
+            .query_wasm_smart(&cw721_contract, &nft::contract::QueryMsg::Minter {});
+        if minter.map_or(true, |res| res.minter != info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
 
-            Ok(Response::new()
-                .add_attribute("execute", "nft_purchase")
-                .add_attribute("token_id", token_id)
-                .add_attribute("from", original_owner)
-                .add_attribute("to", cw20_msg.sender)
-                .add_message(msg))
+    let mut total_bps: u64 = 0;
+    for royalty in &royalties {
+        deps.api.addr_validate(&royalty.recipient)?;
+        if royalty.bps == 0 {
+            return Err(ContractError::ZeroRoyalty {});
         }
-        Err(_) => return Err(ContractError::NoBidsForTokenID {}),
+        total_bps = total_bps
+            .checked_add(royalty.bps)
+            .ok_or(ContractError::Overflow {})?;
+    }
+    if total_bps > config.max_royalty_bps {
+        return Err(ContractError::RoyaltyTooHigh {
+            max: config.max_royalty_bps,
+        });
     }
-}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetCw20Deposit { address } => to_binary(&try_query_cw20_deposit(deps, address)?),
-        QueryMsg::GetDeposits { address } => to_binary(&try_query_deposit(deps, address)?),
-        QueryMsg::GetCw721Deposit { address, contract } => {
-            to_binary(&try_query_cw721_deposit(deps, address, contract)?)
+    if royalties.is_empty() {
+        ROYALTIES.remove(deps.storage, &cw721_contract);
+    } else {
+        ROYALTIES.save(deps.storage, &cw721_contract, &royalties)?;
+    }
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::SetRoyalties {
+            collection: cw721_contract,
+            total_bps,
         }
+        .into(),
+    ))
+}
+
+pub fn try_propose_new_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+    expires_in: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let new_admin = deps.api.addr_validate(&addr)?.to_string();
+    if new_admin == config.admin {
+        return Err(ContractError::SameAdmin {});
+    }
+    // a proposal expiring in the block it is made could never be claimed
+    if expires_in == 0 {
+        return Err(ContractError::ProposalTooShort {});
     }
+    if expires_in > MAX_ADMIN_PROPOSAL_SECONDS {
+        return Err(ContractError::ProposalTooLong {
+            max: MAX_ADMIN_PROPOSAL_SECONDS,
+        });
+    }
+
+    let proposal = AdminProposal {
+        new_admin,
+        expires_at: env.block.time.plus_seconds(expires_in),
+    };
+    ADMIN_PROPOSAL.save(deps.storage, &proposal)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::ProposeAdmin {
+            new_admin: proposal.new_admin,
+            expires_at: proposal.expires_at.seconds(),
+        }
+        .into(),
+    ))
 }
 
-pub fn try_query_deposit(deps: Deps, address: String) -> StdResult<DepositResponse> {
-    let _valid_addr = deps.api.addr_validate(&address)?;
+pub fn try_claim_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let proposal = ADMIN_PROPOSAL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::AdminProposalNotFound {})?;
+    if info.sender != proposal.new_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if env.block.time >= proposal.expires_at {
+        return Err(ContractError::AdminProposalExpired {});
+    }
 
-    let res: StdResult<Vec<_>> = DEPOSITS
-        .prefix(&address)
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect();
+    ADMIN_PROPOSAL.remove(deps.storage);
+    let mut config = CONFIG.load(deps.storage)?;
+    config.admin = proposal.new_admin;
+    CONFIG.save(deps.storage, &config)?;
 
-    let deposits_found = res?;
+    Ok(Response::new().add_event(
+        MarketplaceEvent::ClaimAdmin {
+            admin: config.admin,
+        }
+        .into(),
+    ))
+}
 
-    if deposits_found.len() == 0 {
-        return Err(StdError::generic_err("No deposits found for that address"));
+pub fn try_drop_admin_proposal(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
     }
+    ADMIN_PROPOSAL.remove(deps.storage);
 
-    let mut deposits: Vec<Deposit> = vec![];
+    Ok(Response::new().add_event(
+        MarketplaceEvent::DropAdminProposal {
+            admin: config.admin,
+        }
+        .into(),
+    ))
+}
 
-    for deposit in deposits_found {
-        deposits.push(deposit.1);
+pub fn try_set_referrer(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    share_bps: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if share_bps > MAX_FEE_BPS {
+        return Err(ContractError::InvalidFee { max: MAX_FEE_BPS });
     }
+    let address = deps.api.addr_validate(&address)?.to_string();
+    REFERRERS.save(deps.storage, &address, &share_bps)?;
 
-    Ok(DepositResponse { deposits })
+    Ok(Response::new().add_event(
+        MarketplaceEvent::SetReferrer {
+            referrer: address,
+            share_bps,
+        }
+        .into(),
+    ))
 }
 
-pub fn try_query_cw20_deposit(deps: Deps, address: String) -> StdResult<Cw20DepositResponse> {
-    let _valid_addr = deps.api.addr_validate(&address)?;
+pub fn try_remove_referrer(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !REFERRERS.has(deps.storage, &address) {
+        return Err(ContractError::ReferrerNotFound {});
+    }
+    REFERRERS.remove(deps.storage, &address);
 
-    let wrapped_deposits: StdResult<Vec<_>> = CW20_DEPOSITS
-        .prefix(&address)
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect();
+    Ok(Response::new().add_event(MarketplaceEvent::RemoveReferrer { referrer: address }.into()))
+}
 
-    let deposits = wrapped_deposits?;
+/// Rejects empty batches and batches larger than the configured maximum.
+fn check_batch_size(deps: Deps, len: usize) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
 
-    if deposits.len() == 0 {
-        return Err(StdError::generic_err(
-            "No cw20 deposits exist for that address",
-        ));
+    if len == 0 {
+        return Err(ContractError::EmptyBatch {});
     }
-
-    let mut real_deposits: Vec<Cw20Deposit> = vec![];
-    for deposit in deposits {
-        real_deposits.push(deposit.1);
+    if len > config.max_batch_size as usize {
+        return Err(ContractError::BatchTooLarge {
+            max: config.max_batch_size,
+        });
     }
 
-    Ok(Cw20DepositResponse {
-        deposits: real_deposits,
-    })
+    Ok(())
 }
 
-pub fn try_query_cw721_deposit(
-    deps: Deps,
-    address: String,
-    contract: String,
-) -> StdResult<Cw721DepositResponse> {
-    let _valid_addr = deps.api.addr_validate(&address)?;
-    let _valid_contract_addr = deps.api.addr_validate(&contract)?;
+pub fn try_batch_withdraw_cw721(
+    deps: DepsMut,
+    info: MessageInfo,
+    nfts: Vec<NftItem>,
+) -> Result<Response, ContractError> {
+    check_batch_size(deps.as_ref(), nfts.len())?;
 
-    let res: StdResult<Vec<_>> = CW721_DEPOSITS
-        .prefix((&address, &contract))
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect();
+    let sender = info.sender.to_string();
+    let mut res = Response::new();
 
-    let deposits_found = res?;
+    for nft in nfts {
+        let key = (
+            sender.as_str(),
+            nft.cw721_contract.as_str(),
+            nft.token_id.as_str(),
+        );
+        if !CW721_DEPOSITS.has(deps.storage, key) {
+            return Err(ContractError::NoCw721ToWithdraw {});
+        }
+        ensure_not_locked(deps.as_ref(), &nft.cw721_contract, &nft.token_id)?;
 
-    if deposits_found.len() == 0 {
-        return Err(StdError::generic_err(
-            "No cw721 deposits exist for that address",
-        ));
-    }
+        CW721_DEPOSITS.remove(deps.storage, key);
+        remove_ask(deps.storage, &nft.cw721_contract, &nft.token_id)?;
 
-    let mut deposits: Vec<Cw721Deposit> = vec![];
-    for deposit in deposits_found {
-        deposits.push(deposit.1);
+        let exec_msg = nft::contract::ExecuteMsg::TransferNft {
+            recipient: sender.clone(),
+            token_id: nft.token_id.clone(),
+        };
+
+        res = res
+            .add_message(WasmMsg::Execute {
+                contract_addr: nft.cw721_contract.clone(),
+                msg: to_binary(&exec_msg)?,
+                funds: vec![],
+            })
+            .add_event(
+                MarketplaceEvent::Delist {
+                    collection: nft.cw721_contract,
+                    token_id: nft.token_id,
+                    seller: sender.clone(),
+                }
+                .into(),
+            );
     }
 
-    Ok(Cw721DepositResponse { deposits })
+    Ok(res)
+}
+
+pub fn try_batch_withdraw(
+    deps: DepsMut,
+    info: MessageInfo,
+    withdrawals: Vec<Withdrawal>,
+) -> Result<Response, ContractError> {
+    check_batch_size(deps.as_ref(), withdrawals.len())?;
+
+    let sender = info.sender.to_string();
+    let mut res = Response::new();
+
+    for withdrawal in withdrawals {
+        let asset = match withdrawal {
+            Withdrawal::Native { denom, amount } => Asset {
+                info: AssetInfo::Native { denom },
+                amount,
+            },
+            Withdrawal::Cw20 {
+                cw20_contract,
+                amount,
+            } => Asset {
+                info: AssetInfo::Cw20 {
+                    contract: cw20_contract,
+                },
+                amount,
+            },
+        };
+
+        debit_deposit(deps.storage, &sender, &asset)?;
+        sub_liability(deps.storage, &asset)?;
+        res = res.add_message(asset.transfer_msg(&sender)?).add_event(
+            MarketplaceEvent::Withdraw {
+                owner: sender.clone(),
+                payment: asset.info.id().to_string(),
+                amount: Uint128::new(asset.amount),
+            }
+            .into(),
+        );
+    }
+
+    Ok(res)
+}
+
+pub fn try_batch_update_asks(
+    deps: DepsMut,
+    info: MessageInfo,
+    asks: Vec<AskUpdate>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    check_batch_size(deps.as_ref(), asks.len())?;
+
+    let sender = info.sender.to_string();
+    let mut res = Response::new();
+
+    for update in asks {
+        // Only the depositor of the NFT may price it; this also re-lists a token whose ask was removed
+        let key = (
+            sender.as_str(),
+            update.cw721_contract.as_str(),
+            update.token_id.as_str(),
+        );
+        if !CW721_DEPOSITS.has(deps.storage, key) {
+            return Err(ContractError::InvalidOwner {});
+        }
+        ensure_not_locked(deps.as_ref(), &update.cw721_contract, &update.token_id)?;
+        let reserved_for = update
+            .reserved_for
+            .map(|buyer| deps.api.addr_validate(&buyer))
+            .transpose()?
+            .map(String::from);
+        validate_accepted(deps.as_ref(), &update.cw20_contract, &update.accepted)?;
+
+        let ask = Offer {
+            owner: sender.clone(),
+            token_id: update.token_id.clone(),
+            cw721_contract: update.cw721_contract.clone(),
+            cw20_contract: update.cw20_contract.clone(),
+            amount: update.amount,
+            reserved_for: reserved_for.clone(),
+            accepted: update.accepted.clone(),
+        };
+        save_ask(deps.storage, &ask)?;
+
+        res = res.add_event(
+            MarketplaceEvent::List {
+                collection: update.cw721_contract,
+                token_id: update.token_id,
+                seller: sender.clone(),
+                price: Uint128::new(update.amount),
+                payment: update.cw20_contract,
+                reserved_for,
+                accepted: update.accepted,
+            }
+            .into(),
+        );
+    }
+
+    Ok(res)
+}
+
+pub fn try_create_bundle(
+    deps: DepsMut,
+    info: MessageInfo,
+    nfts: Vec<NftItem>,
+    price: Asset,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    check_batch_size(deps.as_ref(), nfts.len())?;
+
+    let seller = info.sender.to_string();
+    let bundle_id = BUNDLE_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    BUNDLE_COUNT.save(deps.storage, &bundle_id)?;
+
+    for nft in &nfts {
+        let key = (
+            seller.as_str(),
+            nft.cw721_contract.as_str(),
+            nft.token_id.as_str(),
+        );
+        if !CW721_DEPOSITS.has(deps.storage, key) {
+            return Err(ContractError::InvalidOwner {});
+        }
+        // also rejects the same token appearing twice in `nfts`
+        ensure_not_locked(deps.as_ref(), &nft.cw721_contract, &nft.token_id)?;
+
+        remove_ask(deps.storage, &nft.cw721_contract, &nft.token_id)?;
+        ESCROWED_NFTS.save(
+            deps.storage,
+            (&nft.cw721_contract, &nft.token_id),
+            &NftLock::Bundle { bundle_id },
+        )?;
+    }
+
+    let bundle = Bundle {
+        bundle_id,
+        seller: seller.clone(),
+        nfts,
+        price,
+    };
+    BUNDLES.save(deps.storage, bundle_id, &bundle)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::ListBundle {
+            bundle_id,
+            seller,
+            price: Uint128::new(bundle.price.amount),
+            payment: bundle.price.info.id().to_string(),
+            count: bundle.nfts.len() as u64,
+        }
+        .into(),
+    ))
+}
+
+/// Removes a bundle together with the escrow and deposit records of its NFTs.
+fn close_bundle(storage: &mut dyn Storage, bundle: &Bundle) {
+    BUNDLES.remove(storage, bundle.bundle_id);
+    for nft in &bundle.nfts {
+        ESCROWED_NFTS.remove(storage, (&nft.cw721_contract, &nft.token_id));
+        CW721_DEPOSITS.remove(
+            storage,
+            (&bundle.seller, &nft.cw721_contract, &nft.token_id),
+        );
+    }
+}
+
+/// Releases the NFTs of a bundle from escrow and transfers them to `recipient`.
+fn release_bundle(
+    storage: &mut dyn Storage,
+    bundle: &Bundle,
+    recipient: &str,
+) -> StdResult<Vec<CosmosMsg>> {
+    close_bundle(storage, bundle);
+    nft_transfer_msgs(&bundle.nfts, recipient)
+}
+
+pub fn try_cancel_bundle(
+    deps: DepsMut,
+    info: MessageInfo,
+    bundle_id: u64,
+) -> Result<Response, ContractError> {
+    let bundle = BUNDLES
+        .may_load(deps.storage, bundle_id)?
+        .ok_or(ContractError::BundleNotFound {})?;
+    if info.sender != bundle.seller {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let msgs = release_bundle(deps.storage, &bundle, &bundle.seller)?;
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::DelistBundle {
+                bundle_id,
+                seller: bundle.seller,
+            }
+            .into(),
+        )
+        .add_messages(msgs))
+}
+
+/// Sells a bundle to `buyer`. The price is split evenly over the NFTs of the bundle, the first
+/// taking any remainder, and each NFT is settled as a sale of its own, so that fees, referrals,
+/// royalties and sale records work as for a single purchase.
+pub fn execute_purchase_bundle(
+    mut deps: DepsMut,
+    env: Env,
+    bundle_id: u64,
+    buyer: String,
+    payment: Asset,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let bundle = BUNDLES
+        .may_load(deps.storage, bundle_id)?
+        .ok_or(ContractError::BundleNotFound {})?;
+    if payment != bundle.price {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let share_bps = referral_bps(deps.storage, referrer.as_deref(), &buyer)?;
+    close_bundle(deps.storage, &bundle);
+
+    let count = bundle.nfts.len() as u128;
+    let mut fee = 0;
+    let mut referral_fee = 0;
+    let mut sales = Response::new();
+    for (i, nft) in bundle.nfts.iter().enumerate() {
+        let mut price = Asset {
+            info: bundle.price.info.clone(),
+            amount: bundle.price.amount / count,
+        };
+        if i == 0 {
+            price.amount += bundle.price.amount % count;
+        }
+        let (_, share_fee) = split_fee(&config, &price);
+        fee += share_fee.amount;
+        referral_fee += referral_amount(&share_fee, share_bps);
+
+        let sale = complete_sale(
+            deps.branch(),
+            &env,
+            nft.cw721_contract.clone(),
+            nft.token_id.clone(),
+            bundle.seller.clone(),
+            buyer.clone(),
+            price,
+            referrer.clone(),
+        )?;
+        sales = sales.add_submessages(sale.messages).add_events(sale.events);
+    }
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::SellBundle {
+                bundle_id,
+                seller: bundle.seller,
+                buyer,
+                price: Uint128::new(bundle.price.amount),
+                payment: bundle.price.info.id().to_string(),
+                fee: Uint128::new(fee),
+                referrer,
+                referral_fee: Uint128::new(referral_fee),
+            }
+            .into(),
+        )
+        .add_submessages(sales.messages)
+        .add_events(sales.events))
+}
+
+pub fn try_create_swap(
+    deps: DepsMut,
+    info: MessageInfo,
+    offered: Vec<NftItem>,
+    wanted: Vec<NftItem>,
+    maker_top_up: Option<Asset>,
+    taker_top_up: Option<Asset>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    check_batch_size(deps.as_ref(), offered.len())?;
+    check_batch_size(deps.as_ref(), wanted.len())?;
+    // a repeated NFT could never be received twice, so the swap would never settle
+    for nfts in [&offered, &wanted] {
+        if nfts
+            .iter()
+            .enumerate()
+            .any(|(i, nft)| nfts[..i].contains(nft))
+        {
+            return Err(ContractError::DuplicateNft {});
+        }
+    }
+
+    let maker = info.sender.to_string();
+    let swap_id = SWAP_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    SWAP_COUNT.save(deps.storage, &swap_id)?;
+
+    for nft in &offered {
+        let key = (
+            maker.as_str(),
+            nft.cw721_contract.as_str(),
+            nft.token_id.as_str(),
+        );
+        if !CW721_DEPOSITS.has(deps.storage, key) {
+            return Err(ContractError::InvalidOwner {});
+        }
+        ensure_not_locked(deps.as_ref(), &nft.cw721_contract, &nft.token_id)?;
+
+        remove_ask(deps.storage, &nft.cw721_contract, &nft.token_id)?;
+        ESCROWED_NFTS.save(
+            deps.storage,
+            (&nft.cw721_contract, &nft.token_id),
+            &NftLock::Swap { swap_id },
+        )?;
+    }
+
+    // the maker's top-up is escrowed out of their deposits until the swap settles or is cancelled
+    if let Some(top_up) = &maker_top_up {
+        debit_deposit(deps.storage, &maker, top_up)?;
+    }
+
+    let swap = Swap {
+        swap_id,
+        maker: maker.clone(),
+        offered,
+        wanted,
+        maker_top_up,
+        taker_top_up,
+    };
+    SWAPS.save(deps.storage, swap_id, &swap)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::CreateSwap {
+            swap_id,
+            seller: maker,
+        }
+        .into(),
+    ))
+}
+
+/// Builds the transfers of `nfts` held by the marketplace to `recipient`.
+pub(crate) fn nft_transfer_msgs(nfts: &[NftItem], recipient: &str) -> StdResult<Vec<CosmosMsg>> {
+    nfts.iter()
+        .map(|nft| {
+            let exec_msg = nft::contract::ExecuteMsg::TransferNft {
+                recipient: recipient.to_string(),
+                token_id: nft.token_id.clone(),
+            };
+            Ok(WasmMsg::Execute {
+                contract_addr: nft.cw721_contract.clone(),
+                msg: to_binary(&exec_msg)?,
+                funds: vec![],
+            }
+            .into())
+        })
+        .collect()
+}
+
+/// Removes a swap together with the escrow records of the maker's NFTs and any received fills.
+/// Returns the taker, if any NFT was received, and the received NFTs.
+fn close_swap(storage: &mut dyn Storage, swap: &Swap) -> StdResult<(Option<String>, Vec<NftItem>)> {
+    SWAPS.remove(storage, swap.swap_id);
+    for nft in &swap.offered {
+        ESCROWED_NFTS.remove(storage, (&nft.cw721_contract, &nft.token_id));
+        CW721_DEPOSITS.remove(storage, (&swap.maker, &nft.cw721_contract, &nft.token_id));
+    }
+
+    take_swap_fills(storage, swap)
+}
+
+/// Removes and returns the NFTs received so far for a swap, along with who sent them.
+fn take_swap_fills(
+    storage: &mut dyn Storage,
+    swap: &Swap,
+) -> StdResult<(Option<String>, Vec<NftItem>)> {
+    let mut taker = None;
+    let mut received = vec![];
+    for nft in &swap.wanted {
+        let key = (
+            swap.swap_id,
+            nft.cw721_contract.as_str(),
+            nft.token_id.as_str(),
+        );
+        if let Some(sender) = SWAP_FILLS.may_load(storage, key)? {
+            SWAP_FILLS.remove(storage, key);
+            taker = Some(sender);
+            received.push(nft.clone());
+        }
+    }
+    SWAP_FILL_STARTED.remove(storage, swap.swap_id);
+    Ok((taker, received))
+}
+
+/// Receives one wanted NFT of a swap from `taker`, settling the swap once all have arrived.
+/// While a taker's fill is incomplete other takers are turned away, until the fill times out and
+/// the next taker's NFT returns the stale fill to its sender.
+pub fn execute_fill_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    swap_id: u64,
+    taker: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let swap = SWAPS
+        .may_load(deps.storage, swap_id)?
+        .ok_or(ContractError::SwapNotFound {})?;
+
+    let received = NftItem {
+        cw721_contract: info.sender.to_string(),
+        token_id,
+    };
+    let received_key = (
+        swap_id,
+        received.cw721_contract.as_str(),
+        received.token_id.as_str(),
+    );
+    if !swap.wanted.contains(&received) || SWAP_FILLS.has(deps.storage, received_key) {
+        return Err(ContractError::NftNotWanted {});
+    }
+
+    // every wanted NFT has to come from the same counterparty
+    let mut filled = 0;
+    let mut stale_fill = false;
+    for nft in &swap.wanted {
+        let key = (swap_id, nft.cw721_contract.as_str(), nft.token_id.as_str());
+        if let Some(sender) = SWAP_FILLS.may_load(deps.storage, key)? {
+            if sender != taker {
+                let started = SWAP_FILL_STARTED.load(deps.storage, swap_id)?;
+                if env.block.time < started.plus_seconds(SWAP_FILL_TIMEOUT_SECONDS) {
+                    return Err(ContractError::Unauthorized {});
+                }
+                stale_fill = true;
+                break;
+            }
+            filled += 1;
+        }
+    }
+    let mut refunds = vec![];
+    if stale_fill {
+        let (stale_taker, returned) = take_swap_fills(deps.storage, &swap)?;
+        if let Some(stale_taker) = stale_taker {
+            refunds = nft_transfer_msgs(&returned, &stale_taker)?;
+        }
+        filled = 0;
+    }
+    if filled == 0 {
+        SWAP_FILL_STARTED.save(deps.storage, swap_id, &env.block.time)?;
+    }
+    SWAP_FILLS.save(deps.storage, received_key, &taker)?;
+    filled += 1;
+
+    let settled = filled == swap.wanted.len();
+    let res = Response::new().add_messages(refunds).add_event(
+        MarketplaceEvent::FillSwap {
+            swap_id,
+            collection: received.cw721_contract,
+            token_id: received.token_id,
+            buyer: taker.clone(),
+            settled,
+        }
+        .into(),
+    );
+
+    if !settled {
+        return Ok(res);
+    }
+
+    // last wanted NFT received: settle both sides
+    close_swap(deps.storage, &swap)?;
+    if let Some(top_up) = &swap.maker_top_up {
+        credit_deposit(deps.storage, &taker, top_up)?;
+    }
+    if let Some(top_up) = &swap.taker_top_up {
+        debit_deposit(deps.storage, &taker, top_up)?;
+        credit_deposit(deps.storage, &swap.maker, top_up)?;
+    }
+
+    Ok(res
+        .add_messages(nft_transfer_msgs(&swap.offered, &taker)?)
+        .add_messages(nft_transfer_msgs(&swap.wanted, &swap.maker)?))
+}
+
+pub fn try_cancel_swap(
+    deps: DepsMut,
+    info: MessageInfo,
+    swap_id: u64,
+) -> Result<Response, ContractError> {
+    let swap = SWAPS
+        .may_load(deps.storage, swap_id)?
+        .ok_or(ContractError::SwapNotFound {})?;
+    if info.sender != swap.maker {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let (taker, received) = close_swap(deps.storage, &swap)?;
+    if let Some(top_up) = &swap.maker_top_up {
+        credit_deposit(deps.storage, &swap.maker, top_up)?;
+    }
+
+    let mut res = Response::new()
+        .add_event(
+            MarketplaceEvent::CancelSwap {
+                swap_id,
+                seller: swap.maker.clone(),
+            }
+            .into(),
+        )
+        .add_messages(nft_transfer_msgs(&swap.offered, &swap.maker)?);
+    if let Some(taker) = taker {
+        res = res.add_messages(nft_transfer_msgs(&received, &taker)?);
+    }
+
+    Ok(res)
+}
+
+pub fn try_withdraw_swap_fill(
+    deps: DepsMut,
+    info: MessageInfo,
+    swap_id: u64,
+) -> Result<Response, ContractError> {
+    let swap = SWAPS
+        .may_load(deps.storage, swap_id)?
+        .ok_or(ContractError::SwapNotFound {})?;
+
+    let (taker, received) = take_swap_fills(deps.storage, &swap)?;
+    if taker.as_deref() != Some(info.sender.as_str()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::WithdrawSwapFill {
+                swap_id,
+                buyer: info.sender.to_string(),
+            }
+            .into(),
+        )
+        .add_messages(nft_transfer_msgs(&received, info.sender.as_str())?))
+}
+
+pub fn try_request_loan(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral: NftItem,
+    cw20_contract: String,
+    principal: u128,
+    interest: u128,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    if principal == 0 {
+        return Err(ContractError::ZeroPrincipal {});
+    }
+    principal
+        .checked_add(interest)
+        .ok_or(ContractError::Overflow {})?;
+
+    let borrower = info.sender.to_string();
+    let key = (
+        borrower.as_str(),
+        collateral.cw721_contract.as_str(),
+        collateral.token_id.as_str(),
+    );
+    if !CW721_DEPOSITS.has(deps.storage, key) {
+        return Err(ContractError::InvalidOwner {});
+    }
+    ensure_not_locked(
+        deps.as_ref(),
+        &collateral.cw721_contract,
+        &collateral.token_id,
+    )?;
+    let cw20_contract = deps.api.addr_validate(&cw20_contract)?.to_string();
+
+    let loan_id = LOAN_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    LOAN_COUNT.save(deps.storage, &loan_id)?;
+
+    remove_ask(
+        deps.storage,
+        &collateral.cw721_contract,
+        &collateral.token_id,
+    )?;
+    ESCROWED_NFTS.save(
+        deps.storage,
+        (&collateral.cw721_contract, &collateral.token_id),
+        &NftLock::Loan { loan_id },
+    )?;
+
+    let loan = Loan {
+        loan_id,
+        borrower: borrower.clone(),
+        collateral: collateral.clone(),
+        cw20_contract: cw20_contract.clone(),
+        principal,
+        interest,
+        duration,
+        lender: None,
+        expires_at: None,
+    };
+    LOANS.save(deps.storage, loan_id, &loan)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::RequestLoan {
+            loan_id,
+            collection: collateral.cw721_contract,
+            token_id: collateral.token_id,
+            borrower,
+            principal: Uint128::new(principal),
+            interest: Uint128::new(interest),
+            payment: cw20_contract,
+            duration,
+        }
+        .into(),
+    ))
+}
+
+pub fn try_cancel_loan(
+    deps: DepsMut,
+    info: MessageInfo,
+    loan_id: u64,
+) -> Result<Response, ContractError> {
+    let loan = LOANS
+        .may_load(deps.storage, loan_id)?
+        .ok_or(ContractError::LoanNotFound {})?;
+    if info.sender != loan.borrower {
+        return Err(ContractError::Unauthorized {});
+    }
+    if loan.lender.is_some() {
+        return Err(ContractError::LoanAlreadyFunded {});
+    }
+
+    LOANS.remove(deps.storage, loan_id);
+    ESCROWED_NFTS.remove(
+        deps.storage,
+        (&loan.collateral.cw721_contract, &loan.collateral.token_id),
+    );
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::CancelLoan {
+            loan_id,
+            borrower: loan.borrower,
+        }
+        .into(),
+    ))
+}
+
+pub fn execute_fund_loan(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    loan_id: u64,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let mut loan = LOANS
+        .may_load(deps.storage, loan_id)?
+        .ok_or(ContractError::LoanNotFound {})?;
+    if loan.lender.is_some() {
+        return Err(ContractError::LoanAlreadyFunded {});
+    }
+    if info.sender != loan.cw20_contract || cw20_msg.amount.u128() != loan.principal {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    let expires_at = env.block.time.plus_seconds(loan.duration);
+    loan.lender = Some(cw20_msg.sender.clone());
+    loan.expires_at = Some(expires_at);
+    LOANS.save(deps.storage, loan_id, &loan)?;
+
+    let principal = Asset {
+        info: AssetInfo::Cw20 {
+            contract: loan.cw20_contract.clone(),
+        },
+        amount: loan.principal,
+    };
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::FundLoan {
+                loan_id,
+                lender: cw20_msg.sender,
+                principal: cw20_msg.amount,
+                payment: loan.cw20_contract.clone(),
+                expires_at: expires_at.seconds(),
+            }
+            .into(),
+        )
+        .add_message(principal.transfer_msg(&loan.borrower)?))
+}
+
+pub fn execute_repay_loan(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    loan_id: u64,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let loan = LOANS
+        .may_load(deps.storage, loan_id)?
+        .ok_or(ContractError::LoanNotFound {})?;
+    let (lender, expires_at) = funded_terms(&loan)?;
+    if env.block.time >= expires_at {
+        return Err(ContractError::LoanExpired {});
+    }
+    let owed = loan
+        .principal
+        .checked_add(loan.interest)
+        .ok_or(ContractError::Overflow {})?;
+    if info.sender != loan.cw20_contract || cw20_msg.amount.u128() != owed {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    let repayment = Asset {
+        info: AssetInfo::Cw20 {
+            contract: loan.cw20_contract.clone(),
+        },
+        amount: owed,
+    };
+    let mut msgs = vec![repayment.transfer_msg(&lender)?];
+    msgs.extend(release_collateral(deps.storage, &loan, &loan.borrower)?);
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::RepayLoan {
+                loan_id,
+                borrower: loan.borrower,
+                lender,
+                amount: Uint128::new(owed),
+                payment: loan.cw20_contract,
+            }
+            .into(),
+        )
+        .add_messages(msgs))
+}
+
+pub fn try_claim_collateral(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    loan_id: u64,
+) -> Result<Response, ContractError> {
+    let loan = LOANS
+        .may_load(deps.storage, loan_id)?
+        .ok_or(ContractError::LoanNotFound {})?;
+    let (lender, expires_at) = funded_terms(&loan)?;
+    if info.sender != lender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if env.block.time < expires_at {
+        return Err(ContractError::LoanNotExpired {});
+    }
+
+    let msgs = release_collateral(deps.storage, &loan, &lender)?;
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::ClaimCollateral {
+                loan_id,
+                collection: loan.collateral.cw721_contract,
+                token_id: loan.collateral.token_id,
+                lender,
+            }
+            .into(),
+        )
+        .add_messages(msgs))
+}
+
+/// The lender and expiry of a funded loan.
+fn funded_terms(loan: &Loan) -> Result<(String, Timestamp), ContractError> {
+    match (&loan.lender, loan.expires_at) {
+        (Some(lender), Some(expires_at)) => Ok((lender.clone(), expires_at)),
+        _ => Err(ContractError::LoanNotFunded {}),
+    }
+}
+
+/// Closes a loan and transfers its collateral out of the marketplace to `recipient`.
+fn release_collateral(
+    storage: &mut dyn Storage,
+    loan: &Loan,
+    recipient: &str,
+) -> StdResult<Vec<CosmosMsg>> {
+    let nft = &loan.collateral;
+    LOANS.remove(storage, loan.loan_id);
+    ESCROWED_NFTS.remove(storage, (&nft.cw721_contract, &nft.token_id));
+    CW721_DEPOSITS.remove(
+        storage,
+        (&loan.borrower, &nft.cw721_contract, &nft.token_id),
+    );
+
+    nft_transfer_msgs(std::slice::from_ref(nft), recipient)
+}
+
+pub fn try_list_rental(
+    deps: DepsMut,
+    info: MessageInfo,
+    cw721_contract: String,
+    token_id: String,
+    cw20_contract: String,
+    price_per_period: u128,
+    period: u64,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    if price_per_period == 0 || period == 0 {
+        return Err(ContractError::InvalidRentalTerms {});
+    }
+    let owner = info.sender.to_string();
+    if !CW721_DEPOSITS.has(deps.storage, (&owner, &cw721_contract, &token_id)) {
+        return Err(ContractError::InvalidOwner {});
+    }
+    ensure_not_locked(deps.as_ref(), &cw721_contract, &token_id)?;
+
+    remove_ask(deps.storage, &cw721_contract, &token_id)?;
+    ESCROWED_NFTS.save(
+        deps.storage,
+        (&cw721_contract, &token_id),
+        &NftLock::Rental {},
+    )?;
+
+    let rental = Rental {
+        owner: owner.clone(),
+        cw721_contract: cw721_contract.clone(),
+        token_id: token_id.clone(),
+        cw20_contract: deps.api.addr_validate(&cw20_contract)?.to_string(),
+        price_per_period,
+        period,
+        renter: None,
+        expires_at: None,
+    };
+    RENTALS.save(deps.storage, (&cw721_contract, &token_id), &rental)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::ListRental {
+            collection: cw721_contract,
+            token_id,
+            seller: owner,
+            price: Uint128::new(price_per_period),
+            payment: rental.cw20_contract,
+            period,
+        }
+        .into(),
+    ))
+}
+
+pub fn execute_rent(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw721_contract: String,
+    token_id: String,
+    periods: u64,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let mut rental = RENTALS
+        .may_load(deps.storage, (&cw721_contract, &token_id))?
+        .ok_or(ContractError::RentalNotFound {})?;
+    if rental.current_user(env.block.time).is_some() {
+        return Err(ContractError::RentalActive {});
+    }
+    let price = rental
+        .price_per_period
+        .checked_mul(periods as u128)
+        .ok_or(ContractError::Overflow {})?;
+    if periods == 0 || info.sender != rental.cw20_contract || cw20_msg.amount.u128() != price {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    let expires_at = rental
+        .period
+        .checked_mul(periods)
+        .and_then(|seconds| seconds.checked_mul(1_000_000_000))
+        .and_then(|nanos| env.block.time.nanos().checked_add(nanos))
+        .map(Timestamp::from_nanos)
+        .ok_or(ContractError::Overflow {})?;
+    rental.renter = Some(cw20_msg.sender.clone());
+    rental.expires_at = Some(expires_at);
+    RENTALS.save(deps.storage, (&cw721_contract, &token_id), &rental)?;
+
+    let payment = Asset {
+        info: AssetInfo::Cw20 {
+            contract: rental.cw20_contract.clone(),
+        },
+        amount: price,
+    };
+    let config = CONFIG.load(deps.storage)?;
+    let (payout, fee) = split_fee(&config, &payment);
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Rent {
+                collection: cw721_contract,
+                token_id,
+                seller: rental.owner.clone(),
+                buyer: cw20_msg.sender,
+                price: Uint128::new(price),
+                payment: rental.cw20_contract.clone(),
+                fee: Uint128::new(fee.amount),
+                expires_at: expires_at.seconds(),
+            }
+            .into(),
+        )
+        .add_messages(payout_msgs(&config, &rental.owner, &payout, &fee)?))
+}
+
+pub fn try_reclaim_rental(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw721_contract: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let rental = RENTALS
+        .may_load(deps.storage, (&cw721_contract, &token_id))?
+        .ok_or(ContractError::RentalNotFound {})?;
+    if info.sender != rental.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if rental.current_user(env.block.time).is_some() {
+        return Err(ContractError::RentalActive {});
+    }
+
+    RENTALS.remove(deps.storage, (&cw721_contract, &token_id));
+    ESCROWED_NFTS.remove(deps.storage, (&cw721_contract, &token_id));
+    CW721_DEPOSITS.remove(deps.storage, (&rental.owner, &cw721_contract, &token_id));
+
+    let nft = NftItem {
+        cw721_contract: cw721_contract.clone(),
+        token_id: token_id.clone(),
+    };
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::ReclaimRental {
+                collection: cw721_contract,
+                token_id,
+                seller: rental.owner.clone(),
+            }
+            .into(),
+        )
+        .add_messages(nft_transfer_msgs(&[nft], &rental.owner)?))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_fractionalize(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft: NftItem,
+    name: String,
+    symbol: String,
+    supply: u128,
+    buyout_token: String,
+    buyout_price: u128,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    if supply == 0 || buyout_price == 0 {
+        return Err(ContractError::InvalidVaultTerms {});
+    }
+    let curator = info.sender.to_string();
+    let key = (
+        curator.as_str(),
+        nft.cw721_contract.as_str(),
+        nft.token_id.as_str(),
+    );
+    if !CW721_DEPOSITS.has(deps.storage, key) {
+        return Err(ContractError::InvalidOwner {});
+    }
+    ensure_not_locked(deps.as_ref(), &nft.cw721_contract, &nft.token_id)?;
+    let code_id = CONFIG
+        .load(deps.storage)?
+        .cw20_code_id
+        .ok_or(ContractError::Cw20CodeIdNotSet {})?;
+
+    let vault_id = VAULT_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    VAULT_COUNT.save(deps.storage, &vault_id)?;
+
+    remove_ask(deps.storage, &nft.cw721_contract, &nft.token_id)?;
+    ESCROWED_NFTS.save(
+        deps.storage,
+        (&nft.cw721_contract, &nft.token_id),
+        &NftLock::Vault { vault_id },
+    )?;
+
+    // the share token address is filled in by the instantiate reply
+    let vault = Vault {
+        vault_id,
+        curator: curator.clone(),
+        nft: nft.clone(),
+        share_token: String::new(),
+        supply,
+        buyout_token: deps.api.addr_validate(&buyout_token)?.to_string(),
+        buyout_price,
+        buyout: None,
+    };
+    PENDING_VAULT.save(deps.storage, &vault)?;
+
+    let init_msg = cw20_base::msg::InstantiateMsg {
+        name,
+        symbol,
+        decimals: 6,
+        initial_balances: vec![Cw20Coin {
+            address: curator.clone(),
+            amount: Uint128::new(supply),
+        }],
+        mint: None,
+        marketing: None,
+    };
+    let msg = WasmMsg::Instantiate {
+        admin: Some(env.contract.address.to_string()),
+        code_id,
+        msg: to_binary(&init_msg)?,
+        funds: vec![],
+        label: format!("vault {} shares", vault_id),
+    };
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Fractionalize {
+                vault_id,
+                collection: nft.cw721_contract,
+                token_id: nft.token_id,
+                seller: curator,
+                supply: Uint128::new(supply),
+            }
+            .into(),
+        )
+        .add_submessage(SubMsg::reply_on_success(
+            msg,
+            INSTANTIATE_SHARE_TOKEN_REPLY_ID,
+        )))
+}
+
+/// Mints receipt tokens for a native deposit, instantiating the receipt token of the denom
+/// on its first deposit.
+fn execute_mint_receipt(
+    deps: DepsMut,
+    env: Env,
+    code_id: u64,
+    recipient: String,
+    funds: Coin,
+) -> Result<Response, ContractError> {
+    // the coins are held for whoever holds the receipts
+    add_liability(deps.storage, &native_asset(&funds))?;
+
+    let res = Response::new().add_event(
+        MarketplaceEvent::Deposit {
+            owner: recipient.clone(),
+            payment: funds.denom.clone(),
+            amount: funds.amount,
+        }
+        .into(),
+    );
+
+    if let Some(receipt_token) = RECEIPT_TOKENS.may_load(deps.storage, &funds.denom)? {
+        let msg = WasmMsg::Execute {
+            contract_addr: receipt_token,
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient,
+                amount: funds.amount,
+            })?,
+            funds: vec![],
+        };
+        return Ok(res.add_message(msg));
+    }
+
+    PENDING_RECEIPT_DENOM.save(deps.storage, &funds.denom)?;
+    let init_msg = cw20_base::msg::InstantiateMsg {
+        name: format!("{} receipt", funds.denom),
+        symbol: receipt_symbol(&funds.denom),
+        decimals: 6,
+        initial_balances: vec![Cw20Coin {
+            address: recipient,
+            amount: funds.amount,
+        }],
+        mint: Some(MinterResponse {
+            minter: env.contract.address.to_string(),
+            cap: None,
+        }),
+        marketing: None,
+    };
+    let msg = WasmMsg::Instantiate {
+        admin: Some(env.contract.address.to_string()),
+        code_id,
+        msg: to_binary(&init_msg)?,
+        funds: vec![],
+        label: format!("{} receipt", funds.denom),
+    };
+
+    Ok(res.add_submessage(SubMsg::reply_on_success(
+        msg,
+        INSTANTIATE_RECEIPT_TOKEN_REPLY_ID,
+    )))
+}
+
+/// A valid cw20 symbol for the receipt of `denom`, e.g. `RUJUNO` for `ujuno`.
+fn receipt_symbol(denom: &str) -> String {
+    let letters: String = denom.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+    let mut symbol = format!("R{}", letters.to_uppercase());
+    symbol.truncate(12);
+    while symbol.len() < 3 {
+        symbol.push('-');
+    }
+    symbol
+}
+
+fn reply_instantiate_receipt_token(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let res =
+        parse_reply_instantiate_data(msg).map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let denom = PENDING_RECEIPT_DENOM.load(deps.storage)?;
+    PENDING_RECEIPT_DENOM.remove(deps.storage);
+    RECEIPT_TOKENS.save(deps.storage, &denom, &res.contract_address)?;
+    RECEIPT_DENOMS.save(deps.storage, &res.contract_address, &denom)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate_receipt_token")
+        .add_attribute("denom", denom)
+        .add_attribute("receipt_token", res.contract_address))
+}
+
+pub fn execute_redeem_receipt(
+    deps: DepsMut,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let receipt_token = info.sender.to_string();
+    let denom = RECEIPT_DENOMS
+        .may_load(deps.storage, &receipt_token)?
+        .ok_or(ContractError::InvalidPayment {})?;
+
+    let burn = WasmMsg::Execute {
+        contract_addr: receipt_token,
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: cw20_msg.amount,
+        })?,
+        funds: vec![],
+    };
+    let payout = Asset {
+        info: AssetInfo::Native {
+            denom: denom.clone(),
+        },
+        amount: cw20_msg.amount.u128(),
+    };
+    sub_liability(deps.storage, &payout)?;
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Withdraw {
+                owner: cw20_msg.sender.clone(),
+                payment: denom,
+                amount: cw20_msg.amount,
+            }
+            .into(),
+        )
+        .add_message(burn)
+        .add_message(payout.transfer_msg(&cw20_msg.sender)?))
+}
+
+fn reply_instantiate_share_token(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let res =
+        parse_reply_instantiate_data(msg).map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let mut vault = PENDING_VAULT.load(deps.storage)?;
+    PENDING_VAULT.remove(deps.storage);
+    vault.share_token = res.contract_address;
+    VAULTS.save(deps.storage, vault.vault_id, &vault)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate_share_token")
+        .add_attribute("vault_id", vault.vault_id.to_string())
+        .add_attribute("share_token", vault.share_token))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_create_collection(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    symbol: String,
+    mint_price: Asset,
+    max_supply: u64,
+    start_time: Timestamp,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let code_id = CONFIG
+        .load(deps.storage)?
+        .nft_code_id
+        .ok_or(ContractError::NftCodeIdNotSet {})?;
+    if let AssetInfo::Cw20 { contract } = &mint_price.info {
+        deps.api.addr_validate(contract)?;
+    }
+
+    let collection_id = COLLECTION_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    COLLECTION_COUNT.save(deps.storage, &collection_id)?;
+
+    // the cw721 address is filled in by the instantiate reply
+    let collection = Collection {
+        collection_id,
+        creator: info.sender.to_string(),
+        cw721_contract: String::new(),
+        mint_price,
+        max_supply,
+        minted: 0,
+        start_time,
+    };
+    PENDING_COLLECTION.save(deps.storage, &collection)?;
+
+    let init_msg = nft::contract::InstantiateMsg {
+        name,
+        symbol,
+        minter: env.contract.address.to_string(),
+        soulbound: false,
+    };
+    let msg = WasmMsg::Instantiate {
+        admin: Some(env.contract.address.to_string()),
+        code_id,
+        msg: to_binary(&init_msg)?,
+        funds: vec![],
+        label: format!("launchpad collection {}", collection_id),
+    };
+
+    // the create-collection event is emitted by the reply, once the cw721 address is known
+    Ok(Response::new().add_submessage(SubMsg::reply_on_success(
+        msg,
+        INSTANTIATE_COLLECTION_REPLY_ID,
+    )))
+}
+
+fn reply_instantiate_collection(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let res =
+        parse_reply_instantiate_data(msg).map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let mut collection = PENDING_COLLECTION.load(deps.storage)?;
+    PENDING_COLLECTION.remove(deps.storage);
+    collection.cw721_contract = res.contract_address;
+    COLLECTIONS.save(deps.storage, collection.collection_id, &collection)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::CreateCollection {
+            collection_id: collection.collection_id,
+            collection: collection.cw721_contract,
+            seller: collection.creator,
+            price: Uint128::new(collection.mint_price.amount),
+            payment: collection.mint_price.info.id().to_string(),
+            max_supply: collection.max_supply,
+        }
+        .into(),
+    ))
+}
+
+pub fn try_set_mint_phases(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection_id: u64,
+    phases: Vec<MintPhase>,
+) -> Result<Response, ContractError> {
+    let collection = COLLECTIONS
+        .may_load(deps.storage, collection_id)?
+        .ok_or(ContractError::CollectionNotFound {})?;
+    if info.sender != collection.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+    // phase ids index the per-address mint counts, so the schedule is fixed once minting starts
+    if collection.minted > 0 {
+        return Err(ContractError::MintPhasesLocked {});
+    }
+
+    for phase in &phases {
+        match hex::decode(&phase.merkle_root) {
+            Ok(root) if root.len() == 32 => {}
+            _ => return Err(ContractError::InvalidMerkleRoot {}),
+        }
+        if let AssetInfo::Cw20 { contract } = &phase.price.info {
+            deps.api.addr_validate(contract)?;
+        }
+    }
+    MINT_PHASES.save(deps.storage, collection_id, &phases)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::SetMintPhases {
+            collection_id,
+            seller: collection.creator,
+            count: phases.len() as u64,
+        }
+        .into(),
+    ))
+}
+
+/// Mints the next token of a launchpad collection to `buyer`, paying the creator the mint price
+/// less the marketplace fee. With an allowlist proof the mint counts towards the buyer's
+/// allowance in that phase and is priced by it.
+pub fn execute_mint(
+    deps: DepsMut,
+    env: Env,
+    collection_id: u64,
+    buyer: String,
+    payment: Asset,
+    allowlist: Option<AllowlistProof>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let mut collection = COLLECTIONS
+        .may_load(deps.storage, collection_id)?
+        .ok_or(ContractError::CollectionNotFound {})?;
+    if collection.minted >= collection.max_supply {
+        return Err(ContractError::SoldOut {});
+    }
+
+    let phase_id = allowlist.as_ref().map(|allowlist| allowlist.phase_id);
+    let price = match allowlist {
+        Some(allowlist) => {
+            let phase = MINT_PHASES
+                .may_load(deps.storage, collection_id)?
+                .unwrap_or_default()
+                .into_iter()
+                .nth(allowlist.phase_id as usize)
+                .ok_or(ContractError::MintPhaseNotFound {})?;
+            if env.block.time < phase.start_time || env.block.time >= phase.end_time {
+                return Err(ContractError::MintPhaseClosed {});
+            }
+            if !verify_merkle_proof(&phase.merkle_root, &buyer, &allowlist.proof) {
+                return Err(ContractError::InvalidProof {});
+            }
+
+            let key = (collection_id, allowlist.phase_id, buyer.as_str());
+            let minted = PHASE_MINTS.may_load(deps.storage, key)?.unwrap_or_default();
+            if minted >= phase.per_address_limit {
+                return Err(ContractError::AllowanceExceeded {});
+            }
+            PHASE_MINTS.save(deps.storage, key, &(minted + 1))?;
+            phase.price
+        }
+        None => {
+            if env.block.time < collection.start_time {
+                return Err(ContractError::MintNotStarted {});
+            }
+            collection.mint_price.clone()
+        }
+    };
+    if payment != price {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    collection.minted += 1;
+    COLLECTIONS.save(deps.storage, collection_id, &collection)?;
+    let token_id = collection.minted.to_string();
+
+    let mint_msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+        token_id: token_id.clone(),
+        owner: buyer.clone(),
+        token_uri: None,
+        extension: None,
+    });
+    let msg = WasmMsg::Execute {
+        contract_addr: collection.cw721_contract.clone(),
+        msg: to_binary(&mint_msg)?,
+        funds: vec![],
+    };
+    let config = CONFIG.load(deps.storage)?;
+    let (payout, fee) = split_fee(&config, &payment);
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Mint {
+                collection_id,
+                collection: collection.cw721_contract,
+                token_id,
+                seller: collection.creator.clone(),
+                buyer,
+                price: Uint128::new(payment.amount),
+                payment: payment.info.id().to_string(),
+                fee: Uint128::new(fee.amount),
+                phase_id,
+                nonce: None,
+            }
+            .into(),
+        )
+        .add_message(msg)
+        .add_messages(payout_msgs(&config, &collection.creator, &payout, &fee)?))
+}
+
+pub fn try_set_signing_key(
+    deps: DepsMut,
+    info: MessageInfo,
+    pubkey: Binary,
+) -> Result<Response, ContractError> {
+    if pubkey.len() != 33 && pubkey.len() != 65 {
+        return Err(ContractError::InvalidPubkey {});
+    }
+    SIGNING_KEYS.save(deps.storage, info.sender.as_str(), &pubkey)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::SetSigningKey {
+            seller: info.sender.to_string(),
+        }
+        .into(),
+    ))
+}
+
+/// Mints the token of a voucher signed with the signing key of the collection's creator to
+/// `buyer`, paying the creator the voucher price less the marketplace fee.
+pub fn execute_redeem_voucher(
+    deps: DepsMut,
+    env: Env,
+    voucher: MintVoucher,
+    signature: Binary,
+    buyer: String,
+    payment: Asset,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let mut collection = COLLECTIONS
+        .may_load(deps.storage, voucher.collection_id)?
+        .ok_or(ContractError::CollectionNotFound {})?;
+    // numeric ids above the mint count are left to the sequential public mint
+    if matches!(voucher.token_id.parse::<u64>(), Ok(id) if id > collection.minted) {
+        return Err(ContractError::ReservedTokenId {});
+    }
+    let pubkey = SIGNING_KEYS
+        .may_load(deps.storage, &collection.creator)?
+        .ok_or(ContractError::SigningKeyNotFound {})?;
+
+    let hash = Sha256::digest(&voucher.sign_bytes(env.contract.address.as_str())?);
+    if !deps
+        .api
+        .secp256k1_verify(&hash, &signature, &pubkey)
+        .unwrap_or(false)
+    {
+        return Err(ContractError::InvalidSignature {});
+    }
+    let nonce_key = (collection.creator.as_str(), voucher.nonce);
+    if USED_NONCES.has(deps.storage, nonce_key) {
+        return Err(ContractError::NonceUsed {});
+    }
+    USED_NONCES.save(deps.storage, nonce_key, &Empty {})?;
+
+    if collection.minted >= collection.max_supply {
+        return Err(ContractError::SoldOut {});
+    }
+    if payment != voucher.price {
+        return Err(ContractError::InvalidPayment {});
+    }
+    collection.minted += 1;
+    COLLECTIONS.save(deps.storage, voucher.collection_id, &collection)?;
+
+    let mint_msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+        token_id: voucher.token_id.clone(),
+        owner: buyer.clone(),
+        token_uri: voucher.token_uri,
+        extension: voucher.extension,
+    });
+    let msg = WasmMsg::Execute {
+        contract_addr: collection.cw721_contract.clone(),
+        msg: to_binary(&mint_msg)?,
+        funds: vec![],
+    };
+    let config = CONFIG.load(deps.storage)?;
+    let (payout, fee) = split_fee(&config, &payment);
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Mint {
+                collection_id: voucher.collection_id,
+                collection: collection.cw721_contract,
+                token_id: voucher.token_id,
+                seller: collection.creator.clone(),
+                buyer,
+                price: Uint128::new(payment.amount),
+                payment: payment.info.id().to_string(),
+                fee: Uint128::new(fee.amount),
+                phase_id: None,
+                nonce: Some(voucher.nonce),
+            }
+            .into(),
+        )
+        .add_message(msg)
+        .add_messages(payout_msgs(&config, &collection.creator, &payout, &fee)?))
+}
+
+/// Checks that `address` is a leaf of the sha256 Merkle tree with the given hex-encoded root.
+/// Leaves are the hashed addresses, and each pair of nodes is hashed in sorted order.
+fn verify_merkle_proof(merkle_root: &str, address: &str, proof: &[String]) -> bool {
+    let mut hash: [u8; 32] = Sha256::digest(address.as_bytes()).into();
+    for sibling in proof {
+        let sibling: [u8; 32] = match hex::decode(sibling).map(<[u8; 32]>::try_from) {
+            Ok(Ok(sibling)) => sibling,
+            _ => return false,
+        };
+        let (first, second) = if hash < sibling {
+            (hash, sibling)
+        } else {
+            (sibling, hash)
+        };
+        hash = Sha256::new().chain(first).chain(second).finalize().into();
+    }
+    hex::encode(hash) == merkle_root.to_lowercase()
+}
+
+/// Loads a vault that has not been bought out.
+fn load_open_vault(deps: Deps, vault_id: u64) -> Result<Vault, ContractError> {
+    let vault = VAULTS
+        .may_load(deps.storage, vault_id)?
+        .ok_or(ContractError::VaultNotFound {})?;
+    if vault.buyout.is_some() {
+        return Err(ContractError::VaultBoughtOut {});
+    }
+    Ok(vault)
+}
+
+/// Releases the NFT of a vault from escrow and transfers it to `recipient`.
+fn release_vault_nft(
+    storage: &mut dyn Storage,
+    vault: &Vault,
+    recipient: &str,
+) -> StdResult<Vec<CosmosMsg>> {
+    let nft = &vault.nft;
+    ESCROWED_NFTS.remove(storage, (&nft.cw721_contract, &nft.token_id));
+    CW721_DEPOSITS.remove(
+        storage,
+        (&vault.curator, &nft.cw721_contract, &nft.token_id),
+    );
+
+    nft_transfer_msgs(std::slice::from_ref(nft), recipient)
+}
+
+fn burn_shares_msg(vault: &Vault, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: vault.share_token.clone(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+pub fn execute_redeem_vault(
+    deps: DepsMut,
+    info: MessageInfo,
+    vault_id: u64,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let vault = load_open_vault(deps.as_ref(), vault_id)?;
+    if info.sender != vault.share_token || cw20_msg.amount.u128() != vault.supply {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    VAULTS.remove(deps.storage, vault_id);
+    let mut msgs = release_vault_nft(deps.storage, &vault, &cw20_msg.sender)?;
+    msgs.push(burn_shares_msg(&vault, cw20_msg.amount)?);
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::RedeemVault {
+                vault_id,
+                collection: vault.nft.cw721_contract,
+                token_id: vault.nft.token_id,
+                buyer: cw20_msg.sender,
+            }
+            .into(),
+        )
+        .add_messages(msgs))
+}
+
+pub fn execute_buyout(
+    deps: DepsMut,
+    info: MessageInfo,
+    vault_id: u64,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let mut vault = load_open_vault(deps.as_ref(), vault_id)?;
+    if info.sender != vault.buyout_token || cw20_msg.amount.u128() < vault.buyout_price {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    // the offer stays escrowed here until the share holders claim it
+    vault.buyout = Some(Buyout {
+        buyer: cw20_msg.sender.clone(),
+        proceeds: cw20_msg.amount.u128(),
+        unclaimed_shares: vault.supply,
+    });
+    VAULTS.save(deps.storage, vault_id, &vault)?;
+    add_liability(
+        deps.storage,
+        &Asset {
+            info: AssetInfo::Cw20 {
+                contract: vault.buyout_token.clone(),
+            },
+            amount: cw20_msg.amount.u128(),
+        },
+    )?;
+    let msgs = release_vault_nft(deps.storage, &vault, &cw20_msg.sender)?;
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Buyout {
+                vault_id,
+                collection: vault.nft.cw721_contract,
+                token_id: vault.nft.token_id,
+                buyer: cw20_msg.sender,
+                price: cw20_msg.amount,
+                payment: vault.buyout_token,
+            }
+            .into(),
+        )
+        .add_messages(msgs))
+}
+
+pub fn execute_claim_proceeds(
+    deps: DepsMut,
+    info: MessageInfo,
+    vault_id: u64,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let mut vault = VAULTS
+        .may_load(deps.storage, vault_id)?
+        .ok_or(ContractError::VaultNotFound {})?;
+    if info.sender != vault.share_token {
+        return Err(ContractError::InvalidPayment {});
+    }
+    let mut buyout = vault.buyout.ok_or(ContractError::VaultNotBoughtOut {})?;
+
+    // paying out of what is left keeps rounding dust with the last claimant
+    let shares = cw20_msg.amount;
+    let payout = Uint128::new(buyout.proceeds).multiply_ratio(shares, buyout.unclaimed_shares);
+    buyout.proceeds -= payout.u128();
+    buyout.unclaimed_shares -= shares.u128();
+
+    let unclaimed_shares = buyout.unclaimed_shares;
+    vault.buyout = Some(buyout);
+    if unclaimed_shares == 0 {
+        VAULTS.remove(deps.storage, vault_id);
+    } else {
+        VAULTS.save(deps.storage, vault_id, &vault)?;
+    }
+
+    let proceeds = Asset {
+        info: AssetInfo::Cw20 {
+            contract: vault.buyout_token.clone(),
+        },
+        amount: payout.u128(),
+    };
+    sub_liability(deps.storage, &proceeds)?;
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::ClaimProceeds {
+                vault_id,
+                owner: cw20_msg.sender.clone(),
+                amount: payout,
+                payment: vault.buyout_token.clone(),
+            }
+            .into(),
+        )
+        .add_message(burn_shares_msg(&vault, shares)?)
+        .add_message(proceeds.transfer_msg(&cw20_msg.sender)?))
+}
+
+pub fn try_ibc_transfer_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft: NftItem,
+    channel_id: String,
+    receiver: String,
+    timeout: Option<u64>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let sender = info.sender.to_string();
+    let key = (
+        sender.as_str(),
+        nft.cw721_contract.as_str(),
+        nft.token_id.as_str(),
+    );
+    if !CW721_DEPOSITS.has(deps.storage, key) {
+        return Err(ContractError::InvalidOwner {});
+    }
+    ensure_not_locked(deps.as_ref(), &nft.cw721_contract, &nft.token_id)?;
+    if !IBC_CHANNELS.has(deps.storage, &channel_id) {
+        return Err(ContractError::UnknownChannel {});
+    }
+
+    // the NFT stays with the marketplace until the packet is acknowledged
+    remove_ask(deps.storage, &nft.cw721_contract, &nft.token_id)?;
+    CW721_DEPOSITS.remove(deps.storage, key);
+    IBC_ESCROW.save(
+        deps.storage,
+        (&channel_id, &nft.cw721_contract, &nft.token_id),
+        &Empty {},
+    )?;
+
+    // vouchers travel under the class id they arrived with
+    let class_id = VOUCHER_CLASS_IDS
+        .may_load(deps.storage, &nft.cw721_contract)?
+        .unwrap_or_else(|| nft.cw721_contract.clone());
+    let packet = NonFungibleTokenPacketData {
+        class_id: class_id.clone(),
+        class_uri: None,
+        token_ids: vec![nft.token_id.clone()],
+        token_uris: None,
+        sender: sender.clone(),
+        receiver: receiver.clone(),
+    };
+    let timeout = env
+        .block
+        .time
+        .plus_seconds(timeout.unwrap_or(DEFAULT_IBC_TIMEOUT_SECONDS));
+    let msg = IbcMsg::SendPacket {
+        channel_id: channel_id.clone(),
+        data: to_binary(&packet)?,
+        timeout: timeout.into(),
+    };
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::IbcTransfer {
+                collection: nft.cw721_contract,
+                token_id: nft.token_id,
+                sender,
+                receiver,
+                channel_id,
+                class_id,
+            }
+            .into(),
+        )
+        .add_message(msg))
+}
+
+pub fn execute_purchase(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    cw721_contract: String,
+    referrer: Option<String>,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let payment = Asset {
+        info: AssetInfo::Cw20 {
+            contract: info.sender.to_string(),
+        },
+        amount: cw20_msg.amount.u128(),
+    };
+    settle_purchase(
+        deps,
+        env,
+        cw721_contract,
+        token_id,
+        cw20_msg.sender,
+        payment,
+        referrer,
+    )
+}
+
+/// Sells a listed NFT to `buyer` for `payment`, which must be one of the prices of the ask.
+fn settle_purchase(
+    mut deps: DepsMut,
+    env: Env,
+    cw721_contract: String,
+    token_id: String,
+    buyer: String,
+    payment: Asset,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+
+    let ask = ASKS
+        .load(deps.storage, (&cw721_contract, &token_id))
+        .map_err(|_| ContractError::NoBidsForTokenID {})?;
+    let price = ask
+        .price_in(&payment.info)
+        .ok_or(ContractError::InvalidPayment {})?;
+    if price != payment.amount {
+        return Err(ContractError::InvalidBid {});
+    }
+    if let Some(reserved) = &ask.reserved_for {
+        if *reserved != buyer {
+            return Err(ContractError::ReservedListing {});
+        }
+    }
+    let res = complete_sale(
+        deps.branch(),
+        &env,
+        cw721_contract.clone(),
+        token_id.clone(),
+        ask.owner.clone(),
+        buyer,
+        payment,
+        referrer,
+    )?;
+    CW721_DEPOSITS.remove(deps.storage, (&ask.owner, &cw721_contract, &token_id));
+    remove_ask(deps.storage, &cw721_contract, &token_id)?;
+
+    Ok(res)
+}
+
+/// Buys the NFT of a sell order signed with the seller's signing key. The marketplace transfers
+/// the NFT on the seller's behalf and settles the payment like that of a listed NFT.
+pub fn execute_fill_order(
+    deps: DepsMut,
+    env: Env,
+    order: SellOrder,
+    signature: Binary,
+    buyer: String,
+    payment: Asset,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let pubkey = SIGNING_KEYS
+        .may_load(deps.storage, &order.seller)?
+        .ok_or(ContractError::SigningKeyNotFound {})?;
+
+    let hash = Sha256::digest(&order.sign_bytes(env.contract.address.as_str())?);
+    if !deps
+        .api
+        .secp256k1_verify(&hash, &signature, &pubkey)
+        .unwrap_or(false)
+    {
+        return Err(ContractError::InvalidSignature {});
+    }
+    if order.expires <= env.block.time {
+        return Err(ContractError::OrderExpired {});
+    }
+    if order_nonce_used(deps.storage, &order.seller, order.nonce)? {
+        return Err(ContractError::NonceUsed {});
+    }
+    if payment != order.price {
+        return Err(ContractError::InvalidPayment {});
+    }
+
+    let res: cw721::OwnerOfResponse = deps.querier.query_wasm_smart(
+        &order.cw721_contract,
+        &nft::contract::QueryMsg::OwnerOf {
+            token_id: order.token_id.clone(),
+            include_expired: Some(false),
+        },
+    )?;
+    if res.owner != order.seller {
+        return Err(ContractError::InvalidOwner {});
+    }
+    let marketplace = env.contract.address.as_str();
+    if !res
+        .approvals
+        .iter()
+        .any(|approval| approval.spender == marketplace)
+        && !is_operator(
+            deps.as_ref(),
+            &order.cw721_contract,
+            &order.seller,
+            marketplace,
+        )?
+    {
+        return Err(ContractError::NotApproved {});
+    }
+    ORDER_NONCES.save(deps.storage, (&order.seller, order.nonce), &Empty {})?;
+
+    complete_sale(
+        deps,
+        &env,
+        order.cw721_contract,
+        order.token_id,
+        order.seller,
+        buyer,
+        payment,
+        referrer,
+    )
+}
+
+pub fn try_cancel_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    nonce: u64,
+) -> Result<Response, ContractError> {
+    ORDER_NONCES.save(deps.storage, (info.sender.as_str(), nonce), &Empty {})?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::CancelOrder {
+            seller: info.sender.to_string(),
+            nonce,
+        }
+        .into(),
+    ))
+}
+
+/// Cancels the sender's sell orders with a nonce below `nonce`. Cancelled nonces stay cancelled,
+/// so a lower value than an earlier one changes nothing.
+pub fn try_cancel_orders_below(
+    deps: DepsMut,
+    info: MessageInfo,
+    nonce: u64,
+) -> Result<Response, ContractError> {
+    let min_nonce = MIN_ORDER_NONCES
+        .may_load(deps.storage, info.sender.as_str())?
+        .unwrap_or_default();
+    if nonce > min_nonce {
+        MIN_ORDER_NONCES.save(deps.storage, info.sender.as_str(), &nonce)?;
+    }
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::CancelOrdersBelow {
+            seller: info.sender.to_string(),
+            nonce,
+        }
+        .into(),
+    ))
+}
+
+/// Whether `owner` approved `operator` for all of its tokens in `cw721_contract`.
+fn is_operator(deps: Deps, cw721_contract: &str, owner: &str, operator: &str) -> StdResult<bool> {
+    let mut start_after = None;
+    loop {
+        let res: cw721::OperatorsResponse = deps.querier.query_wasm_smart(
+            cw721_contract,
+            &nft::contract::QueryMsg::AllOperators {
+                owner: owner.to_string(),
+                include_expired: Some(false),
+                start_after,
+                limit: Some(MAX_OPERATORS_PAGE),
+            },
+        )?;
+        if res
+            .operators
+            .iter()
+            .any(|approval| approval.spender == operator)
+        {
+            return Ok(true);
+        }
+        match res.operators.last() {
+            Some(last) if res.operators.len() == MAX_OPERATORS_PAGE as usize => {
+                start_after = Some(last.spender.clone());
+            }
+            _ => return Ok(false),
+        }
+    }
+}
+
+fn order_nonce_used(storage: &dyn Storage, seller: &str, nonce: u64) -> StdResult<bool> {
+    let min_nonce = MIN_ORDER_NONCES
+        .may_load(storage, seller)?
+        .unwrap_or_default();
+    Ok(nonce < min_nonce || ORDER_NONCES.has(storage, (seller, nonce)))
+}
+
+/// Sells `token_id` from `seller` to `buyer`, transferring the NFT from the marketplace, which
+/// holds it or is approved for it. The payment is credited to the seller's deposits, less the
+/// marketplace fee, part of which goes to the referrer, and the royalties of the collection.
+#[allow(clippy::too_many_arguments)]
+fn complete_sale(
+    deps: DepsMut,
+    env: &Env,
+    cw721_contract: String,
+    token_id: String,
+    seller: String,
+    buyer: String,
+    payment: Asset,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let (mut payout, fee) = split_fee(&config, &payment);
+    let (referral, marketplace_fee) =
+        take_referral(deps.storage, referrer.as_deref(), &buyer, &fee)?;
+    let royalties = royalty_shares(deps.as_ref(), &config, &cw721_contract, &token_id, &payout)?;
+    let royalty: u128 = royalties.iter().map(|(_, share)| share.amount).sum();
+    payout.amount -= royalty;
+
+    let exec_msg = nft::contract::ExecuteMsg::TransferNft {
+        recipient: buyer.clone(),
+        token_id: token_id.clone(),
+    };
+    let msg = WasmMsg::Execute {
+        contract_addr: cw721_contract.clone(),
+        msg: to_binary(&exec_msg)?,
+        funds: vec![],
+    };
+
+    let sale = Sale {
+        sale_id: next_sale_id(deps.storage)?,
+        cw721_contract,
+        token_id,
+        seller,
+        buyer,
+        price: payment.amount,
+        payment_token: payment.info.id().to_string(),
+        block_height: env.block.height,
+        block_time: env.block.time,
+    };
+    record_sale(deps.storage, &sale)?;
+    record_sale_stats(deps.storage, &sale)?;
+
+    // the proceeds are credited to the seller's deposits, to be withdrawn from there
+    credit_deposit(deps.storage, &sale.seller, &payout)?;
+    if marketplace_fee.amount > 0 {
+        credit_deposit(deps.storage, &config.fee_recipient, &marketplace_fee)?;
+    }
+    if let (Some(referrer), true) = (&referrer, referral.amount > 0) {
+        credit_deposit(deps.storage, referrer, &referral)?;
+    }
+    for (recipient, share) in &royalties {
+        credit_deposit(deps.storage, recipient, share)?;
+    }
+    add_liability(deps.storage, &payment)?;
+
+    Ok(Response::new()
+        .add_event(
+            MarketplaceEvent::Sale {
+                sale_id: sale.sale_id,
+                collection: sale.cw721_contract,
+                token_id: sale.token_id,
+                seller: sale.seller,
+                buyer: sale.buyer,
+                price: Uint128::new(sale.price),
+                payment: sale.payment_token,
+                fee: Uint128::new(fee.amount),
+                royalty: Uint128::new(royalty),
+                referrer,
+                referral_fee: Uint128::new(referral.amount),
+            }
+            .into(),
+        )
+        .add_message(msg))
+}
+
+/// Checks the extra prices of an ask: cw20 addresses must be valid and no asset, including the
+/// primary `cw20_contract`, may be priced twice.
+fn validate_accepted(
+    deps: Deps,
+    cw20_contract: &str,
+    accepted: &[Asset],
+) -> Result<(), ContractError> {
+    let mut seen = vec![cw20_contract];
+    for price in accepted {
+        if let AssetInfo::Cw20 { contract } = &price.info {
+            deps.api.addr_validate(contract)?;
+        }
+        if seen.contains(&price.info.id()) {
+            return Err(ContractError::DuplicatePaymentAsset {});
+        }
+        seen.push(price.info.id());
+    }
+    Ok(())
+}
+
+fn ensure_not_paused(deps: Deps) -> Result<(), ContractError> {
+    if CONFIG.load(deps.storage)?.paused {
+        return Err(ContractError::Paused {});
+    }
+    Ok(())
+}
+
+/// Splits the marketplace fee off `payment`, returning the seller's share and the fee.
+fn split_fee(config: &Config, payment: &Asset) -> (Asset, Asset) {
+    let fee = Uint128::new(payment.amount)
+        .multiply_ratio(config.fee_bps, MAX_FEE_BPS)
+        .u128();
+    let share = |amount| Asset {
+        info: payment.info.clone(),
+        amount,
+    };
+    (share(payment.amount - fee), share(fee))
+}
+
+/// The royalties owed on a sale in `cw721_contract` out of the seller's `payout`, skipping empty
+/// shares. If the cap was lowered after the royalties were registered, they are scaled down to it.
+/// Collections without registered royalties owe the CW2981 royalty they report, if any.
+fn royalty_shares(
+    deps: Deps,
+    config: &Config,
+    cw721_contract: &str,
+    token_id: &str,
+    payout: &Asset,
+) -> StdResult<Vec<(String, Asset)>> {
+    let royalties = ROYALTIES
+        .may_load(deps.storage, cw721_contract)?
+        .unwrap_or_default();
+    if royalties.is_empty() {
+        return Ok(
+            cw2981_royalty(deps, config, cw721_contract, token_id, payout)
+                .into_iter()
+                .collect(),
+        );
+    }
+    let total_bps: u64 = royalties.iter().map(|royalty| royalty.bps).sum();
+    if total_bps == 0 {
+        return Ok(vec![]);
+    }
+    let capped_bps = total_bps.min(config.max_royalty_bps);
+
+    Ok(royalties
+        .into_iter()
+        .map(|royalty| {
+            let amount = Uint128::new(payout.amount)
+                .multiply_ratio(royalty.bps * capped_bps, total_bps * MAX_FEE_BPS)
+                .u128();
+            let share = Asset {
+                info: payout.info.clone(),
+                amount,
+            };
+            (royalty.recipient, share)
+        })
+        .filter(|(_, share)| share.amount > 0)
+        .collect())
+}
+
+/// The CW2981 royalty `cw721_contract` reports for `token_id` on the seller's `payout`, capped at
+/// the maximum royalty. Collections that do not implement CW2981 owe none.
+fn cw2981_royalty(
+    deps: Deps,
+    config: &Config,
+    cw721_contract: &str,
+    token_id: &str,
+    payout: &Asset,
+) -> Option<(String, Asset)> {
+    let query = nft::contract::QueryMsg::Extension {
+        msg: nft::contract::Cw2981QueryMsg::RoyaltyInfo {
+            token_id: token_id.to_string(),
+            sale_price: Uint128::new(payout.amount),
+        },
+    };
+    let res: nft::contract::RoyaltiesInfoResponse =
+        deps.querier.query_wasm_smart(cw721_contract, &query).ok()?;
+    let max = Uint128::new(payout.amount).multiply_ratio(config.max_royalty_bps, MAX_FEE_BPS);
+    let amount = res.royalty_amount.min(max).u128();
+    if res.address.is_empty() || amount == 0 {
+        return None;
+    }
+    let share = Asset {
+        info: payout.info.clone(),
+        amount,
+    };
+    Some((res.address, share))
+}
+
+/// Splits the share of a registered `referrer` off the marketplace fee and adds it to the
+/// referrer's earnings. Returns the referral and the rest of the fee.
+fn take_referral(
+    storage: &mut dyn Storage,
+    referrer: Option<&str>,
+    buyer: &str,
+    fee: &Asset,
+) -> Result<(Asset, Asset), ContractError> {
+    let amount = referral_amount(fee, referral_bps(storage, referrer, buyer)?);
+    let share = |amount| Asset {
+        info: fee.info.clone(),
+        amount,
+    };
+
+    if let (Some(referrer), true) = (referrer, amount > 0) {
+        let key = (referrer, fee.info.id());
+        let mut earnings = REFERRAL_EARNINGS
+            .may_load(storage, key)?
+            .unwrap_or_else(|| share(0));
+        earnings.amount += amount;
+        REFERRAL_EARNINGS.save(storage, key, &earnings)?;
+    }
+
+    Ok((share(amount), share(fee.amount - amount)))
+}
+
+/// The share of the marketplace fee `referrer` is registered for, in basis points. Unregistered
+/// referrers earn nothing, and buyers may not refer themselves.
+fn referral_bps(
+    storage: &dyn Storage,
+    referrer: Option<&str>,
+    buyer: &str,
+) -> Result<u64, ContractError> {
+    match referrer {
+        Some(referrer) if referrer == buyer => Err(ContractError::SelfReferral {}),
+        Some(referrer) => Ok(REFERRERS.may_load(storage, referrer)?.unwrap_or_default()),
+        None => Ok(0),
+    }
+}
+
+fn referral_amount(fee: &Asset, share_bps: u64) -> u128 {
+    Uint128::new(fee.amount)
+        .multiply_ratio(share_bps, MAX_FEE_BPS)
+        .u128()
+}
+
+/// Pays the seller's share of a sale to `seller` and the fee to the fee recipient.
+fn payout_msgs(
+    config: &Config,
+    seller: &str,
+    payout: &Asset,
+    fee: &Asset,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut msgs = vec![];
+    if payout.amount > 0 {
+        msgs.push(payout.transfer_msg(seller)?);
+    }
+    if fee.amount > 0 {
+        msgs.push(fee.transfer_msg(&config.fee_recipient)?);
+    }
+    Ok(msgs)
+}
+
+/// Whether `cw721_contract` reports `token_id` as soulbound. Collections that do not report it
+/// are treated as transferable.
+fn is_soulbound(deps: Deps, cw721_contract: &str, token_id: &str) -> bool {
+    let query = nft::contract::QueryMsg::Soulbound {
+        token_id: token_id.to_string(),
+    };
+    deps.querier
+        .query_wasm_smart::<nft::contract::SoulboundResponse>(cw721_contract, &query)
+        .is_ok_and(|res| res.soulbound)
+}
+
+/// Fails if the NFT is held in a bundle or another escrow.
+fn ensure_not_locked(
+    deps: Deps,
+    cw721_contract: &str,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    if ESCROWED_NFTS.has(deps.storage, (cw721_contract, token_id)) {
+        return Err(ContractError::NftLocked {});
+    }
+    Ok(())
+}
+
+/// The single native coin attached to a message, as an asset.
+fn native_payment(info: &MessageInfo) -> Result<Asset, ContractError> {
+    match info.funds.as_slice() {
+        [coin] => Ok(Asset {
+            info: AssetInfo::Native {
+                denom: coin.denom.clone(),
+            },
+            amount: coin.amount.u128(),
+        }),
+        _ => Err(ContractError::InvalidCoin {}),
+    }
+}
+
+/// Moves `asset` out of the marketplace balance `owner` holds in DEPOSITS or CW20_DEPOSITS.
+fn debit_deposit(
+    storage: &mut dyn Storage,
+    owner: &str,
+    asset: &Asset,
+) -> Result<(), ContractError> {
+    match &asset.info {
+        AssetInfo::Native { denom } => {
+            let mut deposit = DEPOSITS
+                .may_load(storage, (owner, denom))?
+                .ok_or(ContractError::NoDepositToWithdraw {})?;
+
+            deposit.amount.amount = deposit
+                .amount
+                .amount
+                .checked_sub(Uint128::new(asset.amount))
+                .map_err(|_| ContractError::InsufficientDeposit {})?;
+            deposit.count = deposit.count.saturating_sub(1);
+            DEPOSITS.save(storage, (owner, denom), &deposit)?;
+        }
+        AssetInfo::Cw20 { contract } => {
+            let mut deposit = CW20_DEPOSITS
+                .may_load(storage, (owner, contract))?
+                .ok_or(ContractError::NoCw20ToWithdraw {})?;
+
+            deposit.amount = deposit
+                .amount
+                .checked_sub(asset.amount)
+                .ok_or(ContractError::InsufficientDeposit {})?;
+            deposit.count = deposit.count.saturating_sub(1);
+            CW20_DEPOSITS.save(storage, (owner, contract), &deposit)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds `asset` to the marketplace balance `owner` holds in DEPOSITS or CW20_DEPOSITS.
+fn credit_deposit(storage: &mut dyn Storage, owner: &str, asset: &Asset) -> StdResult<()> {
+    match &asset.info {
+        AssetInfo::Native { denom } => {
+            let mut deposit = DEPOSITS
+                .may_load(storage, (owner, denom))?
+                .unwrap_or_else(|| Deposit {
+                    owner: owner.to_string(),
+                    amount: Coin::new(0, denom),
+                    count: 0,
+                });
+
+            deposit.amount.amount += Uint128::new(asset.amount);
+            deposit.count += 1;
+            DEPOSITS.save(storage, (owner, denom), &deposit)
+        }
+        AssetInfo::Cw20 { contract } => {
+            let mut deposit = CW20_DEPOSITS
+                .may_load(storage, (owner, contract))?
+                .unwrap_or_else(|| Cw20Deposit {
+                    owner: owner.to_string(),
+                    amount: 0,
+                    contract: contract.clone(),
+                    count: 0,
+                });
+
+            deposit.amount += asset.amount;
+            deposit.count += 1;
+            CW20_DEPOSITS.save(storage, (owner, contract), &deposit)
+        }
+    }
+}
+
+fn native_asset(coin: &Coin) -> Asset {
+    Asset {
+        info: AssetInfo::Native {
+            denom: coin.denom.clone(),
+        },
+        amount: coin.amount.u128(),
+    }
+}
+
+/// Adds `asset` to the total the marketplace holds on behalf of depositors, receipt holders and
+/// sellers.
+fn add_liability(storage: &mut dyn Storage, asset: &Asset) -> StdResult<()> {
+    let mut total = LIABILITIES
+        .may_load(storage, asset.info.id())?
+        .unwrap_or_else(|| Asset {
+            info: asset.info.clone(),
+            amount: 0,
+        });
+    total.amount += asset.amount;
+    LIABILITIES.save(storage, asset.info.id(), &total)
+}
+
+/// Takes `asset` off the total once it has been paid out.
+fn sub_liability(storage: &mut dyn Storage, asset: &Asset) -> StdResult<()> {
+    if let Some(mut total) = LIABILITIES.may_load(storage, asset.info.id())? {
+        // balances recorded before the totals were tracked are not part of them
+        total.amount = total.amount.saturating_sub(asset.amount);
+        LIABILITIES.save(storage, asset.info.id(), &total)?;
+    }
+    Ok(())
+}
+
+/// Saves an ask, replacing any previous ask on the token, and keeps the listing counts and floor
+/// price index of the collection in sync.
+pub fn save_ask(storage: &mut dyn Storage, ask: &Offer) -> StdResult<()> {
+    remove_ask(storage, &ask.cw721_contract, &ask.token_id)?;
+
+    ASKS.save(storage, (&ask.cw721_contract, &ask.token_id), ask)?;
+
+    let mut stats = COLLECTION_STATS
+        .may_load(storage, &ask.cw721_contract)?
+        .unwrap_or_default();
+    stats.listing_count += 1;
+    COLLECTION_STATS.save(storage, &ask.cw721_contract, &stats)?;
+
+    // the ask is listed, and counted towards the floor price, in every asset it accepts
+    for price in ask.prices() {
+        ASK_PRICES.save(
+            storage,
+            (
+                &ask.cw721_contract,
+                price.info.id(),
+                (price.amount, &ask.token_id),
+            ),
+            &Empty {},
+        )?;
+
+        let key = (ask.cw721_contract.as_str(), price.info.id());
+        let mut token_stats = PAYMENT_TOKEN_STATS
+            .may_load(storage, key)?
+            .unwrap_or_default();
+        token_stats.listing_count += 1;
+        PAYMENT_TOKEN_STATS.save(storage, key, &token_stats)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the ask on a token, if there is one, and returns it.
+pub fn remove_ask(
+    storage: &mut dyn Storage,
+    cw721_contract: &str,
+    token_id: &str,
+) -> StdResult<Option<Offer>> {
+    let ask = match ASKS.may_load(storage, (cw721_contract, token_id))? {
+        Some(ask) => ask,
+        None => return Ok(None),
+    };
+
+    ASKS.remove(storage, (cw721_contract, token_id));
+
+    let mut stats = COLLECTION_STATS
+        .may_load(storage, cw721_contract)?
+        .unwrap_or_default();
+    stats.listing_count = stats.listing_count.saturating_sub(1);
+    COLLECTION_STATS.save(storage, cw721_contract, &stats)?;
+
+    for price in ask.prices() {
+        ASK_PRICES.remove(
+            storage,
+            (cw721_contract, price.info.id(), (price.amount, token_id)),
+        );
+
+        let key = (cw721_contract, price.info.id());
+        let mut token_stats = PAYMENT_TOKEN_STATS
+            .may_load(storage, key)?
+            .unwrap_or_default();
+        token_stats.listing_count = token_stats.listing_count.saturating_sub(1);
+        PAYMENT_TOKEN_STATS.save(storage, key, &token_stats)?;
+    }
+
+    Ok(Some(ask))
+}
+
+/// Adds a completed sale to the sale count and the hourly and all-time volumes of its collection.
+fn record_sale_stats(storage: &mut dyn Storage, sale: &Sale) -> StdResult<()> {
+    let mut stats = COLLECTION_STATS
+        .may_load(storage, &sale.cw721_contract)?
+        .unwrap_or_default();
+    stats.sale_count += 1;
+    COLLECTION_STATS.save(storage, &sale.cw721_contract, &stats)?;
+
+    let key = (sale.cw721_contract.as_str(), sale.payment_token.as_str());
+    let mut token_stats = PAYMENT_TOKEN_STATS
+        .may_load(storage, key)?
+        .unwrap_or_default();
+
+    VOLUME_RANKING.remove(
+        storage,
+        (
+            &sale.payment_token,
+            token_stats.volume,
+            &sale.cw721_contract,
+        ),
+    );
+    token_stats.volume += sale.price;
+    VOLUME_RANKING.save(
+        storage,
+        (
+            &sale.payment_token,
+            token_stats.volume,
+            &sale.cw721_contract,
+        ),
+        &Empty {},
+    )?;
+    PAYMENT_TOKEN_STATS.save(storage, key, &token_stats)?;
+
+    let hour = sale.block_time.seconds() / SECONDS_PER_HOUR;
+    HOURLY_VOLUMES.update(
+        storage,
+        (&sale.cw721_contract, &sale.payment_token, hour),
+        |volume| -> StdResult<_> { Ok(volume.unwrap_or_default() + sale.price) },
+    )?;
+
+    Ok(())
+}
+
+fn next_sale_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let sale_id = SALE_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    SALE_COUNT.save(storage, &sale_id)?;
+    Ok(sale_id)
+}
+
+/// Stores a completed sale and indexes it by collection, token and both participants.
+fn record_sale(storage: &mut dyn Storage, sale: &Sale) -> StdResult<()> {
+    SALES.save(storage, sale.sale_id, sale)?;
+
+    SALES_BY_COLLECTION.save(storage, (&sale.cw721_contract, sale.sale_id), &Empty {})?;
+    SALES_BY_TOKEN.save(
+        storage,
+        (&sale.cw721_contract, &sale.token_id, sale.sale_id),
+        &Empty {},
+    )?;
+    SALES_BY_ADDRESS.save(storage, (&sale.seller, sale.sale_id), &Empty {})?;
+    SALES_BY_ADDRESS.save(storage, (&sale.buyer, sale.sale_id), &Empty {})?;
+
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_SHARE_TOKEN_REPLY_ID => reply_instantiate_share_token(deps, msg),
+        INSTANTIATE_RECEIPT_TOKEN_REPLY_ID => reply_instantiate_receipt_token(deps, msg),
+        INSTANTIATE_VOUCHER_REPLY_ID => reply_instantiate_voucher(deps, msg),
+        INSTANTIATE_COLLECTION_REPLY_ID => reply_instantiate_collection(deps, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::UpdateFees {
+            fee_bps,
+            fee_recipient,
+        } => sudo_update_fees(deps, fee_bps, fee_recipient),
+        SudoMsg::SetPaused { paused } => sudo_set_paused(deps, paused),
+        SudoMsg::UpdateAdmin { admin } => sudo_update_admin(deps, admin),
+        SudoMsg::ForceDelist {
+            cw721_contract,
+            token_id,
+        } => sudo_force_delist(deps, cw721_contract, token_id),
+    }
+}
+
+pub fn sudo_update_fees(
+    deps: DepsMut,
+    fee_bps: u64,
+    fee_recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    if fee_bps > MAX_FEE_BPS {
+        return Err(ContractError::InvalidFee { max: MAX_FEE_BPS });
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.fee_bps = fee_bps;
+    config.fee_recipient = match fee_recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?.to_string(),
+        None => config.admin.clone(),
+    };
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::UpdateFees {
+            fee_bps,
+            fee_recipient: config.fee_recipient,
+        }
+        .into(),
+    ))
+}
+
+pub fn sudo_set_paused(deps: DepsMut, paused: bool) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    config.paused = paused;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_event(MarketplaceEvent::SetPaused { paused }.into()))
+}
+
+pub fn sudo_update_admin(deps: DepsMut, admin: String) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    config.admin = deps.api.addr_validate(&admin)?.to_string();
+    CONFIG.save(deps.storage, &config)?;
+    ADMIN_PROPOSAL.remove(deps.storage);
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::UpdateAdmin {
+            admin: config.admin,
+        }
+        .into(),
+    ))
+}
+
+pub fn sudo_force_delist(
+    deps: DepsMut,
+    cw721_contract: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let ask = ASKS
+        .may_load(deps.storage, (&cw721_contract, &token_id))?
+        .ok_or(ContractError::NoBidsForTokenID {})?;
+    remove_ask(deps.storage, &cw721_contract, &token_id)?;
+
+    Ok(Response::new().add_event(
+        MarketplaceEvent::Delist {
+            collection: cw721_contract,
+            token_id,
+            seller: ask.owner,
+        }
+        .into(),
+    ))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetCw20Deposit { address } => to_binary(&try_query_cw20_deposit(deps, address)?),
+        QueryMsg::GetDeposits { address } => to_binary(&try_query_deposit(deps, address)?),
+        QueryMsg::GetCw721Deposit { address, contract } => {
+            to_binary(&try_query_cw721_deposit(deps, address, contract)?)
+        }
+        QueryMsg::GetConfig {} => to_binary(&try_query_config(deps)?),
+        QueryMsg::PendingAdmin {} => {
+            let proposal = ADMIN_PROPOSAL.may_load(deps.storage)?;
+            to_binary(&PendingAdminResponse {
+                new_admin: proposal.as_ref().map(|p| p.new_admin.clone()),
+                expires_at: proposal.map(|p| p.expires_at),
+            })
+        }
+        QueryMsg::Royalties { cw721_contract } => to_binary(&RoyaltiesResponse {
+            royalties: ROYALTIES
+                .may_load(deps.storage, &cw721_contract)?
+                .unwrap_or_default(),
+            cw721_contract,
+        }),
+        QueryMsg::ReferralEarnings { referrer } => {
+            to_binary(&try_query_referral_earnings(deps, referrer)?)
+        }
+        QueryMsg::Referrers { start_after, limit } => {
+            to_binary(&try_query_referrers(deps, start_after, limit)?)
+        }
+        QueryMsg::SalesByCollection {
+            cw721_contract,
+            start_after,
+            limit,
+        } => to_binary(&try_query_sales(
+            deps,
+            SALES_BY_COLLECTION,
+            &cw721_contract,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::SalesByToken {
+            cw721_contract,
+            token_id,
+            start_after,
+            limit,
+        } => to_binary(&try_query_sales_by_token(
+            deps,
+            cw721_contract,
+            token_id,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::SalesByAddress {
+            address,
+            start_after,
+            limit,
+        } => {
+            deps.api.addr_validate(&address)?;
+            to_binary(&try_query_sales(
+                deps,
+                SALES_BY_ADDRESS,
+                &address,
+                start_after,
+                limit,
+            )?)
+        }
+        QueryMsg::CollectionStats { cw721_contract } => {
+            to_binary(&try_query_collection_stats(deps, env, cw721_contract)?)
+        }
+        QueryMsg::TopCollections {
+            payment_token,
+            start_after,
+            limit,
+        } => to_binary(&try_query_top_collections(
+            deps,
+            payment_token,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Bundle { bundle_id } => to_binary(&BUNDLES.load(deps.storage, bundle_id)?),
+        QueryMsg::Bundles { start_after, limit } => {
+            to_binary(&try_query_bundles(deps, start_after, limit)?)
+        }
+        QueryMsg::Swap { swap_id } => to_binary(&try_query_swap(deps, swap_id)?),
+        QueryMsg::Swaps { start_after, limit } => {
+            to_binary(&try_query_swaps(deps, start_after, limit)?)
+        }
+        QueryMsg::Loan { loan_id } => to_binary(&LOANS.load(deps.storage, loan_id)?),
+        QueryMsg::Loans { start_after, limit } => {
+            to_binary(&try_query_loans(deps, start_after, limit)?)
+        }
+        QueryMsg::Rental {
+            cw721_contract,
+            token_id,
+        } => to_binary(&RENTALS.load(deps.storage, (&cw721_contract, &token_id))?),
+        QueryMsg::Vault { vault_id } => to_binary(&VAULTS.load(deps.storage, vault_id)?),
+        QueryMsg::Vaults { start_after, limit } => {
+            to_binary(&try_query_vaults(deps, start_after, limit)?)
+        }
+        QueryMsg::Collection { collection_id } => {
+            to_binary(&COLLECTIONS.load(deps.storage, collection_id)?)
+        }
+        QueryMsg::Collections { start_after, limit } => {
+            to_binary(&try_query_collections(deps, start_after, limit)?)
+        }
+        QueryMsg::MintPhases { collection_id } => to_binary(&MintPhasesResponse {
+            collection_id,
+            phases: MINT_PHASES
+                .may_load(deps.storage, collection_id)?
+                .unwrap_or_default(),
+        }),
+        QueryMsg::SigningKey { creator } => to_binary(&SigningKeyResponse {
+            pubkey: SIGNING_KEYS.may_load(deps.storage, &creator)?,
+            creator,
+        }),
+        QueryMsg::NonceUsed { creator, nonce } => to_binary(&NonceUsedResponse {
+            used: USED_NONCES.has(deps.storage, (&creator, nonce)),
+        }),
+        QueryMsg::OrderNonceUsed { seller, nonce } => to_binary(&NonceUsedResponse {
+            used: order_nonce_used(deps.storage, &seller, nonce)?,
+        }),
+        QueryMsg::MintAllowance {
+            collection_id,
+            phase_id,
+            address,
+        } => to_binary(&try_query_mint_allowance(
+            deps,
+            collection_id,
+            phase_id,
+            address,
+        )?),
+        QueryMsg::ReceiptToken { denom } => to_binary(&ReceiptTokenResponse {
+            receipt_token: RECEIPT_TOKENS.may_load(deps.storage, &denom)?,
+            denom,
+        }),
+        QueryMsg::Solvency { start_after, limit } => {
+            to_binary(&try_query_solvency(deps, env, start_after, limit)?)
+        }
+        QueryMsg::VoucherContract { class_id } => to_binary(&VoucherContractResponse {
+            cw721_contract: VOUCHER_CONTRACTS.may_load(deps.storage, &class_id)?,
+            class_id,
+        }),
+        QueryMsg::CurrentUser {
+            cw721_contract,
+            token_id,
+        } => to_binary(&try_query_current_user(
+            deps,
+            env,
+            cw721_contract,
+            token_id,
+        )?),
+        QueryMsg::Asks {
+            cw721_contract,
+            viewer,
+            start_after,
+            limit,
+        } => to_binary(&try_query_asks(
+            deps,
+            cw721_contract,
+            viewer,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::AsksBySeller {
+            seller,
+            viewer,
+            start_after,
+            limit,
+        } => to_binary(&try_query_asks_by_seller(
+            deps,
+            seller,
+            viewer,
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+pub fn try_query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(ConfigResponse {
+        admin: config.admin,
+        max_batch_size: config.max_batch_size,
+        cw20_code_id: config.cw20_code_id,
+        nft_code_id: config.nft_code_id,
+        fee_bps: config.fee_bps,
+        fee_recipient: config.fee_recipient,
+        paused: config.paused,
+        max_royalty_bps: config.max_royalty_bps,
+        deposit_receipts: config.deposit_receipts,
+    })
+}
+
+pub fn try_query_deposit(deps: Deps, address: String) -> StdResult<DepositResponse> {
+    let _valid_addr = deps.api.addr_validate(&address)?;
+
+    let res: StdResult<Vec<_>> = DEPOSITS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect();
+
+    let deposits_found = res?;
+
+    if deposits_found.is_empty() {
+        return Err(StdError::generic_err("No deposits found for that address"));
+    }
+
+    let mut deposits: Vec<Deposit> = vec![];
+
+    for deposit in deposits_found {
+        deposits.push(deposit.1);
+    }
+
+    Ok(DepositResponse { deposits })
+}
+
+pub fn try_query_cw20_deposit(deps: Deps, address: String) -> StdResult<Cw20DepositResponse> {
+    let _valid_addr = deps.api.addr_validate(&address)?;
+
+    let wrapped_deposits: StdResult<Vec<_>> = CW20_DEPOSITS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect();
+
+    let deposits = wrapped_deposits?;
+
+    if deposits.is_empty() {
+        return Err(StdError::generic_err(
+            "No cw20 deposits exist for that address",
+        ));
+    }
+
+    let mut real_deposits: Vec<Cw20Deposit> = vec![];
+    for deposit in deposits {
+        real_deposits.push(deposit.1);
+    }
+
+    Ok(Cw20DepositResponse {
+        deposits: real_deposits,
+    })
+}
+
+pub fn try_query_cw721_deposit(
+    deps: Deps,
+    address: String,
+    contract: String,
+) -> StdResult<Cw721DepositResponse> {
+    let _valid_addr = deps.api.addr_validate(&address)?;
+    let _valid_contract_addr = deps.api.addr_validate(&contract)?;
+
+    let res: StdResult<Vec<_>> = CW721_DEPOSITS
+        .prefix((&address, &contract))
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect();
+
+    let deposits_found = res?;
+
+    if deposits_found.is_empty() {
+        return Err(StdError::generic_err(
+            "No cw721 deposits exist for that address",
+        ));
+    }
+
+    let mut deposits: Vec<Cw721Deposit> = vec![];
+    for deposit in deposits_found {
+        deposits.push(deposit.1);
+    }
+
+    Ok(Cw721DepositResponse { deposits })
+}
+
+/// Pages through a `(prefix, sale id)` index in ascending sale id order.
+pub fn try_query_sales(
+    deps: Deps,
+    index: Map<(&str, u64), Empty>,
+    prefix: &str,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SalesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let sales = index
+        .prefix(prefix)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|sale_id| SALES.load(deps.storage, sale_id?))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(SalesResponse { sales })
+}
+
+pub fn try_query_sales_by_token(
+    deps: Deps,
+    cw721_contract: String,
+    token_id: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SalesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let sales = SALES_BY_TOKEN
+        .prefix((&cw721_contract, &token_id))
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|sale_id| SALES.load(deps.storage, sale_id?))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(SalesResponse { sales })
+}
+
+pub fn try_query_collection_stats(
+    deps: Deps,
+    env: Env,
+    cw721_contract: String,
+) -> StdResult<CollectionStatsResponse> {
+    let stats = COLLECTION_STATS
+        .may_load(deps.storage, &cw721_contract)?
+        .unwrap_or_default();
+
+    // the 24h window covers the current hour and the 23 before it
+    let current_hour = env.block.time.seconds() / SECONDS_PER_HOUR;
+    let window_start = current_hour.saturating_sub(23);
+
+    let payment_tokens = PAYMENT_TOKEN_STATS
+        .prefix(&cw721_contract)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (payment_token, token_stats) = item?;
+
+            let floor_price = ASK_PRICES
+                .prefix((&cw721_contract, &payment_token))
+                .keys(deps.storage, None, None, Order::Ascending)
+                .next()
+                .transpose()?
+                .map(|(price, _)| price);
+
+            let volume_24h = HOURLY_VOLUMES
+                .prefix((&cw721_contract, &payment_token))
+                .range(
+                    deps.storage,
+                    Some(Bound::inclusive(window_start)),
+                    None,
+                    Order::Ascending,
+                )
+                .map(|item| item.map(|(_, volume)| volume))
+                .sum::<StdResult<u128>>()?;
+
+            Ok(PaymentTokenStatsResponse {
+                payment_token,
+                listing_count: token_stats.listing_count,
+                floor_price,
+                volume_24h,
+                volume_all_time: token_stats.volume,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(CollectionStatsResponse {
+        cw721_contract,
+        listing_count: stats.listing_count,
+        sale_count: stats.sale_count,
+        payment_tokens,
+    })
+}
+
+pub fn try_query_top_collections(
+    deps: Deps,
+    payment_token: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TopCollectionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // resume below the volume the `start_after` collection is ranked at
+    let end = match &start_after {
+        Some(cw721_contract) => {
+            let volume = PAYMENT_TOKEN_STATS
+                .may_load(deps.storage, (cw721_contract, &payment_token))?
+                .unwrap_or_default()
+                .volume;
+            Some(Bound::exclusive((volume, cw721_contract.as_str())))
+        }
+        None => None,
+    };
+
+    let collections = VOLUME_RANKING
+        .sub_prefix(&payment_token)
+        .keys(deps.storage, None, end, Order::Descending)
+        .take(limit)
+        .map(|key| {
+            let (volume, cw721_contract) = key?;
+            Ok(CollectionVolume {
+                cw721_contract,
+                volume,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TopCollectionsResponse { collections })
+}
+
+pub fn try_query_referral_earnings(
+    deps: Deps,
+    referrer: String,
+) -> StdResult<ReferralEarningsResponse> {
+    let earnings = REFERRAL_EARNINGS
+        .prefix(&referrer)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ReferralEarningsResponse {
+        share_bps: REFERRERS.may_load(deps.storage, &referrer)?,
+        referrer,
+        earnings,
+    })
+}
+
+pub fn try_query_referrers(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ReferrersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let referrers = REFERRERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(address, share_bps)| Referrer { address, share_bps }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ReferrersResponse { referrers })
+}
+
+pub fn try_query_bundles(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<BundlesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let bundles = BUNDLES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, bundle)| bundle))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(BundlesResponse { bundles })
+}
+
+pub fn try_query_loans(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<LoansResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let loans = LOANS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, loan)| loan))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(LoansResponse { loans })
+}
+
+pub fn try_query_solvency(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<SolvencyResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let liabilities = LIABILITIES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, total)| total))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut assets = vec![];
+    for total in liabilities {
+        let balance = match &total.info {
+            AssetInfo::Native { denom } => {
+                deps.querier
+                    .query_balance(&env.contract.address, denom)?
+                    .amount
+            }
+            AssetInfo::Cw20 { contract } => {
+                // anything that does not answer as a cw20 holds nothing for the marketplace
+                let res: StdResult<BalanceResponse> = deps.querier.query_wasm_smart(
+                    contract,
+                    &Cw20QueryMsg::Balance {
+                        address: env.contract.address.to_string(),
+                    },
+                );
+                res.map(|res| res.balance).unwrap_or_default()
+            }
+        };
+        assets.push(AssetSolvency {
+            info: total.info,
+            liabilities: total.amount,
+            balance: balance.u128(),
+            shortfall: total.amount.saturating_sub(balance.u128()),
+        });
+    }
+
+    Ok(SolvencyResponse {
+        solvent: assets.iter().all(|asset| asset.shortfall == 0),
+        assets,
+    })
+}
+
+pub fn try_query_vaults(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VaultsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let vaults = VAULTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, vault)| vault))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(VaultsResponse { vaults })
+}
+
+pub fn try_query_collections(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<CollectionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let collections = COLLECTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, collection)| collection))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(CollectionsResponse { collections })
+}
+
+pub fn try_query_mint_allowance(
+    deps: Deps,
+    collection_id: u64,
+    phase_id: u32,
+    address: String,
+) -> StdResult<MintAllowanceResponse> {
+    let phase = MINT_PHASES
+        .may_load(deps.storage, collection_id)?
+        .unwrap_or_default()
+        .into_iter()
+        .nth(phase_id as usize)
+        .ok_or_else(|| StdError::not_found("MintPhase"))?;
+    let minted = PHASE_MINTS
+        .may_load(deps.storage, (collection_id, phase_id, &address))?
+        .unwrap_or_default();
+
+    Ok(MintAllowanceResponse {
+        minted,
+        remaining: phase.per_address_limit.saturating_sub(minted),
+    })
+}
+
+pub fn try_query_current_user(
+    deps: Deps,
+    env: Env,
+    cw721_contract: String,
+    token_id: String,
+) -> StdResult<CurrentUserResponse> {
+    let rental = RENTALS.may_load(deps.storage, (&cw721_contract, &token_id))?;
+    let user = rental.and_then(|rental| {
+        rental
+            .current_user(env.block.time)
+            .map(|user| (user, rental.expires_at))
+    });
+
+    Ok(match user {
+        Some((user, expires_at)) => CurrentUserResponse {
+            user: Some(user),
+            expires_at,
+        },
+        None => CurrentUserResponse {
+            user: None,
+            expires_at: None,
+        },
+    })
+}
+
+pub fn try_query_asks(
+    deps: Deps,
+    cw721_contract: String,
+    viewer: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AsksResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let viewer = viewer.unwrap_or_default();
+
+    // Reserved asks are filtered before the limit so hidden ones don't shorten a page
+    let asks = ASKS
+        .prefix(&cw721_contract)
+        .range(deps.storage, start, None, Order::Ascending)
+        .map(|item| item.map(|(_, ask)| ask))
+        .filter(|ask| ask.as_ref().map_or(true, |ask| ask.is_visible_to(&viewer)))
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AsksResponse { asks })
+}
+
+pub fn try_query_asks_by_seller(
+    deps: Deps,
+    seller: String,
+    viewer: Option<String>,
+    start_after: Option<NftItem>,
+    limit: Option<u32>,
+) -> StdResult<AsksResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .as_ref()
+        .map(|nft| Bound::exclusive((nft.cw721_contract.as_str(), nft.token_id.as_str())));
+    let viewer = viewer.unwrap_or_default();
+
+    // Deposits without an ask (bundled, escrowed or sold) are skipped
+    let mut asks = vec![];
+    for item in CW721_DEPOSITS
+        .sub_prefix(&seller)
+        .keys(deps.storage, start, None, Order::Ascending)
+    {
+        if asks.len() == limit {
+            break;
+        }
+        let (cw721_contract, token_id) = item?;
+        if let Some(ask) = ASKS.may_load(deps.storage, (&cw721_contract, &token_id))? {
+            if ask.is_visible_to(&viewer) {
+                asks.push(ask);
+            }
+        }
+    }
+
+    Ok(AsksResponse { asks })
+}
+
+pub fn try_query_swap(deps: Deps, swap_id: u64) -> StdResult<SwapResponse> {
+    let swap = SWAPS.load(deps.storage, swap_id)?;
+
+    let mut taker = None;
+    let mut received = vec![];
+    for nft in &swap.wanted {
+        let key = (swap_id, nft.cw721_contract.as_str(), nft.token_id.as_str());
+        if let Some(sender) = SWAP_FILLS.may_load(deps.storage, key)? {
+            taker = Some(sender);
+            received.push(nft.clone());
+        }
+    }
+
+    Ok(SwapResponse {
+        swap,
+        taker,
+        received,
+    })
+}
+
+pub fn try_query_swaps(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SwapsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let swaps = SWAPS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, swap)| swap))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(SwapsResponse { swaps })
 }
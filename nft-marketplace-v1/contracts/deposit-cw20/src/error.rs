@@ -29,4 +29,196 @@ pub enum ContractError {
 
     #[error("This Cw721 token is already deposited into the contract")]
     Cw721AlreadyDeposited {},
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Batch must contain at least one item")]
+    EmptyBatch {},
+
+    #[error("Batch exceeds the maximum size of {max} items")]
+    BatchTooLarge { max: u32 },
+
+    #[error("User does not have a deposit of this denom to withdraw")]
+    NoDepositToWithdraw {},
+
+    #[error("Withdrawal amount exceeds the deposited balance")]
+    InsufficientDeposit {},
+
+    #[error("This Cw721 token is locked and cannot be listed or withdrawn on its own")]
+    NftLocked {},
+
+    #[error("Payment does not match the asking price")]
+    InvalidPayment {},
+
+    #[error("An ask may name each payment asset only once")]
+    DuplicatePaymentAsset {},
+
+    #[error("Bundle not found")]
+    BundleNotFound {},
+
+    #[error("A swap may name each Cw721 token only once")]
+    DuplicateNft {},
+
+    #[error("Swap not found")]
+    SwapNotFound {},
+
+    #[error("This Cw721 token is not wanted by the swap or was already received")]
+    NftNotWanted {},
+
+    #[error("This listing is reserved for another buyer")]
+    ReservedListing {},
+
+    #[error("Loan not found")]
+    LoanNotFound {},
+
+    #[error("Loan principal must be greater than zero")]
+    ZeroPrincipal {},
+
+    #[error("Loan has already been funded")]
+    LoanAlreadyFunded {},
+
+    #[error("Loan has not been funded")]
+    LoanNotFunded {},
+
+    #[error("Loan has expired")]
+    LoanExpired {},
+
+    #[error("Loan has not expired yet")]
+    LoanNotExpired {},
+
+    #[error("Rental period and price must be greater than zero")]
+    InvalidRentalTerms {},
+
+    #[error("Rental not found")]
+    RentalNotFound {},
+
+    #[error("This Cw721 token is rented out until the current rental expires")]
+    RentalActive {},
+
+    #[error("No cw20 code id is configured")]
+    Cw20CodeIdNotSet {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Vault supply and buyout price must be greater than zero")]
+    InvalidVaultTerms {},
+
+    #[error("Vault not found")]
+    VaultNotFound {},
+
+    #[error("Vault has been bought out")]
+    VaultBoughtOut {},
+
+    #[error("Vault has not been bought out")]
+    VaultNotBoughtOut {},
+
+    #[error("No nft code id is configured")]
+    NftCodeIdNotSet {},
+
+    #[error("Only unordered ICS-721 channels are supported")]
+    OnlyUnorderedChannel {},
+
+    #[error("Invalid ICS-721 version {version}")]
+    InvalidIbcVersion { version: String },
+
+    #[error("ICS-721 channels may not be closed")]
+    CannotCloseChannel {},
+
+    #[error("Unknown IBC channel")]
+    UnknownChannel {},
+
+    #[error("This Cw721 token is not escrowed on the channel")]
+    NftNotEscrowed {},
+
+    #[error("Trading is paused")]
+    Paused {},
+
+    #[error("Fee may not exceed {max} basis points")]
+    InvalidFee { max: u64 },
+
+    #[error("Admin proposals may not last longer than {max} seconds")]
+    ProposalTooLong { max: u64 },
+
+    #[error("Admin proposals must last at least one second")]
+    ProposalTooShort {},
+
+    #[error("The proposed admin is already the admin")]
+    SameAdmin {},
+
+    #[error("Referrer is not registered")]
+    ReferrerNotFound {},
+
+    #[error("Buyers may not refer themselves")]
+    SelfReferral {},
+
+    #[error("Royalties may not exceed {max} basis points")]
+    RoyaltyTooHigh { max: u64 },
+
+    #[error("Royalty shares must be greater than zero")]
+    ZeroRoyalty {},
+
+    #[error("Collection not found")]
+    CollectionNotFound {},
+
+    #[error("Minting has not started")]
+    MintNotStarted {},
+
+    #[error("All tokens of the collection have been minted")]
+    SoldOut {},
+
+    #[error("Mint phases can only be changed before the first mint")]
+    MintPhasesLocked {},
+
+    #[error("Merkle root must be a hex-encoded sha256 hash")]
+    InvalidMerkleRoot {},
+
+    #[error("Mint phase not found")]
+    MintPhaseNotFound {},
+
+    #[error("Mint phase is not open")]
+    MintPhaseClosed {},
+
+    #[error("Address is not on the allowlist")]
+    InvalidProof {},
+
+    #[error("Address has minted its allowance for this phase")]
+    AllowanceExceeded {},
+
+    #[error("Public key must be a 33 or 65 byte secp256k1 key")]
+    InvalidPubkey {},
+
+    #[error("No signing key is registered for this address")]
+    SigningKeyNotFound {},
+
+    #[error("Token id is reserved for the public mint")]
+    ReservedTokenId {},
+
+    #[error("Signature is invalid")]
+    InvalidSignature {},
+
+    #[error("Nonce has already been used or cancelled")]
+    NonceUsed {},
+
+    #[error("Order has expired")]
+    OrderExpired {},
+
+    #[error("Marketplace is not approved to transfer this Cw721 token")]
+    NotApproved {},
+
+    #[error("Soulbound Cw721 tokens cannot be deposited")]
+    SoulboundToken {},
+
+    #[error("No admin proposal found")]
+    AdminProposalNotFound {},
+
+    #[error("The admin proposal has expired")]
+    AdminProposalExpired {},
+
+    #[error("Deposit amount does not match the tokens sent")]
+    InvalidDepositAmount {},
+
+    #[error("Arithmetic overflow")]
+    Overflow {},
 }
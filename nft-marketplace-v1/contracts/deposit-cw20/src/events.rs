@@ -0,0 +1,1105 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use cosmwasm_std::{from_slice, to_vec, Event, StdError, StdResult, Uint128};
+
+use crate::state::Asset;
+
+/// Prefix of the type of every marketplace event. The chain adds `wasm-` in front of custom
+/// event types, so a sale is indexed as `wasm-marketplace-sale`.
+pub const EVENT_TYPE_PREFIX: &str = "marketplace-";
+
+/// The events emitted by the marketplace, one per action.
+///
+/// Attribute keys are shared between actions: `collection` and `token_id` identify an NFT,
+/// `seller` is the party giving it up and `buyer` the party receiving it, `price` and `fee` are
+/// amounts of the `payment` token (a cw20 address or a native denom). Time attributes are in
+/// seconds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarketplaceEvent {
+    Deposit {
+        owner: String,
+        payment: String,
+        amount: Uint128,
+    },
+    Withdraw {
+        owner: String,
+        payment: String,
+        amount: Uint128,
+    },
+    List {
+        collection: String,
+        token_id: String,
+        seller: String,
+        price: Uint128,
+        payment: String,
+        reserved_for: Option<String>,
+        /// The other accepted prices, as a JSON list of assets
+        accepted: Vec<Asset>,
+    },
+    Delist {
+        collection: String,
+        token_id: String,
+        seller: String,
+    },
+    Sale {
+        sale_id: u64,
+        collection: String,
+        token_id: String,
+        seller: String,
+        buyer: String,
+        price: Uint128,
+        payment: String,
+        fee: Uint128,
+        /// Paid to the collection's royalty recipients out of the seller's proceeds
+        royalty: Uint128,
+        /// Referrer paid `referral_fee` out of `fee`
+        referrer: Option<String>,
+        referral_fee: Uint128,
+    },
+    ListBundle {
+        bundle_id: u64,
+        seller: String,
+        price: Uint128,
+        payment: String,
+        count: u64,
+    },
+    DelistBundle {
+        bundle_id: u64,
+        seller: String,
+    },
+    SellBundle {
+        bundle_id: u64,
+        seller: String,
+        buyer: String,
+        price: Uint128,
+        payment: String,
+        fee: Uint128,
+        referrer: Option<String>,
+        referral_fee: Uint128,
+    },
+    CreateSwap {
+        swap_id: u64,
+        seller: String,
+    },
+    FillSwap {
+        swap_id: u64,
+        collection: String,
+        token_id: String,
+        buyer: String,
+        settled: bool,
+    },
+    CancelSwap {
+        swap_id: u64,
+        seller: String,
+    },
+    WithdrawSwapFill {
+        swap_id: u64,
+        buyer: String,
+    },
+    RequestLoan {
+        loan_id: u64,
+        collection: String,
+        token_id: String,
+        borrower: String,
+        principal: Uint128,
+        interest: Uint128,
+        payment: String,
+        duration: u64,
+    },
+    CancelLoan {
+        loan_id: u64,
+        borrower: String,
+    },
+    FundLoan {
+        loan_id: u64,
+        lender: String,
+        principal: Uint128,
+        payment: String,
+        expires_at: u64,
+    },
+    RepayLoan {
+        loan_id: u64,
+        borrower: String,
+        lender: String,
+        amount: Uint128,
+        payment: String,
+    },
+    ClaimCollateral {
+        loan_id: u64,
+        collection: String,
+        token_id: String,
+        lender: String,
+    },
+    ListRental {
+        collection: String,
+        token_id: String,
+        seller: String,
+        price: Uint128,
+        payment: String,
+        period: u64,
+    },
+    Rent {
+        collection: String,
+        token_id: String,
+        seller: String,
+        buyer: String,
+        price: Uint128,
+        payment: String,
+        fee: Uint128,
+        expires_at: u64,
+    },
+    ReclaimRental {
+        collection: String,
+        token_id: String,
+        seller: String,
+    },
+    Fractionalize {
+        vault_id: u64,
+        collection: String,
+        token_id: String,
+        seller: String,
+        supply: Uint128,
+    },
+    RedeemVault {
+        vault_id: u64,
+        collection: String,
+        token_id: String,
+        buyer: String,
+    },
+    Buyout {
+        vault_id: u64,
+        collection: String,
+        token_id: String,
+        buyer: String,
+        price: Uint128,
+        payment: String,
+    },
+    ClaimProceeds {
+        vault_id: u64,
+        owner: String,
+        amount: Uint128,
+        payment: String,
+    },
+    CreateCollection {
+        collection_id: u64,
+        collection: String,
+        seller: String,
+        price: Uint128,
+        payment: String,
+        max_supply: u64,
+    },
+    Mint {
+        collection_id: u64,
+        collection: String,
+        token_id: String,
+        seller: String,
+        buyer: String,
+        price: Uint128,
+        payment: String,
+        fee: Uint128,
+        /// Allowlist phase of the mint; public mints have none
+        phase_id: Option<u32>,
+        /// Nonce of the redeemed voucher, for mints of a creator-signed voucher
+        nonce: Option<u64>,
+    },
+    IbcTransfer {
+        collection: String,
+        token_id: String,
+        sender: String,
+        receiver: String,
+        channel_id: String,
+        class_id: String,
+    },
+    IbcReceive {
+        collection: String,
+        token_id: String,
+        receiver: String,
+        channel_id: String,
+        class_id: String,
+    },
+    IbcRefund {
+        collection: String,
+        token_id: String,
+        sender: String,
+        channel_id: String,
+    },
+    SetMintPhases {
+        collection_id: u64,
+        seller: String,
+        count: u64,
+    },
+    SetSigningKey {
+        seller: String,
+    },
+    CancelOrder {
+        seller: String,
+        nonce: u64,
+    },
+    CancelOrdersBelow {
+        seller: String,
+        nonce: u64,
+    },
+    UpdateConfig {
+        max_batch_size: u32,
+        max_royalty_bps: u64,
+        deposit_receipts: bool,
+    },
+    SetRoyalties {
+        collection: String,
+        total_bps: u64,
+    },
+    ProposeAdmin {
+        new_admin: String,
+        expires_at: u64,
+    },
+    ClaimAdmin {
+        admin: String,
+    },
+    DropAdminProposal {
+        admin: String,
+    },
+    SetReferrer {
+        referrer: String,
+        share_bps: u64,
+    },
+    RemoveReferrer {
+        referrer: String,
+    },
+    UpdateFees {
+        fee_bps: u64,
+        fee_recipient: String,
+    },
+    SetPaused {
+        paused: bool,
+    },
+    UpdateAdmin {
+        admin: String,
+    },
+}
+
+impl MarketplaceEvent {
+    /// The action of the event, e.g. `sale`; the event type is the action behind
+    /// `EVENT_TYPE_PREFIX`.
+    pub fn action(&self) -> &'static str {
+        match self {
+            MarketplaceEvent::Deposit { .. } => "deposit",
+            MarketplaceEvent::Withdraw { .. } => "withdraw",
+            MarketplaceEvent::List { .. } => "list",
+            MarketplaceEvent::Delist { .. } => "delist",
+            MarketplaceEvent::Sale { .. } => "sale",
+            MarketplaceEvent::ListBundle { .. } => "list-bundle",
+            MarketplaceEvent::DelistBundle { .. } => "delist-bundle",
+            MarketplaceEvent::SellBundle { .. } => "sell-bundle",
+            MarketplaceEvent::CreateSwap { .. } => "create-swap",
+            MarketplaceEvent::FillSwap { .. } => "fill-swap",
+            MarketplaceEvent::CancelSwap { .. } => "cancel-swap",
+            MarketplaceEvent::WithdrawSwapFill { .. } => "withdraw-swap-fill",
+            MarketplaceEvent::RequestLoan { .. } => "request-loan",
+            MarketplaceEvent::CancelLoan { .. } => "cancel-loan",
+            MarketplaceEvent::FundLoan { .. } => "fund-loan",
+            MarketplaceEvent::RepayLoan { .. } => "repay-loan",
+            MarketplaceEvent::ClaimCollateral { .. } => "claim-collateral",
+            MarketplaceEvent::ListRental { .. } => "list-rental",
+            MarketplaceEvent::Rent { .. } => "rent",
+            MarketplaceEvent::ReclaimRental { .. } => "reclaim-rental",
+            MarketplaceEvent::Fractionalize { .. } => "fractionalize",
+            MarketplaceEvent::RedeemVault { .. } => "redeem-vault",
+            MarketplaceEvent::Buyout { .. } => "buyout",
+            MarketplaceEvent::ClaimProceeds { .. } => "claim-proceeds",
+            MarketplaceEvent::CreateCollection { .. } => "create-collection",
+            MarketplaceEvent::Mint { .. } => "mint",
+            MarketplaceEvent::IbcTransfer { .. } => "ibc-transfer",
+            MarketplaceEvent::IbcReceive { .. } => "ibc-receive",
+            MarketplaceEvent::IbcRefund { .. } => "ibc-refund",
+            MarketplaceEvent::SetMintPhases { .. } => "set-mint-phases",
+            MarketplaceEvent::SetSigningKey { .. } => "set-signing-key",
+            MarketplaceEvent::CancelOrder { .. } => "cancel-order",
+            MarketplaceEvent::CancelOrdersBelow { .. } => "cancel-orders-below",
+            MarketplaceEvent::UpdateConfig { .. } => "update-config",
+            MarketplaceEvent::SetRoyalties { .. } => "set-royalties",
+            MarketplaceEvent::ProposeAdmin { .. } => "propose-admin",
+            MarketplaceEvent::ClaimAdmin { .. } => "claim-admin",
+            MarketplaceEvent::DropAdminProposal { .. } => "drop-admin-proposal",
+            MarketplaceEvent::SetReferrer { .. } => "set-referrer",
+            MarketplaceEvent::RemoveReferrer { .. } => "remove-referrer",
+            MarketplaceEvent::UpdateFees { .. } => "update-fees",
+            MarketplaceEvent::SetPaused { .. } => "set-paused",
+            MarketplaceEvent::UpdateAdmin { .. } => "update-admin",
+        }
+    }
+
+    /// Parses the marketplace events out of a transaction's events, skipping all others.
+    pub fn parse_all(events: &[Event]) -> StdResult<Vec<MarketplaceEvent>> {
+        events
+            .iter()
+            .filter(|event| action_of(event).is_some())
+            .map(MarketplaceEvent::try_from)
+            .collect()
+    }
+}
+
+/// Adds the referral attributes of a sale, which are only present when a referrer was paid.
+fn push_referral(
+    attrs: &mut Vec<(&'static str, String)>,
+    referrer: Option<String>,
+    referral_fee: Uint128,
+) {
+    if let Some(referrer) = referrer {
+        attrs.push(("referrer", referrer));
+        attrs.push(("referral_fee", referral_fee.to_string()));
+    }
+}
+
+/// The action of a marketplace event type, with or without the `wasm-` prefix added on chain.
+fn action_of(event: &Event) -> Option<&str> {
+    let ty = event.ty.strip_prefix("wasm-").unwrap_or(&event.ty);
+    ty.strip_prefix(EVENT_TYPE_PREFIX)
+}
+
+impl From<MarketplaceEvent> for Event {
+    fn from(event: MarketplaceEvent) -> Self {
+        let ty = format!("{}{}", EVENT_TYPE_PREFIX, event.action());
+        let attrs: Vec<(&str, String)> = match event {
+            MarketplaceEvent::Deposit {
+                owner,
+                payment,
+                amount,
+            }
+            | MarketplaceEvent::Withdraw {
+                owner,
+                payment,
+                amount,
+            } => vec![
+                ("owner", owner),
+                ("payment", payment),
+                ("amount", amount.to_string()),
+            ],
+            MarketplaceEvent::List {
+                collection,
+                token_id,
+                seller,
+                price,
+                payment,
+                reserved_for,
+                accepted,
+            } => {
+                let mut attrs = vec![
+                    ("collection", collection),
+                    ("token_id", token_id),
+                    ("seller", seller),
+                    ("price", price.to_string()),
+                    ("payment", payment),
+                ];
+                if let Some(buyer) = reserved_for {
+                    attrs.push(("reserved_for", buyer));
+                }
+                if !accepted.is_empty() {
+                    let json = to_vec(&accepted).expect("assets serialize to JSON");
+                    attrs.push(("accepted", String::from_utf8_lossy(&json).into_owned()));
+                }
+                attrs
+            }
+            MarketplaceEvent::Delist {
+                collection,
+                token_id,
+                seller,
+            }
+            | MarketplaceEvent::ReclaimRental {
+                collection,
+                token_id,
+                seller,
+            } => vec![
+                ("collection", collection),
+                ("token_id", token_id),
+                ("seller", seller),
+            ],
+            MarketplaceEvent::Sale {
+                sale_id,
+                collection,
+                token_id,
+                seller,
+                buyer,
+                price,
+                payment,
+                fee,
+                royalty,
+                referrer,
+                referral_fee,
+            } => {
+                let mut attrs = vec![
+                    ("sale_id", sale_id.to_string()),
+                    ("collection", collection),
+                    ("token_id", token_id),
+                    ("seller", seller),
+                    ("buyer", buyer),
+                    ("price", price.to_string()),
+                    ("payment", payment),
+                    ("fee", fee.to_string()),
+                    ("royalty", royalty.to_string()),
+                ];
+                push_referral(&mut attrs, referrer, referral_fee);
+                attrs
+            }
+            MarketplaceEvent::ListBundle {
+                bundle_id,
+                seller,
+                price,
+                payment,
+                count,
+            } => vec![
+                ("bundle_id", bundle_id.to_string()),
+                ("seller", seller),
+                ("price", price.to_string()),
+                ("payment", payment),
+                ("count", count.to_string()),
+            ],
+            MarketplaceEvent::DelistBundle { bundle_id, seller } => {
+                vec![("bundle_id", bundle_id.to_string()), ("seller", seller)]
+            }
+            MarketplaceEvent::SellBundle {
+                bundle_id,
+                seller,
+                buyer,
+                price,
+                payment,
+                fee,
+                referrer,
+                referral_fee,
+            } => {
+                let mut attrs = vec![
+                    ("bundle_id", bundle_id.to_string()),
+                    ("seller", seller),
+                    ("buyer", buyer),
+                    ("price", price.to_string()),
+                    ("payment", payment),
+                    ("fee", fee.to_string()),
+                ];
+                push_referral(&mut attrs, referrer, referral_fee);
+                attrs
+            }
+            MarketplaceEvent::CreateSwap { swap_id, seller }
+            | MarketplaceEvent::CancelSwap { swap_id, seller } => {
+                vec![("swap_id", swap_id.to_string()), ("seller", seller)]
+            }
+            MarketplaceEvent::FillSwap {
+                swap_id,
+                collection,
+                token_id,
+                buyer,
+                settled,
+            } => vec![
+                ("swap_id", swap_id.to_string()),
+                ("collection", collection),
+                ("token_id", token_id),
+                ("buyer", buyer),
+                ("settled", settled.to_string()),
+            ],
+            MarketplaceEvent::WithdrawSwapFill { swap_id, buyer } => {
+                vec![("swap_id", swap_id.to_string()), ("buyer", buyer)]
+            }
+            MarketplaceEvent::RequestLoan {
+                loan_id,
+                collection,
+                token_id,
+                borrower,
+                principal,
+                interest,
+                payment,
+                duration,
+            } => vec![
+                ("loan_id", loan_id.to_string()),
+                ("collection", collection),
+                ("token_id", token_id),
+                ("borrower", borrower),
+                ("principal", principal.to_string()),
+                ("interest", interest.to_string()),
+                ("payment", payment),
+                ("duration", duration.to_string()),
+            ],
+            MarketplaceEvent::CancelLoan { loan_id, borrower } => {
+                vec![("loan_id", loan_id.to_string()), ("borrower", borrower)]
+            }
+            MarketplaceEvent::FundLoan {
+                loan_id,
+                lender,
+                principal,
+                payment,
+                expires_at,
+            } => vec![
+                ("loan_id", loan_id.to_string()),
+                ("lender", lender),
+                ("principal", principal.to_string()),
+                ("payment", payment),
+                ("expires_at", expires_at.to_string()),
+            ],
+            MarketplaceEvent::RepayLoan {
+                loan_id,
+                borrower,
+                lender,
+                amount,
+                payment,
+            } => vec![
+                ("loan_id", loan_id.to_string()),
+                ("borrower", borrower),
+                ("lender", lender),
+                ("amount", amount.to_string()),
+                ("payment", payment),
+            ],
+            MarketplaceEvent::ClaimCollateral {
+                loan_id,
+                collection,
+                token_id,
+                lender,
+            } => vec![
+                ("loan_id", loan_id.to_string()),
+                ("collection", collection),
+                ("token_id", token_id),
+                ("lender", lender),
+            ],
+            MarketplaceEvent::ListRental {
+                collection,
+                token_id,
+                seller,
+                price,
+                payment,
+                period,
+            } => vec![
+                ("collection", collection),
+                ("token_id", token_id),
+                ("seller", seller),
+                ("price", price.to_string()),
+                ("payment", payment),
+                ("period", period.to_string()),
+            ],
+            MarketplaceEvent::Rent {
+                collection,
+                token_id,
+                seller,
+                buyer,
+                price,
+                payment,
+                fee,
+                expires_at,
+            } => vec![
+                ("collection", collection),
+                ("token_id", token_id),
+                ("seller", seller),
+                ("buyer", buyer),
+                ("price", price.to_string()),
+                ("payment", payment),
+                ("fee", fee.to_string()),
+                ("expires_at", expires_at.to_string()),
+            ],
+            MarketplaceEvent::Fractionalize {
+                vault_id,
+                collection,
+                token_id,
+                seller,
+                supply,
+            } => vec![
+                ("vault_id", vault_id.to_string()),
+                ("collection", collection),
+                ("token_id", token_id),
+                ("seller", seller),
+                ("supply", supply.to_string()),
+            ],
+            MarketplaceEvent::RedeemVault {
+                vault_id,
+                collection,
+                token_id,
+                buyer,
+            } => vec![
+                ("vault_id", vault_id.to_string()),
+                ("collection", collection),
+                ("token_id", token_id),
+                ("buyer", buyer),
+            ],
+            MarketplaceEvent::Buyout {
+                vault_id,
+                collection,
+                token_id,
+                buyer,
+                price,
+                payment,
+            } => vec![
+                ("vault_id", vault_id.to_string()),
+                ("collection", collection),
+                ("token_id", token_id),
+                ("buyer", buyer),
+                ("price", price.to_string()),
+                ("payment", payment),
+            ],
+            MarketplaceEvent::ClaimProceeds {
+                vault_id,
+                owner,
+                amount,
+                payment,
+            } => vec![
+                ("vault_id", vault_id.to_string()),
+                ("owner", owner),
+                ("amount", amount.to_string()),
+                ("payment", payment),
+            ],
+            MarketplaceEvent::CreateCollection {
+                collection_id,
+                collection,
+                seller,
+                price,
+                payment,
+                max_supply,
+            } => vec![
+                ("collection_id", collection_id.to_string()),
+                ("collection", collection),
+                ("seller", seller),
+                ("price", price.to_string()),
+                ("payment", payment),
+                ("max_supply", max_supply.to_string()),
+            ],
+            MarketplaceEvent::Mint {
+                collection_id,
+                collection,
+                token_id,
+                seller,
+                buyer,
+                price,
+                payment,
+                fee,
+                phase_id,
+                nonce,
+            } => {
+                let mut attrs = vec![
+                    ("collection_id", collection_id.to_string()),
+                    ("collection", collection),
+                    ("token_id", token_id),
+                    ("seller", seller),
+                    ("buyer", buyer),
+                    ("price", price.to_string()),
+                    ("payment", payment),
+                    ("fee", fee.to_string()),
+                ];
+                if let Some(phase_id) = phase_id {
+                    attrs.push(("phase_id", phase_id.to_string()));
+                }
+                if let Some(nonce) = nonce {
+                    attrs.push(("nonce", nonce.to_string()));
+                }
+                attrs
+            }
+            MarketplaceEvent::IbcTransfer {
+                collection,
+                token_id,
+                sender,
+                receiver,
+                channel_id,
+                class_id,
+            } => vec![
+                ("collection", collection),
+                ("token_id", token_id),
+                ("sender", sender),
+                ("receiver", receiver),
+                ("channel_id", channel_id),
+                ("class_id", class_id),
+            ],
+            MarketplaceEvent::IbcReceive {
+                collection,
+                token_id,
+                receiver,
+                channel_id,
+                class_id,
+            } => vec![
+                ("collection", collection),
+                ("token_id", token_id),
+                ("receiver", receiver),
+                ("channel_id", channel_id),
+                ("class_id", class_id),
+            ],
+            MarketplaceEvent::IbcRefund {
+                collection,
+                token_id,
+                sender,
+                channel_id,
+            } => vec![
+                ("collection", collection),
+                ("token_id", token_id),
+                ("sender", sender),
+                ("channel_id", channel_id),
+            ],
+            MarketplaceEvent::SetMintPhases {
+                collection_id,
+                seller,
+                count,
+            } => vec![
+                ("collection_id", collection_id.to_string()),
+                ("seller", seller),
+                ("count", count.to_string()),
+            ],
+            MarketplaceEvent::SetSigningKey { seller } => vec![("seller", seller)],
+            MarketplaceEvent::CancelOrder { seller, nonce }
+            | MarketplaceEvent::CancelOrdersBelow { seller, nonce } => {
+                vec![("seller", seller), ("nonce", nonce.to_string())]
+            }
+            MarketplaceEvent::UpdateConfig {
+                max_batch_size,
+                max_royalty_bps,
+                deposit_receipts,
+            } => vec![
+                ("max_batch_size", max_batch_size.to_string()),
+                ("max_royalty_bps", max_royalty_bps.to_string()),
+                ("deposit_receipts", deposit_receipts.to_string()),
+            ],
+            MarketplaceEvent::SetRoyalties {
+                collection,
+                total_bps,
+            } => vec![
+                ("collection", collection),
+                ("total_bps", total_bps.to_string()),
+            ],
+            MarketplaceEvent::ProposeAdmin {
+                new_admin,
+                expires_at,
+            } => vec![
+                ("new_admin", new_admin),
+                ("expires_at", expires_at.to_string()),
+            ],
+            MarketplaceEvent::ClaimAdmin { admin }
+            | MarketplaceEvent::DropAdminProposal { admin }
+            | MarketplaceEvent::UpdateAdmin { admin } => vec![("admin", admin)],
+            MarketplaceEvent::SetReferrer {
+                referrer,
+                share_bps,
+            } => vec![("referrer", referrer), ("share_bps", share_bps.to_string())],
+            MarketplaceEvent::RemoveReferrer { referrer } => vec![("referrer", referrer)],
+            MarketplaceEvent::UpdateFees {
+                fee_bps,
+                fee_recipient,
+            } => vec![
+                ("fee_bps", fee_bps.to_string()),
+                ("fee_recipient", fee_recipient),
+            ],
+            MarketplaceEvent::SetPaused { paused } => vec![("paused", paused.to_string())],
+        };
+
+        Event::new(ty).add_attributes(attrs)
+    }
+}
+
+impl TryFrom<&Event> for MarketplaceEvent {
+    type Error = StdError;
+
+    fn try_from(event: &Event) -> StdResult<Self> {
+        let action = action_of(event).ok_or_else(|| {
+            StdError::generic_err(format!("not a marketplace event: {}", event.ty))
+        })?;
+        let attrs = EventAttributes(event);
+
+        Ok(match action {
+            "deposit" => MarketplaceEvent::Deposit {
+                owner: attrs.string("owner")?,
+                payment: attrs.string("payment")?,
+                amount: attrs.uint("amount")?,
+            },
+            "withdraw" => MarketplaceEvent::Withdraw {
+                owner: attrs.string("owner")?,
+                payment: attrs.string("payment")?,
+                amount: attrs.uint("amount")?,
+            },
+            "list" => MarketplaceEvent::List {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                reserved_for: attrs.optional("reserved_for"),
+                accepted: match attrs.optional("accepted") {
+                    Some(json) => from_slice(json.as_bytes())?,
+                    None => vec![],
+                },
+            },
+            "delist" => MarketplaceEvent::Delist {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+            },
+            "sale" => MarketplaceEvent::Sale {
+                sale_id: attrs.parse("sale_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+                buyer: attrs.string("buyer")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                fee: attrs.uint("fee")?,
+                royalty: attrs.uint("royalty")?,
+                referrer: attrs.optional("referrer"),
+                referral_fee: attrs.uint_or_zero("referral_fee")?,
+            },
+            "list-bundle" => MarketplaceEvent::ListBundle {
+                bundle_id: attrs.parse("bundle_id")?,
+                seller: attrs.string("seller")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                count: attrs.parse("count")?,
+            },
+            "delist-bundle" => MarketplaceEvent::DelistBundle {
+                bundle_id: attrs.parse("bundle_id")?,
+                seller: attrs.string("seller")?,
+            },
+            "sell-bundle" => MarketplaceEvent::SellBundle {
+                bundle_id: attrs.parse("bundle_id")?,
+                seller: attrs.string("seller")?,
+                buyer: attrs.string("buyer")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                fee: attrs.uint("fee")?,
+                referrer: attrs.optional("referrer"),
+                referral_fee: attrs.uint_or_zero("referral_fee")?,
+            },
+            "create-swap" => MarketplaceEvent::CreateSwap {
+                swap_id: attrs.parse("swap_id")?,
+                seller: attrs.string("seller")?,
+            },
+            "fill-swap" => MarketplaceEvent::FillSwap {
+                swap_id: attrs.parse("swap_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                buyer: attrs.string("buyer")?,
+                settled: attrs.parse("settled")?,
+            },
+            "cancel-swap" => MarketplaceEvent::CancelSwap {
+                swap_id: attrs.parse("swap_id")?,
+                seller: attrs.string("seller")?,
+            },
+            "withdraw-swap-fill" => MarketplaceEvent::WithdrawSwapFill {
+                swap_id: attrs.parse("swap_id")?,
+                buyer: attrs.string("buyer")?,
+            },
+            "request-loan" => MarketplaceEvent::RequestLoan {
+                loan_id: attrs.parse("loan_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                borrower: attrs.string("borrower")?,
+                principal: attrs.uint("principal")?,
+                interest: attrs.uint("interest")?,
+                payment: attrs.string("payment")?,
+                duration: attrs.parse("duration")?,
+            },
+            "cancel-loan" => MarketplaceEvent::CancelLoan {
+                loan_id: attrs.parse("loan_id")?,
+                borrower: attrs.string("borrower")?,
+            },
+            "fund-loan" => MarketplaceEvent::FundLoan {
+                loan_id: attrs.parse("loan_id")?,
+                lender: attrs.string("lender")?,
+                principal: attrs.uint("principal")?,
+                payment: attrs.string("payment")?,
+                expires_at: attrs.parse("expires_at")?,
+            },
+            "repay-loan" => MarketplaceEvent::RepayLoan {
+                loan_id: attrs.parse("loan_id")?,
+                borrower: attrs.string("borrower")?,
+                lender: attrs.string("lender")?,
+                amount: attrs.uint("amount")?,
+                payment: attrs.string("payment")?,
+            },
+            "claim-collateral" => MarketplaceEvent::ClaimCollateral {
+                loan_id: attrs.parse("loan_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                lender: attrs.string("lender")?,
+            },
+            "list-rental" => MarketplaceEvent::ListRental {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                period: attrs.parse("period")?,
+            },
+            "rent" => MarketplaceEvent::Rent {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+                buyer: attrs.string("buyer")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                fee: attrs.uint("fee")?,
+                expires_at: attrs.parse("expires_at")?,
+            },
+            "reclaim-rental" => MarketplaceEvent::ReclaimRental {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+            },
+            "fractionalize" => MarketplaceEvent::Fractionalize {
+                vault_id: attrs.parse("vault_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+                supply: attrs.uint("supply")?,
+            },
+            "redeem-vault" => MarketplaceEvent::RedeemVault {
+                vault_id: attrs.parse("vault_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                buyer: attrs.string("buyer")?,
+            },
+            "buyout" => MarketplaceEvent::Buyout {
+                vault_id: attrs.parse("vault_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                buyer: attrs.string("buyer")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+            },
+            "claim-proceeds" => MarketplaceEvent::ClaimProceeds {
+                vault_id: attrs.parse("vault_id")?,
+                owner: attrs.string("owner")?,
+                amount: attrs.uint("amount")?,
+                payment: attrs.string("payment")?,
+            },
+            "create-collection" => MarketplaceEvent::CreateCollection {
+                collection_id: attrs.parse("collection_id")?,
+                collection: attrs.string("collection")?,
+                seller: attrs.string("seller")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                max_supply: attrs.parse("max_supply")?,
+            },
+            "mint" => MarketplaceEvent::Mint {
+                collection_id: attrs.parse("collection_id")?,
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                seller: attrs.string("seller")?,
+                buyer: attrs.string("buyer")?,
+                price: attrs.uint("price")?,
+                payment: attrs.string("payment")?,
+                fee: attrs.uint("fee")?,
+                phase_id: attrs
+                    .optional("phase_id")
+                    .map(|_| attrs.parse("phase_id"))
+                    .transpose()?,
+                nonce: attrs
+                    .optional("nonce")
+                    .map(|_| attrs.parse("nonce"))
+                    .transpose()?,
+            },
+            "ibc-transfer" => MarketplaceEvent::IbcTransfer {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                sender: attrs.string("sender")?,
+                receiver: attrs.string("receiver")?,
+                channel_id: attrs.string("channel_id")?,
+                class_id: attrs.string("class_id")?,
+            },
+            "ibc-receive" => MarketplaceEvent::IbcReceive {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                receiver: attrs.string("receiver")?,
+                channel_id: attrs.string("channel_id")?,
+                class_id: attrs.string("class_id")?,
+            },
+            "ibc-refund" => MarketplaceEvent::IbcRefund {
+                collection: attrs.string("collection")?,
+                token_id: attrs.string("token_id")?,
+                sender: attrs.string("sender")?,
+                channel_id: attrs.string("channel_id")?,
+            },
+            "set-mint-phases" => MarketplaceEvent::SetMintPhases {
+                collection_id: attrs.parse("collection_id")?,
+                seller: attrs.string("seller")?,
+                count: attrs.parse("count")?,
+            },
+            "set-signing-key" => MarketplaceEvent::SetSigningKey {
+                seller: attrs.string("seller")?,
+            },
+            "cancel-order" => MarketplaceEvent::CancelOrder {
+                seller: attrs.string("seller")?,
+                nonce: attrs.parse("nonce")?,
+            },
+            "cancel-orders-below" => MarketplaceEvent::CancelOrdersBelow {
+                seller: attrs.string("seller")?,
+                nonce: attrs.parse("nonce")?,
+            },
+            "update-config" => MarketplaceEvent::UpdateConfig {
+                max_batch_size: attrs.parse("max_batch_size")?,
+                max_royalty_bps: attrs.parse("max_royalty_bps")?,
+                deposit_receipts: attrs.parse("deposit_receipts")?,
+            },
+            "set-royalties" => MarketplaceEvent::SetRoyalties {
+                collection: attrs.string("collection")?,
+                total_bps: attrs.parse("total_bps")?,
+            },
+            "propose-admin" => MarketplaceEvent::ProposeAdmin {
+                new_admin: attrs.string("new_admin")?,
+                expires_at: attrs.parse("expires_at")?,
+            },
+            "claim-admin" => MarketplaceEvent::ClaimAdmin {
+                admin: attrs.string("admin")?,
+            },
+            "drop-admin-proposal" => MarketplaceEvent::DropAdminProposal {
+                admin: attrs.string("admin")?,
+            },
+            "set-referrer" => MarketplaceEvent::SetReferrer {
+                referrer: attrs.string("referrer")?,
+                share_bps: attrs.parse("share_bps")?,
+            },
+            "remove-referrer" => MarketplaceEvent::RemoveReferrer {
+                referrer: attrs.string("referrer")?,
+            },
+            "update-fees" => MarketplaceEvent::UpdateFees {
+                fee_bps: attrs.parse("fee_bps")?,
+                fee_recipient: attrs.string("fee_recipient")?,
+            },
+            "set-paused" => MarketplaceEvent::SetPaused {
+                paused: attrs.parse("paused")?,
+            },
+            "update-admin" => MarketplaceEvent::UpdateAdmin {
+                admin: attrs.string("admin")?,
+            },
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "unknown marketplace event: {}",
+                    event.ty
+                )))
+            }
+        })
+    }
+}
+
+struct EventAttributes<'a>(&'a Event);
+
+impl EventAttributes<'_> {
+    fn optional(&self, key: &str) -> Option<String> {
+        self.0
+            .attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+    }
+
+    fn string(&self, key: &str) -> StdResult<String> {
+        self.optional(key)
+            .ok_or_else(|| StdError::generic_err(format!("missing attribute: {}", key)))
+    }
+
+    fn uint(&self, key: &str) -> StdResult<Uint128> {
+        Uint128::from_str(&self.string(key)?)
+    }
+
+    fn uint_or_zero(&self, key: &str) -> StdResult<Uint128> {
+        match self.optional(key) {
+            Some(value) => Uint128::from_str(&value),
+            None => Ok(Uint128::zero()),
+        }
+    }
+
+    fn parse<T: FromStr>(&self, key: &str) -> StdResult<T>
+    where
+        T::Err: ToString,
+    {
+        self.string(key)?
+            .parse()
+            .map_err(|err: T::Err| StdError::parse_err(key, err))
+    }
+}
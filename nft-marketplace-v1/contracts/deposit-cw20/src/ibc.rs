@@ -0,0 +1,397 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, CosmosMsg, DepsMut, Env, Event, IbcBasicResponse, IbcChannel,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcOrder, IbcPacket,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Reply, Response,
+    StdError, StdResult, SubMsg, WasmMsg,
+};
+use cw_utils::parse_reply_instantiate_data;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::contract::nft_transfer_msgs;
+use crate::error::ContractError;
+use crate::events::MarketplaceEvent;
+use crate::state::{
+    NftItem, PendingVoucher, CONFIG, IBC_CHANNELS, IBC_ESCROW, PENDING_VOUCHER, VOUCHER_CLASS_IDS,
+    VOUCHER_CONTRACTS,
+};
+
+pub const ICS721_VERSION: &str = "ics721-1";
+pub const ICS721_ORDERING: IbcOrder = IbcOrder::Unordered;
+
+pub const INSTANTIATE_VOUCHER_REPLY_ID: u64 = 3;
+
+/// The ICS-721 packet, as sent between chains.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NonFungibleTokenPacketData {
+    /// Cw721 contract address for NFTs native to the sending chain, otherwise the full
+    /// `port/channel/...` path of the class
+    pub class_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_uri: Option<String>,
+    pub token_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_uris: Option<Vec<String>>,
+    pub sender: String,
+    pub receiver: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Ics721Ack {
+    Result(Binary),
+    Error(String),
+}
+
+fn ack_success() -> Binary {
+    to_binary(&Ics721Ack::Result(Binary::from(vec![1]))).unwrap()
+}
+
+fn ack_fail(err: String) -> Binary {
+    to_binary(&Ics721Ack::Error(err)).unwrap()
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<(), ContractError> {
+    enforce_order_and_version(msg.channel(), msg.counterparty_version())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.channel();
+    enforce_order_and_version(channel, msg.counterparty_version())?;
+    IBC_CHANNELS.save(deps.storage, &channel.endpoint.channel_id, channel)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_channel_connect")
+        .add_attribute("channel_id", channel.endpoint.channel_id.clone()))
+}
+
+fn enforce_order_and_version(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.order != ICS721_ORDERING {
+        return Err(ContractError::OnlyUnorderedChannel {});
+    }
+    if channel.version != ICS721_VERSION {
+        return Err(ContractError::InvalidIbcVersion {
+            version: channel.version.clone(),
+        });
+    }
+    if let Some(version) = counterparty_version {
+        if version != ICS721_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: version.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// NFTs may be escrowed on a channel, so channels are never closed.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Err(ContractError::CannotCloseChannel {})
+}
+
+/// Always acknowledges the packet; failures are returned as an error acknowledgement so the
+/// sending chain can refund the NFTs.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    do_ibc_packet_receive(deps, env, &msg.packet).or_else(|err| {
+        Ok(IbcReceiveResponse::new()
+            .set_ack(ack_fail(err.to_string()))
+            .add_attribute("action", "ibc_packet_receive")
+            .add_attribute("error", err.to_string()))
+    })
+}
+
+// Checks everything before writing to storage, since an error acknowledgement still commits
+fn do_ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    packet: &IbcPacket,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let data: NonFungibleTokenPacketData = from_binary(&packet.data)?;
+    let receiver = deps.api.addr_validate(&data.receiver)?.to_string();
+    let channel_id = packet.dest.channel_id.as_str();
+    let res = IbcReceiveResponse::new()
+        .set_ack(ack_success())
+        .add_attribute("action", "ibc_packet_receive");
+
+    // NFTs that left from this chain come back with the counterparty's prefix on the class id
+    let prefix = format!("{}/{}/", packet.src.port_id, packet.src.channel_id);
+    if let Some(cw721_contract) = data.class_id.strip_prefix(&prefix) {
+        let nfts: Vec<NftItem> = data
+            .token_ids
+            .iter()
+            .map(|token_id| NftItem {
+                cw721_contract: cw721_contract.to_string(),
+                token_id: token_id.clone(),
+            })
+            .collect();
+        for nft in &nfts {
+            let key = (channel_id, cw721_contract, nft.token_id.as_str());
+            if !IBC_ESCROW.has(deps.storage, key)
+                || VOUCHER_CLASS_IDS.has(deps.storage, cw721_contract)
+            {
+                return Err(ContractError::NftNotEscrowed {});
+            }
+        }
+        for nft in &nfts {
+            IBC_ESCROW.remove(deps.storage, (channel_id, cw721_contract, &nft.token_id));
+        }
+
+        return Ok(res
+            .add_events(receive_events(
+                cw721_contract,
+                &data.token_ids,
+                &receiver,
+                channel_id,
+                &data.class_id,
+            ))
+            .add_messages(nft_transfer_msgs(&nfts, &receiver)?));
+    }
+
+    let class_id = format!("{}/{}/{}", packet.dest.port_id, channel_id, data.class_id);
+    let mints: Vec<nft::contract::MintMsg<nft::contract::Extension>> = data
+        .token_ids
+        .iter()
+        .enumerate()
+        .map(|(i, token_id)| nft::contract::MintMsg {
+            token_id: token_id.clone(),
+            owner: receiver.clone(),
+            token_uri: data
+                .token_uris
+                .as_ref()
+                .and_then(|uris| uris.get(i).cloned()),
+            extension: None,
+        })
+        .collect();
+
+    match VOUCHER_CONTRACTS.may_load(deps.storage, &class_id)? {
+        Some(cw721_contract) => Ok(res
+            .add_events(receive_events(
+                &cw721_contract,
+                &data.token_ids,
+                &receiver,
+                channel_id,
+                &class_id,
+            ))
+            .add_messages(mint_msgs(&cw721_contract, mints)?)),
+        None => {
+            // the voucher collection is created first and the vouchers are minted in the reply
+            let code_id = CONFIG
+                .load(deps.storage)?
+                .nft_code_id
+                .ok_or(ContractError::NftCodeIdNotSet {})?;
+            let init_msg = nft::contract::InstantiateMsg {
+                name: class_id.clone(),
+                symbol: "ics721".to_string(),
+                minter: env.contract.address.to_string(),
+                soulbound: false,
+            };
+            let msg = WasmMsg::Instantiate {
+                admin: Some(env.contract.address.to_string()),
+                code_id,
+                msg: to_binary(&init_msg)?,
+                funds: vec![],
+                label: format!("ics721 voucher {}", class_id),
+            };
+            let pending = PendingVoucher {
+                class_id,
+                channel_id: channel_id.to_string(),
+                mints,
+            };
+            PENDING_VOUCHER.save(deps.storage, &pending)?;
+
+            Ok(res.add_submessage(SubMsg::reply_on_success(msg, INSTANTIATE_VOUCHER_REPLY_ID)))
+        }
+    }
+}
+
+pub fn reply_instantiate_voucher(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let res =
+        parse_reply_instantiate_data(msg).map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let pending = PENDING_VOUCHER.load(deps.storage)?;
+    PENDING_VOUCHER.remove(deps.storage);
+    VOUCHER_CONTRACTS.save(deps.storage, &pending.class_id, &res.contract_address)?;
+    VOUCHER_CLASS_IDS.save(deps.storage, &res.contract_address, &pending.class_id)?;
+
+    let token_ids: Vec<String> = pending
+        .mints
+        .iter()
+        .map(|mint| mint.token_id.clone())
+        .collect();
+    let receiver = pending
+        .mints
+        .first()
+        .map(|mint| mint.owner.clone())
+        .unwrap_or_default();
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate_voucher")
+        .add_attribute("class_id", pending.class_id.clone())
+        .add_attribute("voucher_contract", res.contract_address.clone())
+        .add_events(receive_events(
+            &res.contract_address,
+            &token_ids,
+            &receiver,
+            &pending.channel_id,
+            &pending.class_id,
+        ))
+        .add_messages(mint_msgs(&res.contract_address, pending.mints)?))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    match from_binary(&msg.acknowledgement.data)? {
+        Ics721Ack::Result(_) => on_packet_success(deps, &msg.original_packet),
+        Ics721Ack::Error(err) => {
+            Ok(refund_packet(deps, &msg.original_packet)?.add_attribute("error", err))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    refund_packet(deps, &msg.packet)
+}
+
+/// Native NFTs stay escrowed for the channel; vouchers that went back home are burned.
+fn on_packet_success(deps: DepsMut, packet: &IbcPacket) -> Result<IbcBasicResponse, ContractError> {
+    let data: NonFungibleTokenPacketData = from_binary(&packet.data)?;
+    let res = IbcBasicResponse::new()
+        .add_attribute("action", "ibc_packet_ack")
+        .add_attribute("class_id", data.class_id.clone());
+
+    let cw721_contract = match VOUCHER_CONTRACTS.may_load(deps.storage, &data.class_id)? {
+        Some(cw721_contract) => cw721_contract,
+        None => return Ok(res),
+    };
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for token_id in data.token_ids {
+        IBC_ESCROW.remove(
+            deps.storage,
+            (&packet.src.channel_id, &cw721_contract, &token_id),
+        );
+        msgs.push(
+            WasmMsg::Execute {
+                contract_addr: cw721_contract.clone(),
+                msg: to_binary(&nft::contract::ExecuteMsg::Burn { token_id })?,
+                funds: vec![],
+            }
+            .into(),
+        );
+    }
+
+    Ok(res.add_messages(msgs))
+}
+
+/// Returns the NFTs of a failed or timed out packet to their sender.
+fn refund_packet(deps: DepsMut, packet: &IbcPacket) -> Result<IbcBasicResponse, ContractError> {
+    let data: NonFungibleTokenPacketData = from_binary(&packet.data)?;
+    let cw721_contract = VOUCHER_CONTRACTS
+        .may_load(deps.storage, &data.class_id)?
+        .unwrap_or_else(|| data.class_id.clone());
+
+    let nfts: Vec<NftItem> = data
+        .token_ids
+        .into_iter()
+        .map(|token_id| NftItem {
+            cw721_contract: cw721_contract.clone(),
+            token_id,
+        })
+        .collect();
+    for nft in &nfts {
+        IBC_ESCROW.remove(
+            deps.storage,
+            (&packet.src.channel_id, &cw721_contract, &nft.token_id),
+        );
+    }
+
+    let sender = data.sender;
+    let events = nfts.iter().map(|nft| {
+        MarketplaceEvent::IbcRefund {
+            collection: nft.cw721_contract.clone(),
+            token_id: nft.token_id.clone(),
+            sender: sender.clone(),
+            channel_id: packet.src.channel_id.clone(),
+        }
+        .into()
+    });
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "refund_packet")
+        .add_events(events)
+        .add_messages(nft_transfer_msgs(&nfts, &sender)?))
+}
+
+fn receive_events(
+    cw721_contract: &str,
+    token_ids: &[String],
+    receiver: &str,
+    channel_id: &str,
+    class_id: &str,
+) -> Vec<Event> {
+    token_ids
+        .iter()
+        .map(|token_id| {
+            MarketplaceEvent::IbcReceive {
+                collection: cw721_contract.to_string(),
+                token_id: token_id.clone(),
+                receiver: receiver.to_string(),
+                channel_id: channel_id.to_string(),
+                class_id: class_id.to_string(),
+            }
+            .into()
+        })
+        .collect()
+}
+
+fn mint_msgs(
+    cw721_contract: &str,
+    mints: Vec<nft::contract::MintMsg<nft::contract::Extension>>,
+) -> StdResult<Vec<CosmosMsg>> {
+    mints
+        .into_iter()
+        .map(|mint| {
+            Ok(WasmMsg::Execute {
+                contract_addr: cw721_contract.to_string(),
+                msg: to_binary(&nft::contract::ExecuteMsg::Mint(mint))?,
+                funds: vec![],
+            }
+            .into())
+        })
+        .collect()
+}
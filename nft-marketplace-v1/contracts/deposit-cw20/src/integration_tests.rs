@@ -1,14 +1,22 @@
 #[cfg(test)]
 mod tests {
 
-    use crate::msg::{Cw20DepositResponse, QueryMsg};
-    use anyhow::Error;
-    use cosmwasm_std::{
-        from_binary, to_binary, Addr, Binary, Coin, Empty, StdError, StdResult, Uint128,
+    use crate::events::MarketplaceEvent;
+    use crate::msg::{
+        AllowlistProof, AskUpdate, AssetSolvency, CollectionStatsResponse, Cw20DepositResponse,
+        Cw721DepositResponse, DepositResponse, MintVoucher, QueryMsg, RoyaltiesResponse, SellOrder,
+        SolvencyResponse, SudoMsg,
     };
+    use crate::state::{Asset, AssetInfo, MintPhase, NftItem, Royalty};
+    use anyhow::Error;
+    use cosmwasm_std::{to_binary, Addr, Binary, Coin, Empty, Event, StdError, StdResult, Uint128};
     use cw20::Cw20Coin;
 
-    use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use serde::de::DeserializeOwned;
+    use sha2::{Digest, Sha256};
+
+    use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
 
     use crate::contract;
     use cw20_example::{self};
@@ -46,7 +54,9 @@ mod tests {
 
     fn contract_nft_marketplace() -> Box<dyn Contract<Empty>> {
         let contract =
-            ContractWrapper::new(contract::execute, contract::instantiate, contract::query);
+            ContractWrapper::new(contract::execute, contract::instantiate, contract::query)
+                .with_sudo(contract::sudo)
+                .with_reply(contract::reply);
         Box::new(contract)
     }
     fn contract_cw20() -> Box<dyn Contract<Empty>> {
@@ -92,9 +102,32 @@ mod tests {
         }
 
         fn instantiate_nft_marketplace(&mut self) -> Result<Addr, Error> {
+            self.instantiate_nft_marketplace_with(None, false)
+        }
+
+        /// Instantiates the marketplace with the cw20 code id it uses for share tokens.
+        fn instantiate_nft_marketplace_with_cw20_code_id(&mut self) -> Result<Addr, Error> {
+            self.instantiate_nft_marketplace_with(Some(self.cw20_id), false)
+        }
+
+        /// Instantiates the marketplace paying native deposits out as receipt tokens.
+        fn instantiate_nft_marketplace_with_receipts(&mut self) -> Result<Addr, Error> {
+            self.instantiate_nft_marketplace_with(Some(self.cw20_id), true)
+        }
+
+        fn instantiate_nft_marketplace_with(
+            &mut self,
+            cw20_code_id: Option<u64>,
+            deposit_receipts: bool,
+        ) -> Result<Addr, Error> {
             let code_id = self.nft_marketplace_id;
             let sender = Addr::unchecked(self.owner.clone());
-            let init_msg = crate::msg::InstantiateMsg {};
+            let init_msg = crate::msg::InstantiateMsg {
+                max_batch_size: None,
+                cw20_code_id,
+                nft_code_id: Some(self.cw721_id),
+                deposit_receipts: Some(deposit_receipts),
+            };
             let send_funds = vec![];
             let label = "nft_marketplace".to_string();
             let admin = Some(self.owner.clone());
@@ -104,11 +137,11 @@ mod tests {
         }
 
         fn instantiate_cw20(&mut self) -> Result<Addr, Error> {
-            let code_id = self.nft_marketplace_id;
+            let code_id = self.cw20_id;
             let sender = Addr::unchecked(self.owner.clone());
             let init_msg = cw20_base::msg::InstantiateMsg {
                 name: "new_cw20_token".to_string(),
-                symbol: "cw20".to_string(),
+                symbol: "CWT".to_string(),
                 decimals: 6,
                 initial_balances: vec![Cw20Coin {
                     address: USER.to_string(),
@@ -126,12 +159,12 @@ mod tests {
         }
 
         fn instantiate_cw721(&mut self) -> Result<Addr, Error> {
-            let code_id = self.nft_marketplace_id;
+            let code_id = self.cw721_id;
             let sender = Addr::unchecked(self.owner.clone());
             let init_msg = cw721_base::InstantiateMsg {
                 name: "cw721_project".to_string(),
                 symbol: "cw721".to_string(),
-                minter: String::from(USER.clone()),
+                minter: String::from(USER),
             };
             let send_funds = vec![];
             let label = "new_cw721_contract".to_string();
@@ -141,13 +174,175 @@ mod tests {
                 .instantiate_contract(code_id, sender, &init_msg, &send_funds, label, admin)
         }
 
-        fn smart_query(&self, contract_addr: String, msg: QueryMsg) -> Result<Binary, StdError> {
+        fn smart_query<T: DeserializeOwned>(
+            &self,
+            contract_addr: String,
+            msg: QueryMsg,
+        ) -> Result<T, StdError> {
             self.app.wrap().query_wasm_smart(contract_addr, &msg)
         }
 
         fn query_balance(&self, address: String, denom: String) -> Result<Coin, StdError> {
             self.app.wrap().query_balance(address, denom)
         }
+
+        fn query_cw20_balance(&self, cw20_addr: &Addr, address: &str) -> Uint128 {
+            let res: cw20::BalanceResponse = self
+                .app
+                .wrap()
+                .query_wasm_smart(
+                    cw20_addr,
+                    &cw20_base::msg::QueryMsg::Balance {
+                        address: address.to_string(),
+                    },
+                )
+                .unwrap();
+            res.balance
+        }
+
+        fn query_nft_owner(&self, cw721_addr: &Addr, token_id: &str) -> String {
+            let res: cw721::OwnerOfResponse = self
+                .app
+                .wrap()
+                .query_wasm_smart(
+                    cw721_addr,
+                    &nft::contract::QueryMsg::OwnerOf {
+                        token_id: token_id.to_string(),
+                        include_expired: None,
+                    },
+                )
+                .unwrap();
+            res.owner
+        }
+
+        /// Mints `token_id` to `owner`, who then deposits it into the marketplace.
+        fn mint_and_deposit(
+            &mut self,
+            cw721_addr: &Addr,
+            nft_marketplace_addr: &Addr,
+            owner: &str,
+            token_id: &str,
+        ) {
+            let msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+                token_id: token_id.to_string(),
+                owner: owner.to_string(),
+                token_uri: None,
+                extension: None,
+            });
+            self.app
+                .execute_contract(Addr::unchecked(USER), cw721_addr.clone(), &msg, &[])
+                .unwrap();
+
+            let msg = nft::contract::ExecuteMsg::SendNft {
+                contract: nft_marketplace_addr.to_string(),
+                token_id: token_id.to_string(),
+                msg: to_binary(&crate::msg::Cw721HookMsg::Deposit {
+                    owner: owner.to_string(),
+                    token_id: token_id.to_string(),
+                    cw20_contract: "cw20addr".to_string(),
+                    amount: 100,
+                    reserved_for: None,
+                    accepted: vec![],
+                })
+                .unwrap(),
+            };
+            self.app
+                .execute_contract(Addr::unchecked(owner), cw721_addr.clone(), &msg, &[])
+                .unwrap();
+        }
+
+        /// Sends cw20 tokens from `sender` to the marketplace with the given hook message.
+        fn send_cw20(
+            &mut self,
+            cw20_addr: &Addr,
+            nft_marketplace_addr: &Addr,
+            sender: &str,
+            amount: u128,
+            hook: &crate::msg::Cw20HookMsg,
+        ) -> Result<AppResponse, Error> {
+            let msg = cw20_base::msg::ExecuteMsg::Send {
+                contract: nft_marketplace_addr.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(hook).unwrap(),
+            };
+            self.app
+                .execute_contract(Addr::unchecked(sender), cw20_addr.clone(), &msg, &[])
+        }
+
+        /// USER deposits an NFT and splits it into 1000 shares, bought out for at least 5000 cw20.
+        fn setup_vault(&mut self) -> (Addr, Addr, Addr, Addr) {
+            let cw20_addr = self.instantiate_cw20().unwrap();
+            let cw721_addr = self.instantiate_cw721().unwrap();
+            let nft_marketplace_addr = self
+                .instantiate_nft_marketplace_with_cw20_code_id()
+                .unwrap();
+            self.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, USER, "1");
+
+            let msg = crate::msg::ExecuteMsg::Fractionalize {
+                cw721_contract: cw721_addr.to_string(),
+                token_id: "1".to_string(),
+                name: "vault shares".to_string(),
+                symbol: "SHARE".to_string(),
+                supply: 1000,
+                buyout_token: cw20_addr.to_string(),
+                buyout_price: 5000,
+            };
+            self.app
+                .execute_contract(
+                    Addr::unchecked(USER),
+                    nft_marketplace_addr.clone(),
+                    &msg,
+                    &[],
+                )
+                .unwrap();
+
+            let vault: crate::state::Vault = self
+                .smart_query(
+                    nft_marketplace_addr.to_string(),
+                    QueryMsg::Vault { vault_id: 1 },
+                )
+                .unwrap();
+            let share_addr = Addr::unchecked(vault.share_token);
+
+            (cw20_addr, cw721_addr, nft_marketplace_addr, share_addr)
+        }
+
+        fn transfer_cw20(&mut self, cw20_addr: &Addr, sender: &str, recipient: &str, amount: u128) {
+            let msg = cw20_base::msg::ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::new(amount),
+            };
+            self.app
+                .execute_contract(Addr::unchecked(sender), cw20_addr.clone(), &msg, &[])
+                .unwrap();
+        }
+
+        /// BUYER deposits an NFT and asks USER for a loan of 1000 plus 100 interest over a day.
+        fn setup_loan(&mut self) -> (Addr, Addr, Addr) {
+            let cw20_addr = self.instantiate_cw20().unwrap();
+            let cw721_addr = self.instantiate_cw721().unwrap();
+            let nft_marketplace_addr = self.instantiate_nft_marketplace().unwrap();
+            self.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, BUYER, "1");
+
+            let msg = crate::msg::ExecuteMsg::RequestLoan {
+                cw721_contract: cw721_addr.to_string(),
+                token_id: "1".to_string(),
+                cw20_contract: cw20_addr.to_string(),
+                principal: 1000,
+                interest: 100,
+                duration: 86400,
+            };
+            self.app
+                .execute_contract(
+                    Addr::unchecked(BUYER),
+                    nft_marketplace_addr.clone(),
+                    &msg,
+                    &[],
+                )
+                .unwrap();
+
+            (cw20_addr, cw721_addr, nft_marketplace_addr)
+        }
     }
 
     #[test]
@@ -308,7 +503,8 @@ mod tests {
             address: USER.to_string(),
         };
 
-        let res = suite.smart_query(cw20_addr.clone().to_string(), msg);
+        let res: StdResult<Cw20DepositResponse> =
+            suite.smart_query(cw20_addr.clone().to_string(), msg);
 
         match res {
             Err(_) => {}
@@ -323,8 +519,8 @@ mod tests {
             amount: 100,
         };
         let cw20_msg = cw20::Cw20ReceiveMsg {
-            sender: nft_marketplace_addr.clone().to_string(),
-            amount: Uint128::new(0),
+            sender: USER.to_string(),
+            amount: Uint128::new(100),
             msg: to_binary(&cw20_hook).unwrap(),
         };
         let msg = crate::msg::ExecuteMsg::Receive(cw20_msg);
@@ -354,12 +550,10 @@ mod tests {
         let msg = crate::msg::QueryMsg::GetCw20Deposit {
             address: suite.owner.clone().to_string(),
         };
-        let res = suite
-            .smart_query(cw20_addr.clone().to_string(), msg)
+        let value: Cw20DepositResponse = suite
+            .smart_query(nft_marketplace_addr.clone().to_string(), msg)
             .unwrap();
 
-        let value: Cw20DepositResponse = from_binary(&res).unwrap();
-
         println!("VALUE: {:?}", value);
         assert_eq!(value.deposits[0].owner.clone(), USER.to_string());
 
@@ -418,4 +612,1807 @@ mod tests {
         assert_eq!(res.denom, "utest".to_string());
         assert_eq!(res.amount, Uint128::new(999_999_999)); */
     }
+
+    #[test]
+    fn test_batch_withdraw_nft() {
+        let mut suite = Suite::init().unwrap();
+        let cw721_addr = suite.instantiate_cw721().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+
+        //MINT TWO NFTS AND DEPOSIT THEM INTO THE NFT MARKETPLACE
+        for token_id in ["1", "2"] {
+            let msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+                token_id: token_id.to_string(),
+                owner: USER.to_string(),
+                token_uri: None,
+                extension: None,
+            });
+            suite
+                .app
+                .execute_contract(Addr::unchecked(USER), cw721_addr.clone(), &msg, &[])
+                .unwrap();
+
+            let msg = nft::contract::ExecuteMsg::SendNft {
+                contract: nft_marketplace_addr.to_string(),
+                token_id: token_id.to_string(),
+                msg: to_binary(&crate::msg::Cw721HookMsg::Deposit {
+                    owner: USER.to_string(),
+                    token_id: token_id.to_string(),
+                    cw20_contract: "cw20addr".to_string(),
+                    amount: 100,
+                    reserved_for: None,
+                    accepted: vec![],
+                })
+                .unwrap(),
+            };
+            suite
+                .app
+                .execute_contract(Addr::unchecked(USER), cw721_addr.clone(), &msg, &[])
+                .unwrap();
+        }
+
+        //A BATCH WITH ONE TOKEN THAT WAS NEVER DEPOSITED FAILS AS A WHOLE
+        let msg = crate::msg::ExecuteMsg::BatchWithdrawNft {
+            nfts: vec![
+                NftItem {
+                    cw721_contract: cw721_addr.to_string(),
+                    token_id: "1".to_string(),
+                },
+                NftItem {
+                    cw721_contract: cw721_addr.to_string(),
+                    token_id: "3".to_string(),
+                },
+            ],
+        };
+        let res = suite.app.execute_contract(
+            Addr::unchecked(USER),
+            nft_marketplace_addr.clone(),
+            &msg,
+            &[],
+        );
+        assert!(res.is_err());
+
+        let msg = QueryMsg::GetCw721Deposit {
+            address: USER.to_string(),
+            contract: cw721_addr.to_string(),
+        };
+        let value: Cw721DepositResponse = suite
+            .smart_query(nft_marketplace_addr.to_string(), msg)
+            .unwrap();
+        assert_eq!(value.deposits.len(), 2);
+
+        //WITHDRAW BOTH NFTS IN ONE TRANSACTION
+        let msg = crate::msg::ExecuteMsg::BatchWithdrawNft {
+            nfts: vec![
+                NftItem {
+                    cw721_contract: cw721_addr.to_string(),
+                    token_id: "1".to_string(),
+                },
+                NftItem {
+                    cw721_contract: cw721_addr.to_string(),
+                    token_id: "2".to_string(),
+                },
+            ],
+        };
+        let res = suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+        assert!(
+            res.has_event(&Event::new("wasm-marketplace-delist").add_attribute("token_id", "2"))
+        );
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert_eq!(
+            events[1],
+            MarketplaceEvent::Delist {
+                collection: cw721_addr.to_string(),
+                token_id: "2".to_string(),
+                seller: USER.to_string(),
+            }
+        );
+
+        for token_id in ["1", "2"] {
+            let res: cw721::OwnerOfResponse = suite
+                .app
+                .wrap()
+                .query_wasm_smart(
+                    cw721_addr.clone(),
+                    &nft::contract::QueryMsg::OwnerOf {
+                        token_id: token_id.to_string(),
+                        include_expired: None,
+                    },
+                )
+                .unwrap();
+            assert_eq!(res.owner, USER.to_string());
+        }
+    }
+
+    #[test]
+    fn test_loan_funded_and_repaid() {
+        let mut suite = Suite::init().unwrap();
+        let (cw20_addr, cw721_addr, nft_marketplace_addr) = suite.setup_loan();
+
+        //THE COLLATERAL CAN NO LONGER BE WITHDRAWN
+        let msg = crate::msg::ExecuteMsg::WithdrawNft {
+            cw721_contract: cw721_addr.to_string(),
+            token_id: "1".to_string(),
+        };
+        let res = suite.app.execute_contract(
+            Addr::unchecked(BUYER),
+            nft_marketplace_addr.clone(),
+            &msg,
+            &[],
+        );
+        assert!(res.is_err());
+
+        //FUNDING WITH LESS THAN THE PRINCIPAL FAILS
+        let fund = crate::msg::Cw20HookMsg::FundLoan { loan_id: 1 };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 999, &fund);
+        assert!(res.is_err());
+
+        //NO LOAN CAN BE FUNDED WHILE TRADING IS PAUSED
+        let msg = SudoMsg::SetPaused { paused: true };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &fund);
+        assert!(res.is_err());
+        let msg = SudoMsg::SetPaused { paused: false };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+
+        //THE LENDER FUNDS THE LOAN AND THE PRINCIPAL GOES TO THE BORROWER
+        suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &fund)
+            .unwrap();
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, BUYER),
+            Uint128::new(1000)
+        );
+
+        let loan: crate::state::Loan = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::Loan { loan_id: 1 },
+            )
+            .unwrap();
+        assert_eq!(loan.lender, Some(USER.to_string()));
+
+        //A FUNDED LOAN CANNOT BE CANCELLED OR FUNDED AGAIN
+        let msg = crate::msg::ExecuteMsg::CancelLoan { loan_id: 1 };
+        let res = suite.app.execute_contract(
+            Addr::unchecked(BUYER),
+            nft_marketplace_addr.clone(),
+            &msg,
+            &[],
+        );
+        assert!(res.is_err());
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &fund);
+        assert!(res.is_err());
+
+        //THE BORROWER REPAYS PRINCIPAL PLUS INTEREST AND GETS THE NFT BACK
+        let msg = cw20_base::msg::ExecuteMsg::Transfer {
+            recipient: BUYER.to_string(),
+            amount: Uint128::new(100),
+        };
+        suite
+            .app
+            .execute_contract(Addr::unchecked(USER), cw20_addr.clone(), &msg, &[])
+            .unwrap();
+
+        let repay = crate::msg::Cw20HookMsg::RepayLoan { loan_id: 1 };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, BUYER, 1000, &repay);
+        assert!(res.is_err());
+        suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, BUYER, 1100, &repay)
+            .unwrap();
+
+        assert_eq!(suite.query_cw20_balance(&cw20_addr, BUYER), Uint128::zero());
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, USER),
+            Uint128::new(1_000_000)
+        );
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), BUYER.to_string());
+
+        let res: StdResult<crate::state::Loan> = suite.smart_query(
+            nft_marketplace_addr.to_string(),
+            QueryMsg::Loan { loan_id: 1 },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_loan_collateral_claimed_after_expiry() {
+        let mut suite = Suite::init().unwrap();
+        let (cw20_addr, cw721_addr, nft_marketplace_addr) = suite.setup_loan();
+
+        //AN UNFUNDED LOAN CANNOT BE CLAIMED
+        let claim = crate::msg::ExecuteMsg::ClaimCollateral { loan_id: 1 };
+        let res = suite.app.execute_contract(
+            Addr::unchecked(USER),
+            nft_marketplace_addr.clone(),
+            &claim,
+            &[],
+        );
+        assert!(res.is_err());
+
+        let fund = crate::msg::Cw20HookMsg::FundLoan { loan_id: 1 };
+        suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &fund)
+            .unwrap();
+
+        //THE LENDER HAS TO WAIT FOR THE LOAN TO EXPIRE
+        let res = suite.app.execute_contract(
+            Addr::unchecked(USER),
+            nft_marketplace_addr.clone(),
+            &claim,
+            &[],
+        );
+        assert!(res.is_err());
+
+        suite
+            .app
+            .update_block(|block| block.time = block.time.plus_seconds(86400));
+
+        //THE BORROWER CAN NO LONGER REPAY
+        let repay = crate::msg::Cw20HookMsg::RepayLoan { loan_id: 1 };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, BUYER, 1000, &repay);
+        assert!(res.is_err());
+
+        //ONLY THE LENDER CAN CLAIM THE COLLATERAL
+        let res = suite.app.execute_contract(
+            Addr::unchecked(BUYER),
+            nft_marketplace_addr.clone(),
+            &claim,
+            &[],
+        );
+        assert!(res.is_err());
+
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &claim,
+                &[],
+            )
+            .unwrap();
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), USER.to_string());
+
+        let msg = QueryMsg::GetCw721Deposit {
+            address: BUYER.to_string(),
+            contract: cw721_addr.to_string(),
+        };
+        let res: StdResult<Cw721DepositResponse> =
+            suite.smart_query(nft_marketplace_addr.to_string(), msg);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_cancel_unfunded_loan() {
+        let mut suite = Suite::init().unwrap();
+        let (cw20_addr, cw721_addr, nft_marketplace_addr) = suite.setup_loan();
+
+        let msg = crate::msg::ExecuteMsg::CancelLoan { loan_id: 1 };
+        let res = suite.app.execute_contract(
+            Addr::unchecked(USER),
+            nft_marketplace_addr.clone(),
+            &msg,
+            &[],
+        );
+        assert!(res.is_err());
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        //LOANS WITHOUT A PRINCIPAL OR WITH AN UNREPAYABLE AMOUNT CANNOT BE REQUESTED
+        for (principal, interest) in [(0, 100), (u128::MAX, 1)] {
+            let msg = crate::msg::ExecuteMsg::RequestLoan {
+                cw721_contract: cw721_addr.to_string(),
+                token_id: "1".to_string(),
+                cw20_contract: cw20_addr.to_string(),
+                principal,
+                interest,
+                duration: 86400,
+            };
+            let res = suite.app.execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            );
+            assert!(res.is_err());
+        }
+
+        //THE NFT IS STILL DEPOSITED AND CAN BE WITHDRAWN AGAIN
+        let msg = crate::msg::ExecuteMsg::WithdrawNft {
+            cw721_contract: cw721_addr.to_string(),
+            token_id: "1".to_string(),
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), BUYER.to_string());
+    }
+
+    #[test]
+    fn test_fractionalize_and_redeem() {
+        let mut suite = Suite::init().unwrap();
+        let (cw20_addr, cw721_addr, nft_marketplace_addr, share_addr) = suite.setup_vault();
+
+        //VAULTS WITHOUT SHARES OR A BUYOUT PRICE CANNOT BE CREATED
+        suite.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, USER, "2");
+        for (supply, buyout_price) in [(0, 5000), (1000, 0)] {
+            let msg = crate::msg::ExecuteMsg::Fractionalize {
+                cw721_contract: cw721_addr.to_string(),
+                token_id: "2".to_string(),
+                name: "vault shares".to_string(),
+                symbol: "SHARE".to_string(),
+                supply,
+                buyout_token: cw20_addr.to_string(),
+                buyout_price,
+            };
+            let res = suite.app.execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            );
+            assert!(res.is_err());
+        }
+
+        //THE NFT IS HELD BY THE MARKETPLACE AND THE CURATOR HOLDS ALL SHARES
+        assert_eq!(
+            suite.query_nft_owner(&cw721_addr, "1"),
+            nft_marketplace_addr.to_string()
+        );
+        assert_eq!(
+            suite.query_cw20_balance(&share_addr, USER),
+            Uint128::new(1000)
+        );
+
+        //PART OF THE SUPPLY CANNOT REDEEM THE NFT
+        suite.transfer_cw20(&share_addr, USER, BUYER, 400);
+        let redeem = crate::msg::Cw20HookMsg::RedeemVault { vault_id: 1 };
+        let res = suite.send_cw20(&share_addr, &nft_marketplace_addr, USER, 600, &redeem);
+        assert!(res.is_err());
+
+        //THE WHOLE SUPPLY REDEEMS THE NFT AND THE SHARES ARE BURNED
+        suite.transfer_cw20(&share_addr, BUYER, USER, 400);
+        suite
+            .send_cw20(&share_addr, &nft_marketplace_addr, USER, 1000, &redeem)
+            .unwrap();
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), USER.to_string());
+
+        let info: cw20::TokenInfoResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&share_addr, &cw20_base::msg::QueryMsg::TokenInfo {})
+            .unwrap();
+        assert_eq!(info.total_supply, Uint128::zero());
+    }
+
+    #[test]
+    fn test_vault_buyout_and_claims() {
+        let mut suite = Suite::init().unwrap();
+        let (cw20_addr, cw721_addr, nft_marketplace_addr, share_addr) = suite.setup_vault();
+        suite.transfer_cw20(&share_addr, USER, BUYER, 250);
+        suite.transfer_cw20(&cw20_addr, USER, BUYER, 10_000);
+
+        //AN OFFER BELOW THE BUYOUT PRICE FAILS
+        let buyout = crate::msg::Cw20HookMsg::Buyout { vault_id: 1 };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, BUYER, 4999, &buyout);
+        assert!(res.is_err());
+
+        //THE BUYOUT FORCES THE SALE OF THE NFT
+        suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, BUYER, 5001, &buyout)
+            .unwrap();
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), BUYER.to_string());
+
+        let redeem = crate::msg::Cw20HookMsg::RedeemVault { vault_id: 1 };
+        let res = suite.send_cw20(&share_addr, &nft_marketplace_addr, USER, 750, &redeem);
+        assert!(res.is_err());
+
+        //SHARE HOLDERS CLAIM THEIR PRO-RATA PROCEEDS
+        let claim = crate::msg::Cw20HookMsg::ClaimProceeds { vault_id: 1 };
+        suite
+            .send_cw20(&share_addr, &nft_marketplace_addr, BUYER, 250, &claim)
+            .unwrap();
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, BUYER),
+            Uint128::new(10_000 - 5001 + 1250)
+        );
+
+        suite
+            .send_cw20(&share_addr, &nft_marketplace_addr, USER, 750, &claim)
+            .unwrap();
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, USER),
+            Uint128::new(1_000_000 - 10_000 + 3751)
+        );
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, nft_marketplace_addr.as_str()),
+            Uint128::zero()
+        );
+
+        let res: StdResult<crate::state::Vault> = suite.smart_query(
+            nft_marketplace_addr.to_string(),
+            QueryMsg::Vault { vault_id: 1 },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_native_deposit_receipts() {
+        let mut suite = Suite::init().unwrap();
+        let deposit = crate::msg::ExecuteMsg::Deposit {};
+
+        //A CW20 CODE ID FOR VAULTS ALONE KEEPS NATIVE DEPOSITS IN THE LEDGER
+        let nft_marketplace_addr = suite
+            .instantiate_nft_marketplace_with_cw20_code_id()
+            .unwrap();
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &deposit,
+                &[Coin::new(1000, "utest")],
+            )
+            .unwrap();
+        let res: DepositResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::GetDeposits {
+                    address: USER.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.deposits[0].amount, Coin::new(1000, "utest"));
+
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace_with_receipts().unwrap();
+
+        //THE FIRST DEPOSIT OF A DENOM INSTANTIATES ITS RECEIPT TOKEN
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &deposit,
+                &[Coin::new(1000, "utest")],
+            )
+            .unwrap();
+        let res: crate::msg::ReceiptTokenResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::ReceiptToken {
+                    denom: "utest".to_string(),
+                },
+            )
+            .unwrap();
+        let receipt_addr = Addr::unchecked(res.receipt_token.unwrap());
+
+        //LATER DEPOSITS MINT MORE RECEIPTS FROM THE SAME TOKEN
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &deposit,
+                &[Coin::new(500, "utest")],
+            )
+            .unwrap();
+        assert_eq!(
+            suite.query_cw20_balance(&receipt_addr, USER),
+            Uint128::new(1000)
+        );
+        assert_eq!(
+            suite.query_cw20_balance(&receipt_addr, BUYER),
+            Uint128::new(500)
+        );
+
+        //ONLY THE MARKETPLACE CAN MINT RECEIPTS
+        let msg = cw20_base::msg::ExecuteMsg::Mint {
+            recipient: USER.to_string(),
+            amount: Uint128::new(1),
+        };
+        let res =
+            suite
+                .app
+                .execute_contract(Addr::unchecked(USER), receipt_addr.clone(), &msg, &[]);
+        assert!(res.is_err());
+
+        //RECEIPTS MOVE THE DEPOSITED VALUE AND ARE BURNED ON REDEMPTION
+        suite.transfer_cw20(&receipt_addr, USER, BUYER, 300);
+        let redeem = crate::msg::Cw20HookMsg::RedeemReceipt {};
+        suite
+            .send_cw20(&receipt_addr, &nft_marketplace_addr, BUYER, 800, &redeem)
+            .unwrap();
+
+        let res = suite
+            .query_balance(BUYER.to_string(), "utest".to_string())
+            .unwrap();
+        assert_eq!(res.amount, Uint128::new(1_000_000_000 - 500 + 800));
+        let res = suite
+            .query_balance(nft_marketplace_addr.to_string(), "utest".to_string())
+            .unwrap();
+        assert_eq!(res.amount, Uint128::new(700));
+
+        let info: cw20::TokenInfoResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&receipt_addr, &cw20_base::msg::QueryMsg::TokenInfo {})
+            .unwrap();
+        assert_eq!(info.total_supply, Uint128::new(700));
+        assert_eq!(info.symbol, "RUTEST");
+    }
+
+    #[test]
+    fn test_solvency() {
+        let mut suite = Suite::init().unwrap();
+        let cw20_addr = suite.instantiate_cw20().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        let solvency = |suite: &Suite| -> SolvencyResponse {
+            suite
+                .smart_query(
+                    nft_marketplace_addr.to_string(),
+                    QueryMsg::Solvency {
+                        start_after: None,
+                        limit: None,
+                    },
+                )
+                .unwrap()
+        };
+
+        //DEPOSITS AND WITHDRAWALS MOVE THE TOTALS
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &crate::msg::ExecuteMsg::Deposit {},
+                &[Coin::new(1000, "utest")],
+            )
+            .unwrap();
+        let hook = crate::msg::Cw20HookMsg::Deposit {
+            owner: USER.to_string(),
+            amount: 500,
+        };
+        suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 500, &hook)
+            .unwrap();
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &crate::msg::ExecuteMsg::Withdraw {
+                    amount: 400,
+                    denom: "utest".to_string(),
+                },
+                &[],
+            )
+            .unwrap();
+
+        let res = solvency(&suite);
+        assert!(res.solvent);
+        assert_eq!(
+            res.assets,
+            vec![
+                AssetSolvency {
+                    info: AssetInfo::Cw20 {
+                        contract: cw20_addr.to_string(),
+                    },
+                    liabilities: 500,
+                    balance: 500,
+                    shortfall: 0,
+                },
+                AssetSolvency {
+                    info: AssetInfo::Native {
+                        denom: "utest".to_string(),
+                    },
+                    liabilities: 600,
+                    balance: 600,
+                    shortfall: 0,
+                },
+            ]
+        );
+
+        //A DEPOSIT RECORDED WITHOUT THE TOKENS ARRIVING SHOWS UP AS A SHORTFALL
+        let cw20_msg = cw20::Cw20ReceiveMsg {
+            sender: USER.to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&hook).unwrap(),
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &crate::msg::ExecuteMsg::Receive(cw20_msg),
+                &[],
+            )
+            .unwrap();
+
+        let res = solvency(&suite);
+        assert!(!res.solvent);
+        assert_eq!(
+            res.assets[1].info,
+            AssetInfo::Cw20 {
+                contract: BUYER.to_string(),
+            }
+        );
+        assert_eq!(res.assets[1].balance, 0);
+        assert_eq!(res.assets[1].shortfall, 500);
+        assert_eq!(res.assets[0].shortfall, 0);
+    }
+
+    #[test]
+    fn test_sudo_governance_controls() {
+        let mut suite = Suite::init().unwrap();
+        let cw20_addr = suite.instantiate_cw20().unwrap();
+        let cw721_addr = suite.instantiate_cw721().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        suite.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, BUYER, "1");
+        suite.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, BUYER, "2");
+        let msg = crate::msg::ExecuteMsg::BatchUpdateAsks {
+            asks: vec![AskUpdate {
+                cw721_contract: cw721_addr.to_string(),
+                token_id: "1".to_string(),
+                cw20_contract: cw20_addr.to_string(),
+                amount: 100,
+                reserved_for: None,
+                accepted: vec![],
+            }],
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        //FEES ARE CAPPED AT 100%
+        let msg = SudoMsg::UpdateFees {
+            fee_bps: 10_001,
+            fee_recipient: None,
+        };
+        assert!(suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .is_err());
+        let msg = SudoMsg::UpdateFees {
+            fee_bps: 250,
+            fee_recipient: Some("fee_collector".to_string()),
+        };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+
+        //NOTHING CAN BE SOLD WHILE TRADING IS PAUSED
+        let msg = SudoMsg::SetPaused { paused: true };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+        let purchase = crate::msg::Cw20HookMsg::Purchase {
+            token_id: "1".to_string(),
+            cw721_contract: cw721_addr.to_string(),
+            referrer: None,
+        };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 100, &purchase);
+        assert!(res.is_err());
+
+        //ONCE RESUMED THE SALE PAYS THE FEE TO THE FEE RECIPIENT
+        let msg = SudoMsg::SetPaused { paused: false };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+        let res = suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 100, &purchase)
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Sale { fee, .. } if *fee == Uint128::new(2)
+        ));
+        for (address, amount) in [(BUYER, 98), ("fee_collector", 2)] {
+            let res: Cw20DepositResponse = suite
+                .smart_query(
+                    nft_marketplace_addr.to_string(),
+                    QueryMsg::GetCw20Deposit {
+                        address: address.to_string(),
+                    },
+                )
+                .unwrap();
+            assert_eq!(res.deposits[0].amount, amount);
+        }
+
+        //GOVERNANCE CAN REPLACE THE ADMIN AND TAKE DOWN LISTINGS
+        let msg = SudoMsg::UpdateAdmin {
+            admin: BUYER.to_string(),
+        };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+        let res: crate::msg::ConfigResponse = suite
+            .smart_query(nft_marketplace_addr.to_string(), QueryMsg::GetConfig {})
+            .unwrap();
+        assert_eq!(res.admin, BUYER.to_string());
+        assert_eq!(res.fee_bps, 250);
+        assert!(!res.paused);
+
+        let msg = SudoMsg::ForceDelist {
+            cw721_contract: cw721_addr.to_string(),
+            token_id: "2".to_string(),
+        };
+        let res = suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+        assert!(
+            res.has_event(&Event::new("wasm-marketplace-delist").add_attribute("token_id", "2"))
+        );
+        let purchase = crate::msg::Cw20HookMsg::Purchase {
+            token_id: "2".to_string(),
+            cw721_contract: cw721_addr.to_string(),
+            referrer: None,
+        };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 100, &purchase);
+        assert!(res.is_err());
+        assert!(suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .is_err());
+    }
+
+    #[test]
+    fn test_multi_asset_ask() {
+        let mut suite = Suite::init().unwrap();
+        let cw20_addr = suite.instantiate_cw20().unwrap();
+        let cw721_addr = suite.instantiate_cw721().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        suite.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, BUYER, "1");
+        suite.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, BUYER, "2");
+
+        //ONE ASK ACCEPTS 100 CW20ADDR, 95 OF THE CW20 OR 50 UTEST
+        let ask = |token_id: &str, accepted: Vec<Asset>| AskUpdate {
+            cw721_contract: cw721_addr.to_string(),
+            token_id: token_id.to_string(),
+            cw20_contract: "cw20addr".to_string(),
+            amount: 100,
+            reserved_for: None,
+            accepted,
+        };
+        let accepted = vec![
+            Asset {
+                info: AssetInfo::Cw20 {
+                    contract: cw20_addr.to_string(),
+                },
+                amount: 95,
+            },
+            Asset {
+                info: AssetInfo::Native {
+                    denom: "utest".to_string(),
+                },
+                amount: 50,
+            },
+        ];
+
+        //EACH ASSET MAY ONLY BE PRICED ONCE
+        let mut duplicated = accepted.clone();
+        duplicated.push(accepted[1].clone());
+        let msg = crate::msg::ExecuteMsg::BatchUpdateAsks {
+            asks: vec![ask("1", duplicated)],
+        };
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[]
+            )
+            .is_err());
+
+        let msg = crate::msg::ExecuteMsg::BatchUpdateAsks {
+            asks: vec![ask("1", accepted.clone()), ask("2", accepted)],
+        };
+        let res = suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::List { accepted, .. } if accepted.len() == 2
+        ));
+
+        //THE ASKS COUNT TOWARDS THE LISTINGS AND FLOOR PRICE OF EVERY ACCEPTED ASSET
+        let res: CollectionStatsResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::CollectionStats {
+                    cw721_contract: cw721_addr.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.listing_count, 2);
+        assert_eq!(res.payment_tokens.len(), 3);
+        for stats in &res.payment_tokens {
+            assert_eq!(stats.listing_count, 2);
+            let floor = match stats.payment_token.as_str() {
+                "cw20addr" => 100,
+                "utest" => 50,
+                _ => 95,
+            };
+            assert_eq!(stats.floor_price, Some(floor));
+        }
+
+        //THE ALTERNATIVE CW20 SETTLES THE SALE AT ITS OWN PRICE
+        let purchase = crate::msg::Cw20HookMsg::Purchase {
+            token_id: "1".to_string(),
+            cw721_contract: cw721_addr.to_string(),
+            referrer: None,
+        };
+        let res = suite.send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 100, &purchase);
+        assert!(res.is_err());
+        let res = suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 95, &purchase)
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert_eq!(
+            events[0],
+            MarketplaceEvent::Sale {
+                sale_id: 1,
+                collection: cw721_addr.to_string(),
+                token_id: "1".to_string(),
+                seller: BUYER.to_string(),
+                buyer: USER.to_string(),
+                price: Uint128::new(95),
+                payment: cw20_addr.to_string(),
+                fee: Uint128::zero(),
+                royalty: Uint128::zero(),
+                referrer: None,
+                referral_fee: Uint128::zero(),
+            }
+        );
+        let res: Cw20DepositResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::GetCw20Deposit {
+                    address: BUYER.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.deposits[0].amount, 95);
+
+        //THE NATIVE PATH PAYS WITH ATTACHED FUNDS
+        let purchase = crate::msg::ExecuteMsg::Purchase {
+            cw721_contract: cw721_addr.to_string(),
+            token_id: "2".to_string(),
+            referrer: None,
+        };
+        for funds in [Coin::new(40, "utest"), Coin::new(50, "uother")] {
+            assert!(suite
+                .app
+                .execute_contract(
+                    Addr::unchecked(USER),
+                    nft_marketplace_addr.clone(),
+                    &purchase,
+                    &[funds],
+                )
+                .is_err());
+        }
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &purchase,
+                &[Coin::new(50, "utest")],
+            )
+            .unwrap();
+        let res: DepositResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::GetDeposits {
+                    address: BUYER.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.deposits[0].amount, Coin::new(50, "utest"));
+
+        let res: CollectionStatsResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::CollectionStats {
+                    cw721_contract: cw721_addr.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.listing_count, 0);
+        assert!(res
+            .payment_tokens
+            .iter()
+            .all(|stats| stats.listing_count == 0 && stats.floor_price.is_none()));
+    }
+
+    #[test]
+    fn test_collection_royalties() {
+        let mut suite = Suite::init().unwrap();
+        let cw20_addr = suite.instantiate_cw20().unwrap();
+        let cw721_addr = suite.instantiate_cw721().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        suite.mint_and_deposit(&cw721_addr, &nft_marketplace_addr, BUYER, "1");
+        let msg = crate::msg::ExecuteMsg::BatchUpdateAsks {
+            asks: vec![AskUpdate {
+                cw721_contract: cw721_addr.to_string(),
+                token_id: "1".to_string(),
+                cw20_contract: cw20_addr.to_string(),
+                amount: 1000,
+                reserved_for: None,
+                accepted: vec![],
+            }],
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        //ONLY THE CW721 MINTER OR THE ADMIN REGISTERS ROYALTIES, UP TO THE GLOBAL MAXIMUM
+        let set_royalties = |artist_bps| crate::msg::ExecuteMsg::SetRoyalties {
+            cw721_contract: cw721_addr.to_string(),
+            royalties: vec![
+                Royalty {
+                    recipient: "artist".to_string(),
+                    bps: artist_bps,
+                },
+                Royalty {
+                    recipient: "studio".to_string(),
+                    bps: 200,
+                },
+            ],
+        };
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &set_royalties(300),
+                &[]
+            )
+            .is_err());
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &set_royalties(900),
+                &[]
+            )
+            .is_err());
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &set_royalties(0),
+                &[]
+            )
+            .is_err());
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &set_royalties(300),
+                &[],
+            )
+            .unwrap();
+        let res: RoyaltiesResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::Royalties {
+                    cw721_contract: cw721_addr.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.royalties.len(), 2);
+
+        //LOWERING THE MAXIMUM SCALES DOWN ROYALTIES ALREADY REGISTERED
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            max_batch_size: None,
+            cw20_code_id: None,
+            nft_code_id: None,
+            max_royalty_bps: Some(250),
+            deposit_receipts: None,
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(suite.owner.clone()),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        let purchase = crate::msg::Cw20HookMsg::Purchase {
+            token_id: "1".to_string(),
+            cw721_contract: cw721_addr.to_string(),
+            referrer: None,
+        };
+        let res = suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &purchase)
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Sale { royalty, .. } if *royalty == Uint128::new(25)
+        ));
+        for (address, amount) in [(BUYER, 975), ("artist", 15), ("studio", 10)] {
+            let res: Cw20DepositResponse = suite
+                .smart_query(
+                    nft_marketplace_addr.to_string(),
+                    QueryMsg::GetCw20Deposit {
+                        address: address.to_string(),
+                    },
+                )
+                .unwrap();
+            assert_eq!(res.deposits[0].amount, amount);
+        }
+    }
+
+    #[test]
+    fn test_cw2981_royalties() {
+        let mut suite = Suite::init().unwrap();
+        let cw20_addr = suite.instantiate_cw20().unwrap();
+        let cw721_addr = suite.instantiate_cw721().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+
+        //THE TOKEN CARRIES ITS ROYALTY, NOTHING IS REGISTERED IN THE MARKETPLACE
+        let msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+            token_id: "1".to_string(),
+            owner: BUYER.to_string(),
+            token_uri: None,
+            extension: Some(nft::contract::Metadata {
+                royalty_payment_address: Some("artist".to_string()),
+                royalty_percentage: Some(5),
+                ..nft::contract::Metadata::default()
+            }),
+        });
+        suite
+            .app
+            .execute_contract(Addr::unchecked(USER), cw721_addr.clone(), &msg, &[])
+            .unwrap();
+        let msg = nft::contract::ExecuteMsg::SendNft {
+            contract: nft_marketplace_addr.to_string(),
+            token_id: "1".to_string(),
+            msg: to_binary(&crate::msg::Cw721HookMsg::Deposit {
+                owner: BUYER.to_string(),
+                token_id: "1".to_string(),
+                cw20_contract: cw20_addr.to_string(),
+                amount: 1000,
+                reserved_for: None,
+                accepted: vec![],
+            })
+            .unwrap(),
+        };
+        suite
+            .app
+            .execute_contract(Addr::unchecked(BUYER), cw721_addr.clone(), &msg, &[])
+            .unwrap();
+
+        let purchase = crate::msg::Cw20HookMsg::Purchase {
+            token_id: "1".to_string(),
+            cw721_contract: cw721_addr.to_string(),
+            referrer: None,
+        };
+        let res = suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &purchase)
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Sale { royalty, .. } if *royalty == Uint128::new(50)
+        ));
+        for (address, amount) in [(BUYER, 950), ("artist", 50)] {
+            let res: Cw20DepositResponse = suite
+                .smart_query(
+                    nft_marketplace_addr.to_string(),
+                    QueryMsg::GetCw20Deposit {
+                        address: address.to_string(),
+                    },
+                )
+                .unwrap();
+            assert_eq!(res.deposits[0].amount, amount);
+        }
+    }
+
+    #[test]
+    fn test_launchpad_collection() {
+        let mut suite = Suite::init().unwrap();
+        let cw20_addr = suite.instantiate_cw20().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        let msg = SudoMsg::UpdateFees {
+            fee_bps: 500,
+            fee_recipient: Some("fee_collector".to_string()),
+        };
+        suite
+            .app
+            .wasm_sudo(nft_marketplace_addr.clone(), &msg)
+            .unwrap();
+
+        //THE CREATOR LAUNCHES A COLLECTION MINTED BY THE MARKETPLACE
+        let start_time = suite.app.block_info().time.plus_seconds(100);
+        let create = |mint_price: Asset, max_supply| crate::msg::ExecuteMsg::CreateCollection {
+            name: "launch".to_string(),
+            symbol: "LNCH".to_string(),
+            mint_price,
+            max_supply,
+            start_time,
+        };
+        let native_price = Asset {
+            info: AssetInfo::Native {
+                denom: "utest".to_string(),
+            },
+            amount: 100,
+        };
+        let res = suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &create(native_price, 2),
+                &[],
+            )
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        let cw721_addr = match &events[0] {
+            MarketplaceEvent::CreateCollection {
+                collection_id: 1,
+                collection,
+                seller,
+                ..
+            } if seller == BUYER => Addr::unchecked(collection),
+            event => panic!("unexpected event: {:?}", event),
+        };
+        let minter: nft::contract::MinterResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&cw721_addr, &nft::contract::QueryMsg::Minter {})
+            .unwrap();
+        assert_eq!(minter.minter, nft_marketplace_addr.to_string());
+
+        //BUYERS MINT FROM THE START TIME ON, UNTIL THE SUPPLY RUNS OUT
+        let mint = crate::msg::ExecuteMsg::Mint {
+            collection_id: 1,
+            allowlist: None,
+        };
+        let mint_with = |suite: &mut Suite, funds: Coin| {
+            suite.app.execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &mint,
+                &[funds],
+            )
+        };
+        assert!(mint_with(&mut suite, Coin::new(100, "utest")).is_err());
+        suite.app.update_block(|block| block.time = start_time);
+        assert!(mint_with(&mut suite, Coin::new(90, "utest")).is_err());
+
+        let creator_balance = suite
+            .query_balance(BUYER.to_string(), "utest".to_string())
+            .unwrap();
+        mint_with(&mut suite, Coin::new(100, "utest")).unwrap();
+        mint_with(&mut suite, Coin::new(100, "utest")).unwrap();
+        assert!(mint_with(&mut suite, Coin::new(100, "utest")).is_err());
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), USER);
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "2"), USER);
+
+        //THE CREATOR GETS THE PROCEEDS LESS THE FEE
+        let res = suite
+            .query_balance(BUYER.to_string(), "utest".to_string())
+            .unwrap();
+        assert_eq!(res.amount, creator_balance.amount + Uint128::new(190));
+        let res = suite
+            .query_balance("fee_collector".to_string(), "utest".to_string())
+            .unwrap();
+        assert_eq!(res.amount, Uint128::new(10));
+
+        //COLLECTIONS CAN ALSO BE PRICED IN A CW20
+        let cw20_price = Asset {
+            info: AssetInfo::Cw20 {
+                contract: cw20_addr.to_string(),
+            },
+            amount: 1000,
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &create(cw20_price, 1),
+                &[],
+            )
+            .unwrap();
+        let mint = crate::msg::Cw20HookMsg::Mint {
+            collection_id: 2,
+            allowlist: None,
+        };
+        suite
+            .send_cw20(&cw20_addr, &nft_marketplace_addr, USER, 1000, &mint)
+            .unwrap();
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, BUYER),
+            Uint128::new(950)
+        );
+        assert_eq!(
+            suite.query_cw20_balance(&cw20_addr, "fee_collector"),
+            Uint128::new(50)
+        );
+
+        let res: crate::msg::CollectionsResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::Collections {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            res.collections
+                .iter()
+                .map(|collection| collection.minted)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn test_allowlist_mint_phases() {
+        let mut suite = Suite::init().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        let now = suite.app.block_info().time;
+        let price = |amount| Asset {
+            info: AssetInfo::Native {
+                denom: "utest".to_string(),
+            },
+            amount,
+        };
+        let msg = crate::msg::ExecuteMsg::CreateCollection {
+            name: "launch".to_string(),
+            symbol: "LNCH".to_string(),
+            mint_price: price(100),
+            max_supply: 5,
+            start_time: now.plus_seconds(1000),
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        //A TWO LEAF TREE: EACH ADDRESS PROVES ITSELF WITH THE OTHER'S LEAF
+        let leaf = |address: &str| -> [u8; 32] { Sha256::digest(address.as_bytes()).into() };
+        let (first, second) = if leaf(USER) < leaf(BUYER) {
+            (leaf(USER), leaf(BUYER))
+        } else {
+            (leaf(BUYER), leaf(USER))
+        };
+        let root = Sha256::new().chain(first).chain(second).finalize();
+        let phase = |merkle_root: String| MintPhase {
+            merkle_root,
+            price: price(60),
+            per_address_limit: 1,
+            start_time: now,
+            end_time: now.plus_seconds(500),
+        };
+        let set_phases = |merkle_root: &str| crate::msg::ExecuteMsg::SetMintPhases {
+            collection_id: 1,
+            phases: vec![phase(merkle_root.to_string())],
+        };
+
+        //ONLY THE CREATOR SETS PHASES, WITH A VALID ROOT
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &set_phases(&hex::encode(root)),
+                &[]
+            )
+            .is_err());
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &set_phases("not a root"),
+                &[]
+            )
+            .is_err());
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &set_phases(&hex::encode(root)),
+                &[],
+            )
+            .unwrap();
+        let res: crate::msg::MintPhasesResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::MintPhases { collection_id: 1 },
+            )
+            .unwrap();
+        assert_eq!(res.phases, vec![phase(hex::encode(root))]);
+
+        //ALLOWLISTED ADDRESSES MINT AT THE PHASE PRICE, UP TO THEIR LIMIT
+        let mint = |proof: Vec<[u8; 32]>| crate::msg::ExecuteMsg::Mint {
+            collection_id: 1,
+            allowlist: Some(AllowlistProof {
+                phase_id: 0,
+                proof: proof.iter().map(hex::encode).collect(),
+            }),
+        };
+        let res = suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &mint(vec![leaf(BUYER)]),
+                &[Coin::new(60, "utest")],
+            )
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Mint { phase_id: Some(0), price, .. } if *price == Uint128::new(60)
+        ));
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &mint(vec![leaf(BUYER)]),
+                &[Coin::new(60, "utest")],
+            )
+            .is_err());
+        let res: crate::msg::MintAllowanceResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::MintAllowance {
+                    collection_id: 1,
+                    phase_id: 0,
+                    address: USER.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!((res.minted, res.remaining), (1, 0));
+
+        //A WRONG PROOF IS REJECTED, AND THE SCHEDULE IS FIXED ONCE MINTING STARTED
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &mint(vec![]),
+                &[Coin::new(60, "utest")],
+            )
+            .is_err());
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &set_phases(&hex::encode(root)),
+                &[]
+            )
+            .is_err());
+
+        //THE PHASE CLOSES AT ITS END TIME, AND THE PUBLIC SALE OPENS AT THE START TIME
+        suite
+            .app
+            .update_block(|block| block.time = now.plus_seconds(1000));
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &mint(vec![leaf(USER)]),
+                &[Coin::new(60, "utest")],
+            )
+            .is_err());
+        let msg = crate::msg::ExecuteMsg::Mint {
+            collection_id: 1,
+            allowlist: None,
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[Coin::new(100, "utest")],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mint_vouchers() {
+        let mut suite = Suite::init().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        let now = suite.app.block_info().time;
+        let price = |amount| Asset {
+            info: AssetInfo::Native {
+                denom: "utest".to_string(),
+            },
+            amount,
+        };
+        let msg = crate::msg::ExecuteMsg::CreateCollection {
+            name: "launch".to_string(),
+            symbol: "LNCH".to_string(),
+            mint_price: price(100),
+            max_supply: 5,
+            start_time: now.plus_seconds(1000),
+        };
+        let res = suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        let cw721_addr = match &events[0] {
+            MarketplaceEvent::CreateCollection { collection, .. } => Addr::unchecked(collection),
+            event => panic!("unexpected event: {:?}", event),
+        };
+
+        let key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let voucher = |token_id: &str, amount, nonce| MintVoucher {
+            collection_id: 1,
+            token_id: token_id.to_string(),
+            token_uri: Some("ipfs://voucher".to_string()),
+            extension: None,
+            price: price(amount),
+            nonce,
+        };
+        let sign = |voucher: &MintVoucher| -> Binary {
+            let bytes = voucher.sign_bytes(nft_marketplace_addr.as_str()).unwrap();
+            let signature: Signature = key.sign(&bytes);
+            Binary::from(signature.as_ref())
+        };
+        let redeem =
+            |voucher: MintVoucher, signature: Binary| crate::msg::ExecuteMsg::RedeemVoucher {
+                voucher: Box::new(voucher),
+                signature,
+            };
+
+        //VOUCHERS NEED THE CREATOR TO HAVE REGISTERED A KEY
+        let first = voucher("voucher-1", 200, 1);
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(first.clone(), sign(&first)),
+                &[Coin::new(200, "utest")],
+            )
+            .is_err());
+        let msg = crate::msg::ExecuteMsg::SetSigningKey {
+            pubkey: Binary::from(key.verifying_key().to_bytes().as_slice()),
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        //A SIGNED VOUCHER MINTS ITS TOKEN BEFORE THE PUBLIC SALE AND PAYS THE CREATOR
+        let creator_balance = suite
+            .query_balance(BUYER.to_string(), "utest".to_string())
+            .unwrap();
+        let res = suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(first.clone(), sign(&first)),
+                &[Coin::new(200, "utest")],
+            )
+            .unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Mint { nonce: Some(1), token_id, .. } if token_id == "voucher-1"
+        ));
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "voucher-1"), USER);
+        let res = suite
+            .query_balance(BUYER.to_string(), "utest".to_string())
+            .unwrap();
+        assert_eq!(res.amount, creator_balance.amount + Uint128::new(200));
+        let res: crate::msg::NonceUsedResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::NonceUsed {
+                    creator: BUYER.to_string(),
+                    nonce: 1,
+                },
+            )
+            .unwrap();
+        assert!(res.used);
+
+        //A VOUCHER IS REDEEMED ONCE, EVEN UNDER ANOTHER TOKEN ID
+        let replay = voucher("voucher-2", 200, 1);
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(replay.clone(), sign(&replay)),
+                &[Coin::new(200, "utest")],
+            )
+            .is_err());
+
+        //TAMPERED VOUCHERS AND WRONG PAYMENTS ARE REJECTED
+        let second = voucher("voucher-2", 200, 2);
+        let cheap = voucher("voucher-2", 20, 2);
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(cheap, sign(&second)),
+                &[Coin::new(20, "utest")],
+            )
+            .is_err());
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(second.clone(), sign(&second)),
+                &[Coin::new(100, "utest")],
+            )
+            .is_err());
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(second.clone(), sign(&second)),
+                &[Coin::new(200, "utest")],
+            )
+            .unwrap();
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "voucher-2"), USER);
+
+        //VOUCHERS CANNOT TAKE TOKEN IDS THE PUBLIC MINT WILL HAND OUT LATER
+        let numeric = voucher("5", 200, 3);
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &redeem(numeric.clone(), sign(&numeric)),
+                &[Coin::new(200, "utest")],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_signed_sell_orders() {
+        let mut suite = Suite::init().unwrap();
+        let nft_marketplace_addr = suite.instantiate_nft_marketplace().unwrap();
+        let cw721_addr = suite.instantiate_cw721().unwrap();
+        let now = suite.app.block_info().time;
+
+        //BUYER OWNS THE NFT AND SIGNS ORDERS WITHOUT DEPOSITING IT
+        let msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+            token_id: "1".to_string(),
+            owner: BUYER.to_string(),
+            token_uri: None,
+            extension: None,
+        });
+        suite
+            .app
+            .execute_contract(Addr::unchecked(USER), cw721_addr.clone(), &msg, &[])
+            .unwrap();
+        let key = SigningKey::from_bytes(&[9u8; 32]).unwrap();
+        let msg = crate::msg::ExecuteMsg::SetSigningKey {
+            pubkey: Binary::from(key.verifying_key().to_bytes().as_slice()),
+        };
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(BUYER),
+                nft_marketplace_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+
+        let order = |nonce, expires| SellOrder {
+            seller: BUYER.to_string(),
+            cw721_contract: cw721_addr.to_string(),
+            token_id: "1".to_string(),
+            price: Asset {
+                info: AssetInfo::Native {
+                    denom: "utest".to_string(),
+                },
+                amount: 500,
+            },
+            expires,
+            nonce,
+        };
+        let fill = |order: SellOrder| {
+            let bytes = order.sign_bytes(nft_marketplace_addr.as_str()).unwrap();
+            let signature: Signature = key.sign(&bytes);
+            crate::msg::ExecuteMsg::FillOrder {
+                order: Box::new(order),
+                signature: Binary::from(signature.as_ref()),
+                referrer: None,
+            }
+        };
+        let fill_with = |suite: &mut Suite, msg: &crate::msg::ExecuteMsg| {
+            suite.app.execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                msg,
+                &[Coin::new(500, "utest")],
+            )
+        };
+
+        //THE MARKETPLACE MUST BE APPROVED FOR THE TOKEN
+        assert!(fill_with(&mut suite, &fill(order(5, now.plus_seconds(100)))).is_err());
+        let msg = nft::contract::ExecuteMsg::Approve {
+            spender: nft_marketplace_addr.to_string(),
+            token_id: "1".to_string(),
+            expires: None,
+        };
+        suite
+            .app
+            .execute_contract(Addr::unchecked(BUYER), cw721_addr.clone(), &msg, &[])
+            .unwrap();
+
+        //EXPIRED, TAMPERED AND CANCELLED ORDERS ARE REJECTED
+        assert!(fill_with(&mut suite, &fill(order(5, now))).is_err());
+        let mut tampered = fill(order(5, now.plus_seconds(100)));
+        if let crate::msg::ExecuteMsg::FillOrder { order, .. } = &mut tampered {
+            order.price.amount = 1;
+        }
+        assert!(suite
+            .app
+            .execute_contract(
+                Addr::unchecked(USER),
+                nft_marketplace_addr.clone(),
+                &tampered,
+                &[Coin::new(1, "utest")],
+            )
+            .is_err());
+        let cancel = |msg: crate::msg::ExecuteMsg, suite: &mut Suite| {
+            suite
+                .app
+                .execute_contract(
+                    Addr::unchecked(BUYER),
+                    nft_marketplace_addr.clone(),
+                    &msg,
+                    &[],
+                )
+                .unwrap();
+        };
+        cancel(crate::msg::ExecuteMsg::CancelOrder { nonce: 7 }, &mut suite);
+        cancel(
+            crate::msg::ExecuteMsg::CancelOrdersBelow { nonce: 5 },
+            &mut suite,
+        );
+        assert!(fill_with(&mut suite, &fill(order(4, now.plus_seconds(100)))).is_err());
+        assert!(fill_with(&mut suite, &fill(order(7, now.plus_seconds(100)))).is_err());
+
+        //A VALID ORDER SETTLES LIKE A LISTED SALE AND CANNOT BE REPLAYED
+        let res = fill_with(&mut suite, &fill(order(5, now.plus_seconds(100)))).unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Sale { seller, buyer, price, .. }
+                if seller == BUYER && buyer == USER && *price == Uint128::new(500)
+        ));
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "1"), USER);
+        let res: DepositResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::GetDeposits {
+                    address: BUYER.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.deposits[0].amount, Coin::new(500, "utest"));
+        let res: crate::msg::NonceUsedResponse = suite
+            .smart_query(
+                nft_marketplace_addr.to_string(),
+                QueryMsg::OrderNonceUsed {
+                    seller: BUYER.to_string(),
+                    nonce: 5,
+                },
+            )
+            .unwrap();
+        assert!(res.used);
+        assert!(fill_with(&mut suite, &fill(order(5, now.plus_seconds(100)))).is_err());
+
+        //APPROVING THE MARKETPLACE AS OPERATOR COVERS ALL OF THE SELLER'S TOKENS
+        let msg = nft::contract::ExecuteMsg::Mint(nft::contract::MintMsg {
+            token_id: "2".to_string(),
+            owner: BUYER.to_string(),
+            token_uri: None,
+            extension: None,
+        });
+        suite
+            .app
+            .execute_contract(Addr::unchecked(USER), cw721_addr.clone(), &msg, &[])
+            .unwrap();
+        let mut second = order(8, now.plus_seconds(100));
+        second.token_id = "2".to_string();
+        assert!(fill_with(&mut suite, &fill(second.clone())).is_err());
+        let msg = nft::contract::ExecuteMsg::ApproveAll {
+            operator: nft_marketplace_addr.to_string(),
+            expires: None,
+        };
+        suite
+            .app
+            .execute_contract(Addr::unchecked(BUYER), cw721_addr.clone(), &msg, &[])
+            .unwrap();
+        fill_with(&mut suite, &fill(second)).unwrap();
+        assert_eq!(suite.query_nft_owner(&cw721_addr, "2"), USER);
+    }
 }
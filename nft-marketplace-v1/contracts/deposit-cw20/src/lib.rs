@@ -1,6 +1,8 @@
 pub mod contract;
 mod error;
+pub mod events;
 pub mod helpers;
+pub mod ibc;
 pub mod integration_tests;
 pub mod msg;
 pub mod state;
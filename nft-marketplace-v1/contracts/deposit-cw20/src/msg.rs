@@ -1,14 +1,28 @@
+use cosmwasm_std::{to_vec, Binary, StdResult, Timestamp};
 use cw20::Cw20ReceiveMsg;
 
 use cw721::Cw721ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{Cw20Deposit, Cw721Deposit, Deposit};
+use crate::state::{
+    Asset, AssetInfo, Bundle, Collection, Cw20Deposit, Cw721Deposit, Deposit, Loan, MintPhase,
+    NftItem, Offer, Royalty, Sale, Swap, Vault,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub struct InstantiateMsg {}
+pub struct InstantiateMsg {
+    /// Maximum number of items accepted by the batch messages. Defaults to 20.
+    pub max_batch_size: Option<u32>,
+    /// Code id of `cw20-example`, needed to fractionalize NFTs and to mint deposit receipts
+    pub cw20_code_id: Option<u64>,
+    /// Code id of the `nft` contract, needed to receive NFTs over ICS-721
+    pub nft_code_id: Option<u64>,
+    /// Pay native deposits out as cw20 receipt tokens instead of recording them in the deposit
+    /// ledger. Requires `cw20_code_id`. Defaults to false.
+    pub deposit_receipts: Option<bool>,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -20,22 +34,428 @@ pub enum ExecuteMsg {
     },
     Receive(Cw20ReceiveMsg),
     ReceiveNft(Cw721ReceiveMsg),
+    /// Withdraws cw20 tokens the sender deposited or earned
     WithdrawCw20 {
-        owner: String,
+        cw20_contract: String,
         amount: u128,
     },
     WithdrawNft {
         cw721_contract: String,
         token_id: String,
     },
+    BatchWithdrawNft {
+        nfts: Vec<NftItem>,
+    },
+    BatchWithdraw {
+        withdrawals: Vec<Withdrawal>,
+    },
+    BatchUpdateAsks {
+        asks: Vec<AskUpdate>,
+    },
+    UpdateConfig {
+        max_batch_size: Option<u32>,
+        cw20_code_id: Option<u64>,
+        nft_code_id: Option<u64>,
+        max_royalty_bps: Option<u64>,
+        deposit_receipts: Option<bool>,
+    },
+    /// Replaces the royalties of a collection, or removes them if empty. Open to the collection's
+    /// cw721 minter and the admin.
+    SetRoyalties {
+        cw721_contract: String,
+        royalties: Vec<Royalty>,
+    },
+    /// Groups deposited NFTs into one lot; any asks on them are removed
+    CreateBundle {
+        nfts: Vec<NftItem>,
+        price: Asset,
+    },
+    CancelBundle {
+        bundle_id: u64,
+    },
+    /// Buys a bundle priced in a native denom, paid with the attached funds
+    PurchaseBundle {
+        bundle_id: u64,
+        /// Referrer that sent the buyer, paid a share of the marketplace fee if registered
+        referrer: Option<String>,
+    },
+    /// Buys a listed NFT whose ask accepts a native denom, paid with the attached funds
+    Purchase {
+        cw721_contract: String,
+        token_id: String,
+        referrer: Option<String>,
+    },
+    /// Escrows deposited NFTs in exchange for the `wanted` NFTs. Top-ups are paid out of the
+    /// marketplace deposits of each side and credited to the other side's deposits.
+    CreateSwap {
+        offered: Vec<NftItem>,
+        wanted: Vec<NftItem>,
+        maker_top_up: Option<Asset>,
+        taker_top_up: Option<Asset>,
+    },
+    /// Returns the maker's NFTs and top-up, and any NFTs already sent by a counterparty
+    CancelSwap {
+        swap_id: u64,
+    },
+    /// Returns the NFTs a counterparty sent to a swap that has not settled yet
+    WithdrawSwapFill {
+        swap_id: u64,
+    },
+    /// Offers a deposited NFT as collateral for a cw20 loan; `duration` is in seconds
+    RequestLoan {
+        cw721_contract: String,
+        token_id: String,
+        cw20_contract: String,
+        principal: u128,
+        interest: u128,
+        duration: u64,
+    },
+    /// Withdraws a loan request that has not been funded; the NFT stays deposited
+    CancelLoan {
+        loan_id: u64,
+    },
+    /// Transfers the collateral of an expired, unpaid loan to the lender
+    ClaimCollateral {
+        loan_id: u64,
+    },
+    /// Escrows a deposited NFT for rent at `price_per_period` per `period` seconds
+    ListRental {
+        cw721_contract: String,
+        token_id: String,
+        cw20_contract: String,
+        price_per_period: u128,
+        period: u64,
+    },
+    /// Ends a rental listing once no rental is running and returns the NFT to its owner
+    ReclaimRental {
+        cw721_contract: String,
+        token_id: String,
+    },
+    /// Locks a deposited NFT in a vault and mints `supply` shares of a new cw20 to the sender
+    Fractionalize {
+        cw721_contract: String,
+        token_id: String,
+        name: String,
+        symbol: String,
+        supply: u128,
+        buyout_token: String,
+        buyout_price: u128,
+    },
+    /// Instantiates a new `nft` collection minted by the marketplace. Buyers mint tokens for
+    /// `mint_price` from `start_time` on, until `max_supply` is reached.
+    CreateCollection {
+        name: String,
+        symbol: String,
+        mint_price: Asset,
+        max_supply: u64,
+        start_time: Timestamp,
+    },
+    /// Replaces the allowlist phases of a launchpad collection. Only the creator may set them,
+    /// and only before the first token is minted.
+    SetMintPhases {
+        collection_id: u64,
+        phases: Vec<MintPhase>,
+    },
+    /// Mints the next token of a launchpad collection priced in a native denom, paid with the
+    /// attached funds
+    Mint {
+        collection_id: u64,
+        /// Mints in an allowlist phase at its price instead of in the public sale
+        #[serde(default)]
+        allowlist: Option<AllowlistProof>,
+    },
+    /// Registers the secp256k1 public key that signs the sender's mint vouchers and sell orders,
+    /// replacing any earlier key
+    SetSigningKey {
+        pubkey: Binary,
+    },
+    /// Mints the token of a voucher signed by the collection's creator, paid with the attached
+    /// funds
+    RedeemVoucher {
+        voucher: Box<MintVoucher>,
+        signature: Binary,
+    },
+    /// Buys the NFT of a sell order signed by its owner, paid with the attached funds. The owner
+    /// must have approved the marketplace for the token, or as operator for all of its tokens.
+    FillOrder {
+        order: Box<SellOrder>,
+        signature: Binary,
+        referrer: Option<String>,
+    },
+    /// Cancels the sender's sell order with this nonce
+    CancelOrder {
+        nonce: u64,
+    },
+    /// Cancels every sell order of the sender with a lower nonce
+    CancelOrdersBelow {
+        nonce: u64,
+    },
+    /// Sends a deposited NFT over an ICS-721 channel; `timeout` is in seconds and defaults to 10
+    /// minutes. NFTs of failed transfers are returned to the sender's wallet.
+    IbcTransferNft {
+        cw721_contract: String,
+        token_id: String,
+        channel_id: String,
+        receiver: String,
+        timeout: Option<u64>,
+    },
+    /// Offers the admin role to `addr`, who has `expires_in` seconds to claim it. Replaces any
+    /// earlier proposal.
+    ProposeNewAdmin {
+        addr: String,
+        expires_in: u64,
+    },
+    /// Called by the proposed admin to take over the role
+    ClaimAdmin {},
+    DropAdminProposal {},
+    /// Registers a referrer, or changes its share, in basis points of the marketplace fee
+    SetReferrer {
+        address: String,
+        share_bps: u64,
+    },
+    /// Stops paying a referrer; its past earnings remain queryable
+    RemoveReferrer {
+        address: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Withdrawal {
+    Native { denom: String, amount: u128 },
+    Cw20 { cw20_contract: String, amount: u128 },
+}
+
+/// A token of a launchpad collection offered by its creator before it is minted
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MintVoucher {
+    pub collection_id: u64,
+    /// Numeric ids above the number of tokens minted so far are reserved for public mints
+    pub token_id: String,
+    pub token_uri: Option<String>,
+    pub extension: nft::contract::Extension,
+    pub price: Asset,
+    /// Each nonce redeems at most one voucher of the creator
+    pub nonce: u64,
+}
+
+impl MintVoucher {
+    /// The bytes the creator signs: the marketplace address followed by the JSON-encoded voucher.
+    /// The signature is checked against their sha256 hash.
+    pub fn sign_bytes(&self, marketplace: &str) -> StdResult<Vec<u8>> {
+        Ok([marketplace.as_bytes(), &to_vec(self)?].concat())
+    }
+}
+
+/// An NFT offered by its owner without depositing it into the marketplace
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SellOrder {
+    pub seller: String,
+    pub cw721_contract: String,
+    pub token_id: String,
+    pub price: Asset,
+    pub expires: Timestamp,
+    /// Each nonce fills at most one order of the seller
+    pub nonce: u64,
+}
+
+impl SellOrder {
+    /// The bytes the seller signs, built like those of a `MintVoucher`
+    pub fn sign_bytes(&self, marketplace: &str) -> StdResult<Vec<u8>> {
+        Ok([marketplace.as_bytes(), &to_vec(self)?].concat())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AllowlistProof {
+    pub phase_id: u32,
+    /// Hex-encoded sibling hashes from the sender's leaf up to the Merkle root
+    pub proof: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AskUpdate {
+    pub cw721_contract: String,
+    pub token_id: String,
+    pub cw20_contract: String,
+    pub amount: u128,
+    pub reserved_for: Option<String>,
+    /// Other assets the NFT may be bought with, each at its own price
+    #[serde(default)]
+    pub accepted: Vec<Asset>,
+}
+
+/// Called by the chain, e.g. through a governance proposal, without going through the admin
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    /// Fee taken on sales and rentals, in basis points. The recipient defaults to the admin.
+    UpdateFees {
+        fee_bps: u64,
+        fee_recipient: Option<String>,
+    },
+    /// Stops or resumes listing, selling, swapping and renting NFTs. Withdrawals keep working.
+    SetPaused {
+        paused: bool,
+    },
+    UpdateAdmin {
+        admin: String,
+    },
+    /// Removes an ask; the NFT stays deposited with its owner
+    ForceDelist {
+        cw721_contract: String,
+        token_id: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetCw20Deposit { address: String },
-    GetDeposits { address: String },
-    GetCw721Deposit { address: String, contract: String },
+    GetCw20Deposit {
+        address: String,
+    },
+    GetDeposits {
+        address: String,
+    },
+    GetCw721Deposit {
+        address: String,
+        contract: String,
+    },
+    GetConfig {},
+    PendingAdmin {},
+    Royalties {
+        cw721_contract: String,
+    },
+    /// The share and total earnings of a referrer, per payment asset
+    ReferralEarnings {
+        referrer: String,
+    },
+    Referrers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    SalesByCollection {
+        cw721_contract: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    SalesByToken {
+        cw721_contract: String,
+        token_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    SalesByAddress {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    CollectionStats {
+        cw721_contract: String,
+    },
+    /// Collections ranked by all-time volume in `payment_token`, highest first
+    TopCollections {
+        payment_token: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Bundle {
+        bundle_id: u64,
+    },
+    Bundles {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Swap {
+        swap_id: u64,
+    },
+    Swaps {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Loan {
+        loan_id: u64,
+    },
+    Loans {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Rental {
+        cw721_contract: String,
+        token_id: String,
+    },
+    Vault {
+        vault_id: u64,
+    },
+    Vaults {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// A launchpad collection
+    Collection {
+        collection_id: u64,
+    },
+    Collections {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The allowlist phases of a launchpad collection; a phase id is its index in the list
+    MintPhases {
+        collection_id: u64,
+    },
+    SigningKey {
+        creator: String,
+    },
+    NonceUsed {
+        creator: String,
+        nonce: u64,
+    },
+    /// Whether a sell order nonce was filled or cancelled
+    OrderNonceUsed {
+        seller: String,
+        nonce: u64,
+    },
+    /// What `address` has minted in a phase and may still mint, if it is on the allowlist
+    MintAllowance {
+        collection_id: u64,
+        phase_id: u32,
+        address: String,
+    },
+    ReceiptToken {
+        denom: String,
+    },
+    /// Compares what the marketplace owes for each asset with what it actually holds
+    Solvency {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// The local voucher collection of an ICS-721 class received from another chain
+    VoucherContract {
+        class_id: String,
+    },
+    /// Who holds the user rights of a rented NFT, if anyone
+    CurrentUser {
+        cw721_contract: String,
+        token_id: String,
+    },
+    /// Asks in a collection; reserved asks are only shown to their buyer or seller as `viewer`
+    Asks {
+        cw721_contract: String,
+        viewer: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Asks placed by `seller`; reserved asks are only shown to their buyer or seller as `viewer`
+    AsksBySeller {
+        seller: String,
+        viewer: Option<String>,
+        start_after: Option<NftItem>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -52,6 +472,57 @@ pub enum Cw20HookMsg {
     Purchase {
         token_id: String,
         cw721_contract: String,
+        /// Referrer that sent the buyer, paid a share of the marketplace fee if registered
+        referrer: Option<String>,
+    },
+    PurchaseBundle {
+        bundle_id: u64,
+        referrer: Option<String>,
+    },
+    /// Lends exactly the principal, which is sent on to the borrower
+    FundLoan {
+        loan_id: u64,
+    },
+    /// Pays back principal plus interest before expiry; the collateral goes back to the borrower
+    RepayLoan {
+        loan_id: u64,
+    },
+    /// Pays for `periods` rental periods, starting now
+    Rent {
+        cw721_contract: String,
+        token_id: String,
+        periods: u64,
+    },
+    /// Sent from a vault's share token: the whole supply redeems the NFT
+    RedeemVault {
+        vault_id: u64,
+    },
+    /// Sent from a vault's buyout token: an offer of at least the buyout price buys the NFT
+    Buyout {
+        vault_id: u64,
+    },
+    /// Sent from a vault's share token after a buyout: swaps shares for their part of the proceeds
+    ClaimProceeds {
+        vault_id: u64,
+    },
+    /// Sent from a receipt token: burns the receipts and pays out the native coins
+    RedeemReceipt {},
+    /// Buys the NFT of a sell order priced in this cw20
+    FillOrder {
+        order: Box<SellOrder>,
+        signature: Binary,
+        referrer: Option<String>,
+    },
+    /// Mints the token of a voucher priced in this cw20
+    RedeemVoucher {
+        voucher: Box<MintVoucher>,
+        signature: Binary,
+    },
+    /// Mints the next token of a launchpad collection priced in this cw20
+    Mint {
+        collection_id: u64,
+        #[serde(default)]
+        allowlist: Option<AllowlistProof>,
     },
 }
 
@@ -63,7 +534,15 @@ pub enum Cw721HookMsg {
         token_id: String,
         cw20_contract: String,
         amount: u128,
+        /// Lists the NFT privately; only this address may purchase it
+        reserved_for: Option<String>,
+        /// Other assets the NFT may be bought with, each at its own price
+        #[serde(default)]
+        accepted: Vec<Asset>,
     },
+    /// Sends one of the wanted NFTs of a swap; the swap settles once all of them are received.
+    /// Another taker's incomplete fill blocks the swap for a day, after which it is sent back.
+    FillSwap { swap_id: u64 },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -83,3 +562,202 @@ pub struct DepositResponse {
 pub struct Cw721DepositResponse {
     pub deposits: Vec<Cw721Deposit>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConfigResponse {
+    pub admin: String,
+    pub max_batch_size: u32,
+    pub cw20_code_id: Option<u64>,
+    pub nft_code_id: Option<u64>,
+    pub fee_bps: u64,
+    pub fee_recipient: String,
+    pub paused: bool,
+    pub max_royalty_bps: u64,
+    pub deposit_receipts: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SalesResponse {
+    pub sales: Vec<Sale>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CollectionStatsResponse {
+    pub cw721_contract: String,
+    pub listing_count: u64,
+    pub sale_count: u64,
+    pub payment_tokens: Vec<PaymentTokenStatsResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PaymentTokenStatsResponse {
+    pub payment_token: String,
+    pub listing_count: u64,
+    pub floor_price: Option<u128>,
+    pub volume_24h: u128,
+    pub volume_all_time: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CollectionVolume {
+    pub cw721_contract: String,
+    pub volume: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TopCollectionsResponse {
+    pub collections: Vec<CollectionVolume>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BundlesResponse {
+    pub bundles: Vec<Bundle>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SwapResponse {
+    pub swap: Swap,
+    pub taker: Option<String>,
+    pub received: Vec<NftItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SwapsResponse {
+    pub swaps: Vec<Swap>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AsksResponse {
+    pub asks: Vec<Offer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LoansResponse {
+    pub loans: Vec<Loan>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CurrentUserResponse {
+    pub user: Option<String>,
+    pub expires_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct VaultsResponse {
+    pub vaults: Vec<Vault>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CollectionsResponse {
+    pub collections: Vec<Collection>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MintPhasesResponse {
+    pub collection_id: u64,
+    pub phases: Vec<MintPhase>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SigningKeyResponse {
+    pub creator: String,
+    pub pubkey: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct NonceUsedResponse {
+    pub used: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MintAllowanceResponse {
+    pub minted: u64,
+    pub remaining: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ReceiptTokenResponse {
+    pub denom: String,
+    pub receipt_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct VoucherContractResponse {
+    pub class_id: String,
+    pub cw721_contract: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AssetSolvency {
+    pub info: AssetInfo,
+    /// Total owed to depositors, receipt holders and sellers
+    pub liabilities: u128,
+    /// Bank or cw20 balance of the marketplace
+    pub balance: u128,
+    pub shortfall: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SolvencyResponse {
+    pub assets: Vec<AssetSolvency>,
+    /// Whether none of the returned assets has a shortfall
+    pub solvent: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PendingAdminResponse {
+    pub new_admin: Option<String>,
+    pub expires_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RoyaltiesResponse {
+    pub cw721_contract: String,
+    pub royalties: Vec<Royalty>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ReferralEarningsResponse {
+    pub referrer: String,
+    /// None once the referrer is no longer registered
+    pub share_bps: Option<u64>,
+    pub earnings: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Referrer {
+    pub address: String,
+    pub share_bps: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ReferrersResponse {
+    pub referrers: Vec<Referrer>,
+}
@@ -1,8 +1,45 @@
-use cosmwasm_std::Coin;
+use cosmwasm_std::{
+    to_binary, BankMsg, Binary, Coin, CosmosMsg, Empty, IbcChannel, StdResult, Timestamp, Uint128,
+    WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cw_storage_plus::Map;
+use cw_storage_plus::{Item, Map};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub admin: String,
+    pub max_batch_size: u32,
+    /// Code id of `cw20-example`, used to instantiate vault share tokens and deposit receipts
+    pub cw20_code_id: Option<u64>,
+    /// Code id of the `nft` contract, used to instantiate ICS-721 voucher collections
+    pub nft_code_id: Option<u64>,
+    /// Share of every sale and rental taken as a fee, in basis points
+    pub fee_bps: u64,
+    pub fee_recipient: String,
+    /// While paused no NFT can be listed, sold, swapped or rented
+    pub paused: bool,
+    /// Cap on the total royalties of a collection, in basis points of the seller's proceeds
+    pub max_royalty_bps: u64,
+    /// Whether native deposits are paid out as cw20 receipt tokens
+    pub deposit_receipts: bool,
+}
+
+/// A share of every sale of a collection owed to `recipient`, in basis points
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Royalty {
+    pub recipient: String,
+    pub bps: u64,
+}
+
+/// A new admin proposed by the current one, who has to claim the role before `expires_at`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminProposal {
+    pub new_admin: String,
+    pub expires_at: Timestamp,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Cw20Deposit {
@@ -33,8 +70,249 @@ pub struct Offer {
     pub cw721_contract: String,
     pub cw20_contract: String,
     pub amount: u128,
+    /// Only this buyer may purchase the NFT; unreserved asks are open to anyone
+    pub reserved_for: Option<String>,
+    /// Other assets the seller accepts instead of `amount` of `cw20_contract`, each at its own price
+    #[serde(default)]
+    pub accepted: Vec<Asset>,
+}
+
+impl Offer {
+    /// Whether `address` may see and purchase this ask.
+    pub fn is_visible_to(&self, address: &str) -> bool {
+        match &self.reserved_for {
+            Some(buyer) => buyer == address || self.owner == address,
+            None => true,
+        }
+    }
+
+    /// The price in `cw20_contract` followed by the prices in the other accepted assets.
+    pub fn prices(&self) -> Vec<Asset> {
+        let mut prices = vec![Asset {
+            info: AssetInfo::Cw20 {
+                contract: self.cw20_contract.clone(),
+            },
+            amount: self.amount,
+        }];
+        prices.extend(self.accepted.iter().cloned());
+        prices
+    }
+
+    /// The price of the ask in `info`, if the seller accepts that asset.
+    pub fn price_in(&self, info: &AssetInfo) -> Option<u128> {
+        self.prices()
+            .into_iter()
+            .find(|price| price.info == *info)
+            .map(|price| price.amount)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftItem {
+    pub cw721_contract: String,
+    pub token_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Sale {
+    pub sale_id: u64,
+    pub cw721_contract: String,
+    pub token_id: String,
+    pub seller: String,
+    pub buyer: String,
+    pub price: u128,
+    pub payment_token: String,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct CollectionStats {
+    pub listing_count: u64,
+    pub sale_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PaymentTokenStats {
+    pub listing_count: u64,
+    pub volume: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Cw20 { contract: String },
+    Native { denom: String },
+}
+
+impl AssetInfo {
+    /// The cw20 contract address or the native denom of the asset.
+    pub fn id(&self) -> &str {
+        match self {
+            AssetInfo::Cw20 { contract } => contract,
+            AssetInfo::Native { denom } => denom,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: u128,
+}
+
+impl Asset {
+    /// Message sending this asset from the marketplace to `recipient`.
+    pub fn transfer_msg(&self, recipient: &str) -> StdResult<CosmosMsg> {
+        Ok(match &self.info {
+            AssetInfo::Cw20 { contract } => WasmMsg::Execute {
+                contract_addr: contract.clone(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount: Uint128::new(self.amount),
+                })?,
+                funds: vec![],
+            }
+            .into(),
+            AssetInfo::Native { denom } => BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount: Uint128::new(self.amount),
+                }],
+            }
+            .into(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Bundle {
+    pub bundle_id: u64,
+    pub seller: String,
+    pub nfts: Vec<NftItem>,
+    pub price: Asset,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Swap {
+    pub swap_id: u64,
+    pub maker: String,
+    pub offered: Vec<NftItem>,
+    pub wanted: Vec<NftItem>,
+    /// Escrowed from the maker's deposits and credited to the taker on settlement
+    pub maker_top_up: Option<Asset>,
+    /// Moved from the taker's deposits to the maker's on settlement
+    pub taker_top_up: Option<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Loan {
+    pub loan_id: u64,
+    pub borrower: String,
+    pub collateral: NftItem,
+    pub cw20_contract: String,
+    pub principal: u128,
+    pub interest: u128,
+    /// Seconds the borrower has to repay once the loan is funded
+    pub duration: u64,
+    pub lender: Option<String>,
+    pub expires_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Rental {
+    pub owner: String,
+    pub cw721_contract: String,
+    pub token_id: String,
+    pub cw20_contract: String,
+    pub price_per_period: u128,
+    /// Length of one rental period in seconds
+    pub period: u64,
+    pub renter: Option<String>,
+    pub expires_at: Option<Timestamp>,
+}
+
+impl Rental {
+    /// The renter, while the rental has not expired at `now`.
+    pub fn current_user(&self, now: Timestamp) -> Option<String> {
+        match (&self.renter, self.expires_at) {
+            (Some(renter), Some(expires_at)) if now < expires_at => Some(renter.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Vault {
+    pub vault_id: u64,
+    pub curator: String,
+    pub nft: NftItem,
+    /// cw20 contract of the shares; the whole supply redeems the NFT
+    pub share_token: String,
+    pub supply: u128,
+    pub buyout_token: String,
+    /// Minimum buyout offer that forces a sale of the NFT
+    pub buyout_price: u128,
+    pub buyout: Option<Buyout>,
+}
+
+/// A collection instantiated by the launchpad, with the marketplace as its minter
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Collection {
+    pub collection_id: u64,
+    /// Receives the proceeds of every mint, less the marketplace fee
+    pub creator: String,
+    /// Filled in by the instantiate reply
+    pub cw721_contract: String,
+    pub mint_price: Asset,
+    pub max_supply: u64,
+    pub minted: u64,
+    /// Minting is closed before this time
+    pub start_time: Timestamp,
+}
+
+/// An allowlist phase of a launchpad collection, open to the addresses of a Merkle tree
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintPhase {
+    /// Hex-encoded root of a sha256 Merkle tree whose leaves are the hashed addresses
+    pub merkle_root: String,
+    pub price: Asset,
+    /// Tokens each address may mint in this phase
+    pub per_address_limit: u64,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Buyout {
+    pub buyer: String,
+    /// Proceeds not yet claimed by share holders, in the vault's buyout token
+    pub proceeds: u128,
+    pub unclaimed_shares: u128,
 }
 
+/// Vouchers waiting for their collection to be instantiated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingVoucher {
+    pub class_id: String,
+    pub channel_id: String,
+    pub mints: Vec<nft::contract::MintMsg<nft::contract::Extension>>,
+}
+
+/// Why a deposited NFT is held back from being listed or withdrawn on its own
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NftLock {
+    Bundle { bundle_id: u64 },
+    Swap { swap_id: u64 },
+    Loan { loan_id: u64 },
+    Rental {},
+    Vault { vault_id: u64 },
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
 //key = owner addr, denom
 pub const DEPOSITS: Map<(&str, &str), Deposit> = Map::new("deposits");
 
@@ -46,3 +324,137 @@ pub const CW721_DEPOSITS: Map<(&str, &str, &str), Cw721Deposit> = Map::new("cw72
 
 //key = cw721 contract addr, token_id
 pub const ASKS: Map<(&str, &str), Offer> = Map::new("asks");
+
+//id of the most recently recorded sale
+pub const SALE_COUNT: Item<u64> = Item::new("sale_count");
+
+//key = sale id
+pub const SALES: Map<u64, Sale> = Map::new("sales");
+
+//key = cw721 contract addr, sale id
+pub const SALES_BY_COLLECTION: Map<(&str, u64), Empty> = Map::new("sales_by_collection");
+
+//key = cw721 contract addr, token_id, sale id
+pub const SALES_BY_TOKEN: Map<(&str, &str, u64), Empty> = Map::new("sales_by_token");
+
+//key = seller or buyer addr, sale id
+pub const SALES_BY_ADDRESS: Map<(&str, u64), Empty> = Map::new("sales_by_address");
+
+//key = cw721 contract addr
+pub const COLLECTION_STATS: Map<&str, CollectionStats> = Map::new("collection_stats");
+
+//key = cw721 contract addr, payment token addr
+pub const PAYMENT_TOKEN_STATS: Map<(&str, &str), PaymentTokenStats> =
+    Map::new("payment_token_stats");
+
+//key = cw721 contract addr, payment token addr, (price, token_id)
+pub const ASK_PRICES: Map<(&str, &str, (u128, &str)), Empty> = Map::new("ask_prices");
+
+//key = cw721 contract addr, payment token addr, hours since epoch
+pub const HOURLY_VOLUMES: Map<(&str, &str, u64), u128> = Map::new("hourly_volumes");
+
+//key = payment token addr, all-time volume, cw721 contract addr
+pub const VOLUME_RANKING: Map<(&str, u128, &str), Empty> = Map::new("volume_ranking");
+
+//id of the most recently created bundle
+pub const BUNDLE_COUNT: Item<u64> = Item::new("bundle_count");
+
+//key = bundle id
+pub const BUNDLES: Map<u64, Bundle> = Map::new("bundles");
+
+//key = cw721 contract addr, token_id
+pub const ESCROWED_NFTS: Map<(&str, &str), NftLock> = Map::new("escrowed_nfts");
+
+//id of the most recently created swap
+pub const SWAP_COUNT: Item<u64> = Item::new("swap_count");
+
+//key = swap id
+pub const SWAPS: Map<u64, Swap> = Map::new("swaps");
+
+//key = swap id, cw721 contract addr, token_id -> sender of the wanted NFT
+pub const SWAP_FILLS: Map<(u64, &str, &str), String> = Map::new("swap_fills");
+
+//key = swap id -> time the current taker sent its first wanted NFT
+pub const SWAP_FILL_STARTED: Map<u64, Timestamp> = Map::new("swap_fill_started");
+
+//id of the most recently requested loan
+pub const LOAN_COUNT: Item<u64> = Item::new("loan_count");
+
+//key = loan id
+pub const LOANS: Map<u64, Loan> = Map::new("loans");
+
+//key = cw721 contract addr, token_id
+pub const RENTALS: Map<(&str, &str), Rental> = Map::new("rentals");
+
+//id of the most recently created vault
+pub const VAULT_COUNT: Item<u64> = Item::new("vault_count");
+
+//key = vault id
+pub const VAULTS: Map<u64, Vault> = Map::new("vaults");
+
+//id of the most recently created launchpad collection
+pub const COLLECTION_COUNT: Item<u64> = Item::new("collection_count");
+
+//key = launchpad collection id
+pub const COLLECTIONS: Map<u64, Collection> = Map::new("collections");
+
+//key = launchpad collection id -> allowlist phases, indexed by phase id
+pub const MINT_PHASES: Map<u64, Vec<MintPhase>> = Map::new("mint_phases");
+
+//key = launchpad collection id, phase id, minter addr -> tokens minted in the phase
+pub const PHASE_MINTS: Map<(u64, u32, &str), u64> = Map::new("phase_mints");
+
+//key = creator addr -> secp256k1 public key that signs the creator's mint vouchers
+pub const SIGNING_KEYS: Map<&str, Binary> = Map::new("signing_keys");
+
+//key = creator addr, nonce of a redeemed mint voucher
+pub const USED_NONCES: Map<(&str, u64), Empty> = Map::new("used_nonces");
+
+//key = seller addr, nonce of a filled or cancelled sell order
+pub const ORDER_NONCES: Map<(&str, u64), Empty> = Map::new("order_nonces");
+
+//key = seller addr -> lowest nonce of the seller's sell orders that can still be filled
+pub const MIN_ORDER_NONCES: Map<&str, u64> = Map::new("min_order_nonces");
+
+//launchpad collection waiting for the reply that carries its cw721 address
+pub const PENDING_COLLECTION: Item<Collection> = Item::new("pending_collection");
+
+//vault waiting for the reply that carries its share token address
+pub const PENDING_VAULT: Item<Vault> = Item::new("pending_vault");
+
+//key = native denom -> cw20 receipt token minted for deposits of the denom
+pub const RECEIPT_TOKENS: Map<&str, String> = Map::new("receipt_tokens");
+
+//key = cw20 receipt token -> native denom it redeems for
+pub const RECEIPT_DENOMS: Map<&str, String> = Map::new("receipt_denoms");
+
+//denom waiting for the reply that carries its receipt token address
+pub const PENDING_RECEIPT_DENOM: Item<String> = Item::new("pending_receipt_denom");
+
+pub const ADMIN_PROPOSAL: Item<AdminProposal> = Item::new("admin_proposal");
+
+//key = cw20 contract addr or native denom -> total the marketplace holds on behalf of others
+pub const LIABILITIES: Map<&str, Asset> = Map::new("liabilities");
+
+//key = cw721 contract addr -> royalties paid on sales of the collection
+pub const ROYALTIES: Map<&str, Vec<Royalty>> = Map::new("royalties");
+
+//key = referrer addr -> share of the marketplace fee paid to the referrer, in basis points
+pub const REFERRERS: Map<&str, u64> = Map::new("referrers");
+
+//key = referrer addr, cw20 contract addr or native denom -> total earned by the referrer
+pub const REFERRAL_EARNINGS: Map<(&str, &str), Asset> = Map::new("referral_earnings");
+
+//key = channel id
+pub const IBC_CHANNELS: Map<&str, IbcChannel> = Map::new("ibc_channels");
+
+//key = channel id, cw721 contract addr, token_id of an NFT sent over the channel
+pub const IBC_ESCROW: Map<(&str, &str, &str), Empty> = Map::new("ibc_escrow");
+
+//key = ICS-721 class id -> local voucher cw721 contract
+pub const VOUCHER_CONTRACTS: Map<&str, String> = Map::new("voucher_contracts");
+
+//key = voucher cw721 contract -> ICS-721 class id
+pub const VOUCHER_CLASS_IDS: Map<&str, String> = Map::new("voucher_class_ids");
+
+pub const PENDING_VOUCHER: Item<PendingVoucher> = Item::new("pending_voucher");
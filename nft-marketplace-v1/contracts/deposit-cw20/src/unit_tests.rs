@@ -1,34 +1,60 @@
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::{from_binary, to_binary, DepsMut, Response, Uint128};
+    use std::convert::TryFrom;
+
+    use cosmwasm_std::{
+        from_binary, to_binary, Binary, ContractResult, CosmosMsg, DepsMut, Env, Event,
+        IbcAcknowledgement, IbcMsg, IbcOrder, Reply, Response, SubMsgResponse, SubMsgResult,
+        SystemError, SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
 
     use cw20::Cw20ReceiveMsg;
     use cw721::Cw721ReceiveMsg;
 
-    use crate::contract::{execute, instantiate, query};
+    use crate::contract::{execute, instantiate, query, reply, sudo};
     use crate::error::ContractError;
+    use crate::events::MarketplaceEvent;
+    use crate::ibc::{
+        ibc_channel_close, ibc_channel_connect, ibc_channel_open, ibc_packet_ack,
+        ibc_packet_receive, ibc_packet_timeout, Ics721Ack, NonFungibleTokenPacketData,
+        ICS721_VERSION, INSTANTIATE_VOUCHER_REPLY_ID,
+    };
     use crate::msg::{
-        Cw20DepositResponse, Cw20HookMsg, Cw721DepositResponse, Cw721HookMsg, DepositResponse,
-        ExecuteMsg, InstantiateMsg, QueryMsg,
+        AskUpdate, AsksResponse, BundlesResponse, CollectionStatsResponse, ConfigResponse,
+        CurrentUserResponse, Cw20DepositResponse, Cw20HookMsg, Cw721DepositResponse, Cw721HookMsg,
+        DepositResponse, ExecuteMsg, InstantiateMsg, PendingAdminResponse, QueryMsg,
+        ReferralEarningsResponse, ReferrersResponse, SalesResponse, SudoMsg, SwapResponse,
+        TopCollectionsResponse, VoucherContractResponse, Withdrawal,
     };
+    use crate::state::{Asset, AssetInfo, Bundle, NftItem, ASKS};
 
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_ibc_channel_close_init, mock_ibc_channel_connect_ack,
+        mock_ibc_channel_open_init, mock_ibc_channel_open_try, mock_ibc_packet_ack,
+        mock_ibc_packet_recv, mock_ibc_packet_timeout, mock_info,
+    };
     use cosmwasm_std::Coin;
 
     const SENDER: &str = "sender_address";
     const AMOUNT: u128 = 100000;
     const DENOM: &str = "utest";
+    const NFT_CODE_ID: u64 = 7;
 
     fn proper_instantiate(deps: DepsMut) -> Result<Response, ContractError> {
-        let msg = InstantiateMsg {};
-        let info = mock_info(&SENDER, &[]);
+        let msg = InstantiateMsg {
+            max_batch_size: Some(2),
+            cw20_code_id: None,
+            nft_code_id: Some(NFT_CODE_ID),
+            deposit_receipts: None,
+        };
+        let info = mock_info(SENDER, &[]);
         instantiate(deps, mock_env(), info, msg)
     }
 
     fn execute_cw20_deposit(deps: DepsMut) -> Result<Response, ContractError> {
         let cw20_msg = Cw20ReceiveMsg {
-            sender: "".to_string(),
-            amount: Uint128::new(0),
+            sender: "right_guy".to_string(),
+            amount: Uint128::new(100),
             msg: to_binary(&Cw20HookMsg::Deposit {
                 owner: "right_guy".to_string(),
                 amount: 100u128,
@@ -36,7 +62,7 @@ mod tests {
         };
 
         let msg = ExecuteMsg::Receive(cw20_msg);
-        let info = mock_info(&"contract_addr", &[]);
+        let info = mock_info("contract_addr", &[]);
         execute(deps, mock_env(), info, msg)
     }
 
@@ -49,18 +75,20 @@ mod tests {
                 token_id: "TNT".to_string(),
                 cw20_contract: "cw20addr".to_string(),
                 amount: 100,
+                reserved_for: None,
+                accepted: vec![],
             })?,
         };
 
         let msg = ExecuteMsg::ReceiveNft(cw721_msg);
-        let info = mock_info(&"contract_addr", &[]);
+        let info = mock_info("contract_addr", &[]);
         execute(deps, mock_env(), info, msg)
     }
 
     fn execute_deposit(deps: DepsMut) -> Result<Response, ContractError> {
         let msg = ExecuteMsg::Deposit {};
         let info = mock_info(
-            &SENDER,
+            SENDER,
             &[Coin {
                 amount: Uint128::new(AMOUNT),
                 denom: DENOM.to_string(),
@@ -118,7 +146,7 @@ mod tests {
             cw721_contract: "contract_addr".to_string(),
             token_id: "TNT".to_string(),
         };
-        let info = mock_info(&"juno1pqn6edrdmr28ekdjv5j2u9uvh6m32tl306kh5h", &[]);
+        let info = mock_info("juno1pqn6edrdmr28ekdjv5j2u9uvh6m32tl306kh5h", &[]);
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
         //println!("RES: {:?}", res);
 
@@ -165,12 +193,13 @@ mod tests {
             msg: to_binary(&Cw20HookMsg::Purchase {
                 token_id: "TNT".to_string(),
                 cw721_contract: "contract_addr".to_string(),
+                referrer: None,
             })
             .unwrap(),
         };
 
         let msg = ExecuteMsg::Receive(cw20_msg);
-        let info = mock_info(&"buyer_addr", &[]);
+        let info = mock_info("cw20addr", &[]);
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
         //println!("RES: {:?}", res);
 
@@ -189,6 +218,17 @@ mod tests {
     #[test]
     fn test_deposit_and_query() {
         let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        for funds in [vec![], vec![Coin::new(1, DENOM), Coin::new(1, "uother")]] {
+            let info = mock_info(SENDER, &funds);
+            let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Deposit {});
+            match res {
+                Err(ContractError::InvalidCoin {}) => {}
+                _ => panic!("should error here"),
+            }
+        }
+
         let _res = execute_deposit(deps.as_mut()).unwrap();
         let _res = execute_deposit(deps.as_mut()).unwrap();
 
@@ -214,6 +254,7 @@ mod tests {
     #[test]
     fn test_deposit_and_withdraw() {
         let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
         let _res = execute_deposit(deps.as_mut()).unwrap();
         let _res = execute_deposit(deps.as_mut()).unwrap();
 
@@ -228,7 +269,7 @@ mod tests {
             amount: 1,
             denom: DENOM.to_string(),
         };
-        let info = mock_info(&SENDER, &[]);
+        let info = mock_info(SENDER, &[]);
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
         //println!("RES: {:?}", res);
@@ -239,6 +280,26 @@ mod tests {
         let res = query(deps.as_ref(), mock_env(), msg).unwrap();
         let res: DepositResponse = from_binary(&res).unwrap();
         assert_eq!(res.deposits[0].amount.amount, Uint128::new(199999));
+
+        let msg = ExecuteMsg::Withdraw {
+            amount: 200000,
+            denom: DENOM.to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg);
+        match res {
+            Err(ContractError::InsufficientDeposit {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::Withdraw {
+            amount: 1,
+            denom: DENOM.to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wrong_guy", &[]), msg);
+        match res {
+            Err(ContractError::NoDepositToWithdraw {}) => {}
+            _ => panic!("should error here"),
+        }
     }
 
     #[test]
@@ -266,6 +327,55 @@ mod tests {
         //println!("AMOUNT IN DEPOSIT CONTRACT: {:?}", res);
     }
 
+    #[test]
+    fn test_cw20_deposit_must_match_tokens_sent() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let info = mock_info("contract_addr", &[]);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "wrong_guy".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&Cw20HookMsg::Deposit {
+                owner: "right_guy".to_string(),
+                amount: 100u128,
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidOwner {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "right_guy".to_string(),
+            amount: Uint128::new(1),
+            msg: to_binary(&Cw20HookMsg::Deposit {
+                owner: "right_guy".to_string(),
+                amount: 100u128,
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidDepositAmount {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // a hook that is not a known message is an error rather than a panic
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "right_guy".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&"unknown").unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        match res {
+            Err(ContractError::Std(_)) => {}
+            _ => panic!("should error here"),
+        }
+    }
+
     #[test]
     fn test_cw20_deposit_and_withdraw() {
         let mut deps = mock_dependencies();
@@ -280,22 +390,30 @@ mod tests {
         println!("AMOUNT IN CONTRACT AFTER 1ST DEPOSIT: {:?}", res);
 
         let msg = ExecuteMsg::WithdrawCw20 {
-            owner: "wrong_guy".to_string(),
+            cw20_contract: "contract_addr".to_string(),
             amount: 100u128,
         };
-        let info = mock_info("contract_addr", &[]);
-        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        let info = mock_info("wrong_guy", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
         match res {
             Err(ContractError::NoCw20ToWithdraw {}) => {}
             _ => panic!("should error here"),
         }
 
-        let msg = ExecuteMsg::WithdrawCw20 {
-            owner: "right_guy".to_string(),
-            amount: 100u128,
+        let overdraw = ExecuteMsg::WithdrawCw20 {
+            cw20_contract: "contract_addr".to_string(),
+            amount: 101u128,
         };
-        let info = mock_info("contract_addr", &[]);
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("right_guy", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), overdraw);
+        match res {
+            Err(ContractError::InsufficientDeposit {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // the tokens are sent back to the owner
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
 
         let query_msg = QueryMsg::GetCw20Deposit {
             address: "right_guy".to_string(),
@@ -304,4 +422,1506 @@ mod tests {
         let res: Cw20DepositResponse = from_binary(&res).unwrap();
         println!("AMOUNT IN CONTRACT AFTER WITHDRAWAL: {:?}", res);
     }
+
+    #[test]
+    fn test_batch_update_asks_and_size_limit() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_cw721_deposit(deps.as_mut()).unwrap();
+
+        let ask = AskUpdate {
+            cw721_contract: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+            cw20_contract: "other_cw20".to_string(),
+            amount: 250,
+            reserved_for: None,
+            accepted: vec![],
+        };
+        let owner = mock_info("juno1pqn6edrdmr28ekdjv5j2u9uvh6m32tl306kh5h", &[]);
+
+        let msg = ExecuteMsg::BatchUpdateAsks {
+            asks: vec![ask.clone(), ask.clone(), ask.clone()],
+        };
+        let res = execute(deps.as_mut(), mock_env(), owner.clone(), msg);
+        match res {
+            Err(ContractError::BatchTooLarge { max: 2 }) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::BatchUpdateAsks { asks: vec![] };
+        let res = execute(deps.as_mut(), mock_env(), owner.clone(), msg);
+        match res {
+            Err(ContractError::EmptyBatch {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::BatchUpdateAsks {
+            asks: vec![ask.clone()],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wrong_guy", &[]), msg);
+        match res {
+            Err(ContractError::InvalidOwner {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::BatchUpdateAsks { asks: vec![ask] };
+        let res = execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+        assert_eq!(res.events.len(), 1);
+
+        let ask = ASKS
+            .load(deps.as_ref().storage, ("contract_addr", "TNT"))
+            .unwrap();
+        assert_eq!(ask.cw20_contract, "other_cw20");
+        assert_eq!(ask.amount, 250);
+    }
+
+    #[test]
+    fn test_batch_withdraw_native() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_deposit(deps.as_mut()).unwrap();
+
+        let msg = ExecuteMsg::BatchWithdraw {
+            withdrawals: vec![Withdrawal::Cw20 {
+                cw20_contract: "contract_addr".to_string(),
+                amount: 10,
+            }],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg);
+        match res {
+            Err(ContractError::NoCw20ToWithdraw {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::BatchWithdraw {
+            withdrawals: vec![Withdrawal::Native {
+                denom: DENOM.to_string(),
+                amount: AMOUNT + 1,
+            }],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg);
+        match res {
+            Err(ContractError::InsufficientDeposit {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::BatchWithdraw {
+            withdrawals: vec![
+                Withdrawal::Native {
+                    denom: DENOM.to_string(),
+                    amount: 10,
+                },
+                Withdrawal::Native {
+                    denom: DENOM.to_string(),
+                    amount: 90,
+                },
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(res.events.len(), 2);
+
+        let msg = QueryMsg::GetDeposits {
+            address: SENDER.to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount.amount, Uint128::new(AMOUNT - 100));
+    }
+
+    #[test]
+    fn test_batch_withdraw_cw721() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_cw721_deposit(deps.as_mut()).unwrap();
+
+        let msg = ExecuteMsg::BatchWithdrawNft {
+            nfts: vec![NftItem {
+                cw721_contract: "contract_addr".to_string(),
+                token_id: "TNT".to_string(),
+            }],
+        };
+        let info = mock_info("juno1pqn6edrdmr28ekdjv5j2u9uvh6m32tl306kh5h", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.events.len(), 1);
+
+        assert!(!ASKS.has(deps.as_ref().storage, ("contract_addr", "TNT")));
+    }
+
+    fn execute_purchase(
+        deps: DepsMut,
+        env: Env,
+        buyer: &str,
+        cw721_contract: &str,
+        token_id: &str,
+        amount: u128,
+    ) -> Result<Response, ContractError> {
+        let cw20_msg = Cw20ReceiveMsg {
+            sender: buyer.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&Cw20HookMsg::Purchase {
+                token_id: token_id.to_string(),
+                cw721_contract: cw721_contract.to_string(),
+                referrer: None,
+            })?,
+        };
+
+        let msg = ExecuteMsg::Receive(cw20_msg);
+        let info = mock_info("cw20addr", &[]);
+        execute(deps, env, info, msg)
+    }
+
+    fn execute_listing(
+        deps: DepsMut,
+        owner: &str,
+        cw721_contract: &str,
+        token_id: &str,
+        amount: u128,
+    ) -> Result<Response, ContractError> {
+        let cw721_msg = Cw721ReceiveMsg {
+            sender: owner.to_string(),
+            token_id: token_id.to_string(),
+            msg: to_binary(&Cw721HookMsg::Deposit {
+                owner: owner.to_string(),
+                token_id: token_id.to_string(),
+                cw20_contract: "cw20addr".to_string(),
+                amount,
+                reserved_for: None,
+                accepted: vec![],
+            })?,
+        };
+
+        let msg = ExecuteMsg::ReceiveNft(cw721_msg);
+        let info = mock_info(cw721_contract, &[]);
+        execute(deps, mock_env(), info, msg)
+    }
+
+    #[test]
+    fn test_purchase_records_sales_history() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        // the same token is listed and sold three times
+        for buyer in ["first_buyer", "second_buyer", "first_buyer"] {
+            let _res = execute_cw721_deposit(deps.as_mut()).unwrap();
+            let _res = execute_purchase(
+                deps.as_mut(),
+                mock_env(),
+                buyer,
+                "contract_addr",
+                "TNT",
+                100,
+            )
+            .unwrap();
+        }
+
+        let msg = QueryMsg::SalesByToken {
+            cw721_contract: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: SalesResponse = from_binary(&res).unwrap();
+        assert_eq!(res.sales.len(), 3);
+        assert_eq!(res.sales[0].sale_id, 1);
+        assert_eq!(
+            res.sales[0].seller,
+            "juno1pqn6edrdmr28ekdjv5j2u9uvh6m32tl306kh5h"
+        );
+        assert_eq!(res.sales[0].buyer, "first_buyer");
+        assert_eq!(res.sales[0].price, 100);
+        assert_eq!(res.sales[0].payment_token, "cw20addr");
+        assert_eq!(res.sales[0].block_height, mock_env().block.height);
+
+        let msg = QueryMsg::SalesByAddress {
+            address: "first_buyer".to_string(),
+            start_after: Some(1),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: SalesResponse = from_binary(&res).unwrap();
+        assert_eq!(res.sales.len(), 1);
+        assert_eq!(res.sales[0].sale_id, 3);
+
+        let msg = QueryMsg::SalesByCollection {
+            cw721_contract: "contract_addr".to_string(),
+            start_after: None,
+            limit: Some(2),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: SalesResponse = from_binary(&res).unwrap();
+        let ids: Vec<u64> = res.sales.iter().map(|sale| sale.sale_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        let msg = QueryMsg::SalesByCollection {
+            cw721_contract: "other_contract".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: SalesResponse = from_binary(&res).unwrap();
+        assert!(res.sales.is_empty());
+    }
+
+    #[test]
+    fn test_collection_stats_track_listings_and_sales() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_a", "1", 300).unwrap();
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_a", "2", 200).unwrap();
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_b", "1", 50).unwrap();
+
+        let msg = QueryMsg::CollectionStats {
+            cw721_contract: "collection_a".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let res: CollectionStatsResponse = from_binary(&res).unwrap();
+        assert_eq!(res.listing_count, 2);
+        assert_eq!(res.sale_count, 0);
+        assert_eq!(res.payment_tokens[0].floor_price, Some(200));
+
+        // repricing the floor token moves the floor
+        let msg_update = ExecuteMsg::BatchUpdateAsks {
+            asks: vec![AskUpdate {
+                cw721_contract: "collection_a".to_string(),
+                token_id: "2".to_string(),
+                cw20_contract: "cw20addr".to_string(),
+                amount: 400,
+                reserved_for: None,
+                accepted: vec![],
+            }],
+        };
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("seller", &[]),
+            msg_update,
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+        let res: CollectionStatsResponse = from_binary(&res).unwrap();
+        assert_eq!(res.listing_count, 2);
+        assert_eq!(res.payment_tokens[0].floor_price, Some(300));
+
+        let _res =
+            execute_purchase(deps.as_mut(), mock_env(), "buyer", "collection_a", "1", 300).unwrap();
+
+        // a day later the first sale drops out of the 24h volume
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(24 * 3600);
+        let _res = execute_purchase(
+            deps.as_mut(),
+            later.clone(),
+            "buyer",
+            "collection_a",
+            "2",
+            400,
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), later, msg).unwrap();
+        let res: CollectionStatsResponse = from_binary(&res).unwrap();
+        assert_eq!(res.listing_count, 0);
+        assert_eq!(res.sale_count, 2);
+        assert_eq!(res.payment_tokens[0].payment_token, "cw20addr");
+        assert_eq!(res.payment_tokens[0].floor_price, None);
+        assert_eq!(res.payment_tokens[0].volume_24h, 400);
+        assert_eq!(res.payment_tokens[0].volume_all_time, 700);
+
+        // cancelling a listing by withdrawing the NFT removes it from the stats
+        let msg = ExecuteMsg::WithdrawNft {
+            cw721_contract: "collection_b".to_string(),
+            token_id: "1".to_string(),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        let msg = QueryMsg::CollectionStats {
+            cw721_contract: "collection_b".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: CollectionStatsResponse = from_binary(&res).unwrap();
+        assert_eq!(res.listing_count, 0);
+        assert_eq!(res.payment_tokens[0].floor_price, None);
+    }
+
+    #[test]
+    fn test_top_collections_by_volume() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        for (collection, price) in [
+            ("collection_a", 100),
+            ("collection_b", 300),
+            ("collection_c", 200),
+        ] {
+            let _res = execute_listing(deps.as_mut(), "seller", collection, "1", price).unwrap();
+            let _res = execute_purchase(deps.as_mut(), mock_env(), "buyer", collection, "1", price)
+                .unwrap();
+        }
+
+        let msg = QueryMsg::TopCollections {
+            payment_token: "cw20addr".to_string(),
+            start_after: None,
+            limit: Some(2),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: TopCollectionsResponse = from_binary(&res).unwrap();
+        let ranked: Vec<&str> = res
+            .collections
+            .iter()
+            .map(|c| c.cw721_contract.as_str())
+            .collect();
+        assert_eq!(ranked, vec!["collection_b", "collection_c"]);
+
+        let msg = QueryMsg::TopCollections {
+            payment_token: "cw20addr".to_string(),
+            start_after: Some("collection_c".to_string()),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: TopCollectionsResponse = from_binary(&res).unwrap();
+        assert_eq!(res.collections.len(), 1);
+        assert_eq!(res.collections[0].cw721_contract, "collection_a");
+        assert_eq!(res.collections[0].volume, 100);
+    }
+
+    fn execute_create_bundle(deps: DepsMut, price: Asset) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::CreateBundle {
+            nfts: vec![
+                NftItem {
+                    cw721_contract: "collection_a".to_string(),
+                    token_id: "1".to_string(),
+                },
+                NftItem {
+                    cw721_contract: "collection_b".to_string(),
+                    token_id: "1".to_string(),
+                },
+            ],
+            price,
+        };
+        execute(deps, mock_env(), mock_info("seller", &[]), msg)
+    }
+
+    #[test]
+    fn test_bundle_purchase_with_native_coins() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_a", "1", 300).unwrap();
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_b", "1", 50).unwrap();
+
+        let price = Asset {
+            info: AssetInfo::Native {
+                denom: DENOM.to_string(),
+            },
+            amount: 500,
+        };
+        let _res = execute_create_bundle(deps.as_mut(), price).unwrap();
+
+        // the individual asks are gone and cannot be recreated while bundled
+        assert!(!ASKS.has(deps.as_ref().storage, ("collection_a", "1")));
+        let msg = ExecuteMsg::BatchUpdateAsks {
+            asks: vec![AskUpdate {
+                cw721_contract: "collection_a".to_string(),
+                token_id: "1".to_string(),
+                cw20_contract: "cw20addr".to_string(),
+                amount: 10,
+                reserved_for: None,
+                accepted: vec![],
+            }],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg);
+        match res {
+            Err(ContractError::NftLocked {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::WithdrawNft {
+            cw721_contract: "collection_b".to_string(),
+            token_id: "1".to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg);
+        match res {
+            Err(ContractError::NftLocked {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = ExecuteMsg::PurchaseBundle {
+            bundle_id: 1,
+            referrer: None,
+        };
+        let info = mock_info("buyer", &[Coin::new(499, DENOM)]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        match res {
+            Err(ContractError::InvalidPayment {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // paying the right amount in a cw20 does not buy a native priced bundle
+        let cw20_msg = Cw20ReceiveMsg {
+            sender: "buyer".to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&Cw20HookMsg::PurchaseBundle {
+                bundle_id: 1,
+                referrer: None,
+            })
+            .unwrap(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("cw20addr", &[]),
+            ExecuteMsg::Receive(cw20_msg),
+        );
+        match res {
+            Err(ContractError::InvalidPayment {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let info = mock_info("buyer", &[Coin::new(500, DENOM)]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        // only the two nft transfers, the proceeds are credited to the seller's deposits
+        assert_eq!(res.messages.len(), 2);
+
+        let msg = QueryMsg::SalesByAddress {
+            address: "buyer".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: SalesResponse = from_binary(&res).unwrap();
+        assert_eq!(res.sales.len(), 2);
+        assert_eq!(res.sales[0].price + res.sales[1].price, 500);
+
+        let msg = QueryMsg::GetDeposits {
+            address: "seller".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount.amount, Uint128::new(500));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Bundle { bundle_id: 1 });
+        assert!(res.is_err());
+
+        let msg = QueryMsg::GetCw721Deposit {
+            address: "seller".to_string(),
+            contract: "collection_a".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_cancel_bundle_returns_nfts() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_a", "1", 300).unwrap();
+        let _res = execute_listing(deps.as_mut(), "seller", "collection_b", "1", 50).unwrap();
+
+        let price = Asset {
+            info: AssetInfo::Cw20 {
+                contract: "cw20addr".to_string(),
+            },
+            amount: 500,
+        };
+        let _res = execute_create_bundle(deps.as_mut(), price.clone()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Bundle { bundle_id: 1 }).unwrap();
+        let bundle: Bundle = from_binary(&res).unwrap();
+        assert_eq!(bundle.seller, "seller");
+        assert_eq!(bundle.price, price);
+
+        let msg = ExecuteMsg::CancelBundle { bundle_id: 1 };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("buyer", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("seller", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+
+        let msg = QueryMsg::Bundles {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: BundlesResponse = from_binary(&res).unwrap();
+        assert!(res.bundles.is_empty());
+    }
+
+    fn execute_fill_swap(
+        deps: DepsMut,
+        taker: &str,
+        cw721_contract: &str,
+        token_id: &str,
+    ) -> Result<Response, ContractError> {
+        let cw721_msg = Cw721ReceiveMsg {
+            sender: taker.to_string(),
+            token_id: token_id.to_string(),
+            msg: to_binary(&Cw721HookMsg::FillSwap { swap_id: 1 })?,
+        };
+
+        let msg = ExecuteMsg::ReceiveNft(cw721_msg);
+        let info = mock_info(cw721_contract, &[]);
+        execute(deps, mock_env(), info, msg)
+    }
+
+    fn execute_create_swap(deps: DepsMut) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::CreateSwap {
+            offered: vec![NftItem {
+                cw721_contract: "collection_a".to_string(),
+                token_id: "1".to_string(),
+            }],
+            wanted: vec![
+                NftItem {
+                    cw721_contract: "collection_b".to_string(),
+                    token_id: "7".to_string(),
+                },
+                NftItem {
+                    cw721_contract: "collection_c".to_string(),
+                    token_id: "9".to_string(),
+                },
+            ],
+            maker_top_up: Some(Asset {
+                info: AssetInfo::Native {
+                    denom: DENOM.to_string(),
+                },
+                amount: 1000,
+            }),
+            taker_top_up: Some(Asset {
+                info: AssetInfo::Cw20 {
+                    contract: "contract_addr".to_string(),
+                },
+                amount: 40,
+            }),
+        };
+        execute(deps, mock_env(), mock_info(SENDER, &[]), msg)
+    }
+
+    #[test]
+    fn test_swap_settles_when_all_wanted_nfts_arrive() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_deposit(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), SENDER, "collection_a", "1", 300).unwrap();
+        // credits "right_guy" with 100 of the "contract_addr" cw20
+        let _res = execute_cw20_deposit(deps.as_mut()).unwrap();
+
+        let _res = execute_create_swap(deps.as_mut()).unwrap();
+        assert!(!ASKS.has(deps.as_ref().storage, ("collection_a", "1")));
+
+        let res = execute_fill_swap(deps.as_mut(), "right_guy", "collection_b", "8");
+        match res {
+            Err(ContractError::NftNotWanted {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let res = execute_fill_swap(deps.as_mut(), "right_guy", "collection_b", "7").unwrap();
+        assert!(res.messages.is_empty());
+
+        let res = execute_fill_swap(deps.as_mut(), "wrong_guy", "collection_c", "9");
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let res = execute_fill_swap(deps.as_mut(), "right_guy", "collection_c", "9").unwrap();
+        // the offered NFT goes to the taker and both wanted NFTs to the maker
+        assert_eq!(res.messages.len(), 3);
+
+        let msg = QueryMsg::GetDeposits {
+            address: "right_guy".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount.amount, Uint128::new(1000));
+
+        let msg = QueryMsg::GetCw20Deposit {
+            address: SENDER.to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: Cw20DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount, 40);
+
+        let msg = QueryMsg::GetCw20Deposit {
+            address: "right_guy".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: Cw20DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount, 60);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Swap { swap_id: 1 });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_swap_fills_cannot_lock_out_other_takers() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), SENDER, "collection_a", "1", 300).unwrap();
+
+        // wanting the same NFT twice would leave the swap unsettleable
+        let wanted = NftItem {
+            cw721_contract: "collection_b".to_string(),
+            token_id: "7".to_string(),
+        };
+        let msg = ExecuteMsg::CreateSwap {
+            offered: vec![NftItem {
+                cw721_contract: "collection_a".to_string(),
+                token_id: "1".to_string(),
+            }],
+            wanted: vec![wanted.clone(), wanted],
+            maker_top_up: None,
+            taker_top_up: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg);
+        match res {
+            Err(ContractError::DuplicateNft {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let _res = execute_deposit(deps.as_mut()).unwrap();
+        let _res = execute_cw20_deposit(deps.as_mut()).unwrap();
+        let _res = execute_create_swap(deps.as_mut()).unwrap();
+        let _res = execute_fill_swap(deps.as_mut(), "right_guy", "collection_b", "7").unwrap();
+        let res = execute_fill_swap(deps.as_mut(), "right_guy", "collection_b", "7");
+        match res {
+            Err(ContractError::NftNotWanted {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // once the partial fill times out, another taker's NFT sends it back
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(24 * 3600);
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            sender: "another_taker".to_string(),
+            token_id: "9".to_string(),
+            msg: to_binary(&Cw721HookMsg::FillSwap { swap_id: 1 }).unwrap(),
+        });
+        let res = execute(deps.as_mut(), env, mock_info("collection_c", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Swap { swap_id: 1 }).unwrap();
+        let res: SwapResponse = from_binary(&res).unwrap();
+        assert_eq!(res.taker, Some("another_taker".to_string()));
+        assert_eq!(res.received.len(), 1);
+    }
+
+    #[test]
+    fn test_cancel_swap_returns_both_sides() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_deposit(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), SENDER, "collection_a", "1", 300).unwrap();
+        let _res = execute_create_swap(deps.as_mut()).unwrap();
+        let _res = execute_fill_swap(deps.as_mut(), "right_guy", "collection_b", "7").unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Swap { swap_id: 1 }).unwrap();
+        let res: SwapResponse = from_binary(&res).unwrap();
+        assert_eq!(res.taker, Some("right_guy".to_string()));
+        assert_eq!(res.received.len(), 1);
+
+        let msg = ExecuteMsg::CancelSwap { swap_id: 1 };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("right_guy", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg).unwrap();
+        // the offered NFT goes back to the maker and the partial fill back to the taker
+        assert_eq!(res.messages.len(), 2);
+
+        let msg = QueryMsg::GetDeposits {
+            address: SENDER.to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount.amount, Uint128::new(AMOUNT));
+    }
+
+    #[test]
+    fn test_reserved_listing() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "open", 100).unwrap();
+
+        let cw721_msg = Cw721ReceiveMsg {
+            sender: SENDER.to_string(),
+            token_id: "otc".to_string(),
+            msg: to_binary(&Cw721HookMsg::Deposit {
+                owner: SENDER.to_string(),
+                token_id: "otc".to_string(),
+                cw20_contract: "cw20addr".to_string(),
+                amount: 500,
+                reserved_for: Some("otc_buyer".to_string()),
+                accepted: vec![],
+            })
+            .unwrap(),
+        };
+        let info = mock_info("contract_addr", &[]);
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::ReceiveNft(cw721_msg),
+        )
+        .unwrap();
+
+        // the reserved ask is hidden from everyone but the buyer and the seller
+        for (viewer, expected) in [(None, 1), (Some("someone"), 1), (Some("otc_buyer"), 2)] {
+            let msg = QueryMsg::Asks {
+                cw721_contract: "contract_addr".to_string(),
+                viewer: viewer.map(String::from),
+                start_after: None,
+                limit: None,
+            };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            let res: AsksResponse = from_binary(&res).unwrap();
+            assert_eq!(res.asks.len(), expected);
+        }
+
+        let msg = QueryMsg::AsksBySeller {
+            seller: SENDER.to_string(),
+            viewer: Some(SENDER.to_string()),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: AsksResponse = from_binary(&res).unwrap();
+        assert_eq!(res.asks.len(), 2);
+        assert_eq!(res.asks[1].reserved_for, Some("otc_buyer".to_string()));
+
+        let res = execute_purchase(
+            deps.as_mut(),
+            mock_env(),
+            "someone",
+            "contract_addr",
+            "otc",
+            500,
+        );
+        match res {
+            Err(ContractError::ReservedListing {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let res = execute_purchase(
+            deps.as_mut(),
+            mock_env(),
+            "otc_buyer",
+            "contract_addr",
+            "otc",
+            500,
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(!ASKS.has(deps.as_mut().storage, ("contract_addr", "otc")));
+    }
+
+    fn execute_rent(
+        deps: DepsMut,
+        env: Env,
+        renter: &str,
+        periods: u64,
+        amount: u128,
+    ) -> Result<Response, ContractError> {
+        let cw20_msg = Cw20ReceiveMsg {
+            sender: renter.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&Cw20HookMsg::Rent {
+                cw721_contract: "contract_addr".to_string(),
+                token_id: "TNT".to_string(),
+                periods,
+            })?,
+        };
+
+        let msg = ExecuteMsg::Receive(cw20_msg);
+        let info = mock_info("cw20addr", &[]);
+        execute(deps, env, info, msg)
+    }
+
+    #[test]
+    fn test_rental_lifecycle() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+
+        for (price_per_period, period) in [(0, 3600), (50, 0)] {
+            let msg = ExecuteMsg::ListRental {
+                cw721_contract: "contract_addr".to_string(),
+                token_id: "TNT".to_string(),
+                cw20_contract: "cw20addr".to_string(),
+                price_per_period,
+                period,
+            };
+            let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg);
+            match res {
+                Err(ContractError::InvalidRentalTerms {}) => {}
+                _ => panic!("should error here"),
+            }
+        }
+
+        let msg = ExecuteMsg::ListRental {
+            cw721_contract: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+            cw20_contract: "cw20addr".to_string(),
+            price_per_period: 50,
+            period: 3600,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg).unwrap();
+        assert!(!ASKS.has(deps.as_mut().storage, ("contract_addr", "TNT")));
+
+        let res = execute_rent(deps.as_mut(), mock_env(), "renter", 2, 50);
+        match res {
+            Err(ContractError::InvalidPayment {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // paid in full, but the rental would end beyond any representable time
+        let res = execute_rent(
+            deps.as_mut(),
+            mock_env(),
+            "renter",
+            u64::MAX,
+            50 * u64::MAX as u128,
+        );
+        match res {
+            Err(ContractError::Overflow {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // two periods are paid straight to the owner
+        let res = execute_rent(deps.as_mut(), mock_env(), "renter", 2, 100).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let query_msg = QueryMsg::CurrentUser {
+            cw721_contract: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+        let res: CurrentUserResponse = from_binary(&res).unwrap();
+        assert_eq!(res.user, Some("renter".to_string()));
+        assert_eq!(
+            res.expires_at,
+            Some(mock_env().block.time.plus_seconds(7200))
+        );
+
+        let res = execute_rent(deps.as_mut(), mock_env(), "another_renter", 1, 50);
+        match res {
+            Err(ContractError::RentalActive {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let reclaim = ExecuteMsg::ReclaimRental {
+            cw721_contract: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(SENDER, &[]),
+            reclaim.clone(),
+        );
+        match res {
+            Err(ContractError::RentalActive {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(7200);
+
+        let res = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+        let res: CurrentUserResponse = from_binary(&res).unwrap();
+        assert_eq!(res.user, None);
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("renter", &[]),
+            reclaim.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let res = execute(deps.as_mut(), env, mock_info(SENDER, &[]), reclaim).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    fn connect_ics721_channel(deps: DepsMut, channel_id: &str) {
+        let msg = mock_ibc_channel_connect_ack(channel_id, IbcOrder::Unordered, ICS721_VERSION);
+        let _res = ibc_channel_connect(deps, mock_env(), msg).unwrap();
+    }
+
+    fn execute_ibc_transfer(deps: DepsMut, channel_id: &str) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::IbcTransferNft {
+            cw721_contract: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+            channel_id: channel_id.to_string(),
+            receiver: "remote_receiver".to_string(),
+            timeout: None,
+        };
+        execute(deps, mock_env(), mock_info(SENDER, &[]), msg)
+    }
+
+    fn sent_packet() -> NonFungibleTokenPacketData {
+        NonFungibleTokenPacketData {
+            class_id: "contract_addr".to_string(),
+            class_uri: None,
+            token_ids: vec!["TNT".to_string()],
+            token_uris: None,
+            sender: SENDER.to_string(),
+            receiver: "remote_receiver".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_ics721_channel_handshake() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        let msg = mock_ibc_channel_open_init("channel-1", IbcOrder::Ordered, ICS721_VERSION);
+        match ibc_channel_open(deps.as_mut(), mock_env(), msg) {
+            Err(ContractError::OnlyUnorderedChannel {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = mock_ibc_channel_open_try("channel-1", IbcOrder::Unordered, "ics20-1");
+        match ibc_channel_open(deps.as_mut(), mock_env(), msg) {
+            Err(ContractError::InvalidIbcVersion { .. }) => {}
+            _ => panic!("should error here"),
+        }
+
+        let msg = mock_ibc_channel_open_try("channel-1", IbcOrder::Unordered, ICS721_VERSION);
+        ibc_channel_open(deps.as_mut(), mock_env(), msg).unwrap();
+
+        // transfers need a connected channel
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        match execute_ibc_transfer(deps.as_mut(), "channel-1") {
+            Err(ContractError::UnknownChannel {}) => {}
+            _ => panic!("should error here"),
+        }
+        connect_ics721_channel(deps.as_mut(), "channel-1");
+        let _res = execute_ibc_transfer(deps.as_mut(), "channel-1").unwrap();
+
+        let msg = mock_ibc_channel_close_init("channel-1", IbcOrder::Unordered, ICS721_VERSION);
+        match ibc_channel_close(deps.as_mut(), mock_env(), msg) {
+            Err(ContractError::CannotCloseChannel {}) => {}
+            _ => panic!("should error here"),
+        }
+    }
+
+    #[test]
+    fn test_ics721_transfer_and_return() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        connect_ics721_channel(deps.as_mut(), "channel-1");
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+
+        // escrowed NFTs cannot leave the chain while trading is paused
+        let pause = |paused| SudoMsg::SetPaused { paused };
+        let _res = sudo(deps.as_mut(), mock_env(), pause(true)).unwrap();
+        match execute_ibc_transfer(deps.as_mut(), "channel-1") {
+            Err(ContractError::Paused {}) => {}
+            _ => panic!("should error here"),
+        }
+        let _res = sudo(deps.as_mut(), mock_env(), pause(false)).unwrap();
+
+        let res = execute_ibc_transfer(deps.as_mut(), "channel-1").unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket {
+                channel_id, data, ..
+            }) => {
+                assert_eq!(channel_id, "channel-1");
+                let packet: NonFungibleTokenPacketData = from_binary(data).unwrap();
+                assert_eq!(packet, sent_packet());
+            }
+            _ => panic!("should send a packet"),
+        }
+        assert!(!ASKS.has(deps.as_mut().storage, ("contract_addr", "TNT")));
+
+        // the NFT stays escrowed once the counterparty acknowledges it
+        let ack =
+            IbcAcknowledgement::new(to_binary(&Ics721Ack::Result(Binary::from(vec![1]))).unwrap());
+        let msg = mock_ibc_packet_ack("channel-1", &sent_packet(), ack).unwrap();
+        let res = ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(res.messages.is_empty());
+
+        // it comes back under the class id the counterparty prefixed it with
+        let mut packet = sent_packet();
+        packet.class_id = "their-port/channel-1234/contract_addr".to_string();
+        packet.receiver = "home_receiver".to_string();
+        let msg = mock_ibc_packet_recv("channel-1", &packet).unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg.clone()).unwrap();
+        let ack: Ics721Ack = from_binary(&res.acknowledgement).unwrap();
+        assert_eq!(ack, Ics721Ack::Result(Binary::from(vec![1])));
+        assert_eq!(res.messages.len(), 1);
+
+        // and cannot be released a second time
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+        let ack: Ics721Ack = from_binary(&res.acknowledgement).unwrap();
+        assert!(matches!(ack, Ics721Ack::Error(_)));
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn test_ics721_refund_on_error_and_timeout() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        connect_ics721_channel(deps.as_mut(), "channel-1");
+
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        let _res = execute_ibc_transfer(deps.as_mut(), "channel-1").unwrap();
+        let ack =
+            IbcAcknowledgement::new(to_binary(&Ics721Ack::Error("rejected".to_string())).unwrap());
+        let msg = mock_ibc_packet_ack("channel-1", &sent_packet(), ack).unwrap();
+        let res = ibc_packet_ack(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        let _res = execute_ibc_transfer(deps.as_mut(), "channel-1").unwrap();
+        let msg = mock_ibc_packet_timeout("channel-1", &sent_packet()).unwrap();
+        let res = ibc_packet_timeout(deps.as_mut(), mock_env(), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => {
+                assert_eq!(contract_addr, "contract_addr")
+            }
+            _ => panic!("should return the NFT"),
+        }
+    }
+
+    #[test]
+    fn test_ics721_receive_mints_vouchers() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        connect_ics721_channel(deps.as_mut(), "channel-1");
+
+        let packet = NonFungibleTokenPacketData {
+            class_id: "remote_collection".to_string(),
+            class_uri: None,
+            token_ids: vec!["1".to_string(), "2".to_string()],
+            token_uris: None,
+            sender: "remote_sender".to_string(),
+            receiver: "receiver".to_string(),
+        };
+
+        // the first NFT of a class creates its voucher collection
+        let msg = mock_ibc_packet_recv("channel-1", &packet).unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, .. }) => {
+                assert_eq!(*code_id, NFT_CODE_ID)
+            }
+            _ => panic!("should instantiate the voucher collection"),
+        }
+
+        // protobuf encoded MsgInstantiateContractResponse { contract_address: "voucher_addr" }
+        let mut data = vec![0x0a, 12];
+        data.extend_from_slice(b"voucher_addr");
+        let reply_msg = Reply {
+            id: INSTANTIATE_VOUCHER_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(Binary::from(data)),
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+
+        let msg = QueryMsg::VoucherContract {
+            class_id: "our-port/channel-1/remote_collection".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: VoucherContractResponse = from_binary(&res).unwrap();
+        assert_eq!(res.cw721_contract, Some("voucher_addr".to_string()));
+
+        // later NFTs of the class are minted straight into it
+        let msg = mock_ibc_packet_recv("channel-1", &packet).unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => {
+                assert_eq!(contract_addr, "voucher_addr")
+            }
+            _ => panic!("should mint a voucher"),
+        }
+    }
+
+    #[test]
+    fn test_marketplace_events() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        let res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        assert_eq!(res.events[0].ty, "marketplace-list");
+
+        let res = execute_purchase(
+            deps.as_mut(),
+            mock_env(),
+            "buyer",
+            "contract_addr",
+            "TNT",
+            100,
+        )
+        .unwrap();
+        let sale = MarketplaceEvent::Sale {
+            sale_id: 1,
+            collection: "contract_addr".to_string(),
+            token_id: "TNT".to_string(),
+            seller: SENDER.to_string(),
+            buyer: "buyer".to_string(),
+            price: Uint128::new(100),
+            payment: "cw20addr".to_string(),
+            fee: Uint128::zero(),
+            royalty: Uint128::zero(),
+            referrer: None,
+            referral_fee: Uint128::zero(),
+        };
+        assert_eq!(res.events, vec![Event::from(sale.clone())]);
+
+        // indexers see the type with the `wasm-` prefix added by the chain
+        let mut event = res.events[0].clone();
+        event.ty = format!("wasm-{}", event.ty);
+        assert_eq!(MarketplaceEvent::try_from(&event).unwrap(), sale);
+        let events = vec![Event::new("transfer"), event];
+        assert_eq!(MarketplaceEvent::parse_all(&events).unwrap(), vec![sale]);
+
+        let event = Event::new("wasm-marketplace-sale").add_attribute("sale_id", "1");
+        assert!(MarketplaceEvent::try_from(&event).is_err());
+    }
+
+    #[test]
+    fn test_admin_and_order_events() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        let msg = ExecuteMsg::CancelOrder { nonce: 7 };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg).unwrap();
+        assert_eq!(
+            MarketplaceEvent::parse_all(&res.events).unwrap(),
+            vec![MarketplaceEvent::CancelOrder {
+                seller: SENDER.to_string(),
+                nonce: 7,
+            }]
+        );
+
+        let msg = SudoMsg::SetPaused { paused: true };
+        let res = sudo(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(res.attributes.is_empty());
+        assert_eq!(
+            MarketplaceEvent::parse_all(&res.events).unwrap(),
+            vec![MarketplaceEvent::SetPaused { paused: true }]
+        );
+    }
+
+    #[test]
+    fn test_sale_proceeds_credited_to_seller() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        let _res = execute_purchase(
+            deps.as_mut(),
+            mock_env(),
+            "buyer",
+            "contract_addr",
+            "TNT",
+            100,
+        )
+        .unwrap();
+
+        let msg = QueryMsg::GetCw20Deposit {
+            address: SENDER.to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: Cw20DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].contract, "cw20addr");
+        assert_eq!(res.deposits[0].amount, 100);
+
+        // the proceeds can be withdrawn like any other deposit
+        let msg = ExecuteMsg::BatchWithdraw {
+            withdrawals: vec![Withdrawal::Cw20 {
+                cw20_contract: "cw20addr".to_string(),
+                amount: 100,
+            }],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(SENDER, &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_two_step_admin_transfer() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        let propose = |addr: &str, expires_in: u64| ExecuteMsg::ProposeNewAdmin {
+            addr: addr.to_string(),
+            expires_in,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrong_guy", &[]),
+            propose("new_admin", 100),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(SENDER, &[]),
+            propose("new_admin", 30 * 24 * 3600),
+        );
+        match res {
+            Err(ContractError::ProposalTooLong { .. }) => {}
+            _ => panic!("should error here"),
+        }
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(SENDER, &[]),
+            propose("new_admin", 0),
+        );
+        match res {
+            Err(ContractError::ProposalTooShort {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // a mistyped address can be replaced, and only the proposed address may claim
+        let info = mock_info(SENDER, &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            propose("typo", 100),
+        )
+        .unwrap();
+        execute(deps.as_mut(), mock_env(), info, propose("new_admin", 100)).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingAdmin {}).unwrap();
+        let res: PendingAdminResponse = from_binary(&res).unwrap();
+        assert_eq!(res.new_admin, Some("new_admin".to_string()));
+        assert_eq!(
+            res.expires_at,
+            Some(mock_env().block.time.plus_seconds(100))
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("typo", &[]),
+            ExecuteMsg::ClaimAdmin {},
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("new_admin", &[]),
+            ExecuteMsg::ClaimAdmin {},
+        );
+        match res {
+            Err(ContractError::AdminProposalExpired {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_admin", &[]),
+            ExecuteMsg::ClaimAdmin {},
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let res: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(res.admin, "new_admin");
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingAdmin {}).unwrap();
+        let res: PendingAdminResponse = from_binary(&res).unwrap();
+        assert_eq!(res.new_admin, None);
+
+        // the new admin can withdraw a proposal before it is claimed
+        let info = mock_info("new_admin", &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            propose(SENDER, 100),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::DropAdminProposal {},
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(SENDER, &[]),
+            ExecuteMsg::ClaimAdmin {},
+        );
+        match res {
+            Err(ContractError::AdminProposalNotFound {}) => {}
+            _ => panic!("should error here"),
+        }
+    }
+
+    #[test]
+    fn test_referral_fees() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+        let msg = SudoMsg::UpdateFees {
+            fee_bps: 1000,
+            fee_recipient: Some("fee_collector".to_string()),
+        };
+        let _res = sudo(deps.as_mut(), mock_env(), msg).unwrap();
+
+        // only the admin registers referrers, for at most the whole fee
+        let register = |share_bps| ExecuteMsg::SetReferrer {
+            address: "partner".to_string(),
+            share_bps,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("partner", &[]),
+            register(5000),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("should error here"),
+        }
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(SENDER, &[]),
+            register(10_001),
+        );
+        match res {
+            Err(ContractError::InvalidFee { .. }) => {}
+            _ => panic!("should error here"),
+        }
+        let info = mock_info(SENDER, &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, register(5000)).unwrap();
+
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        let purchase = |referrer: &str| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "buyer".to_string(),
+                amount: Uint128::new(100),
+                msg: to_binary(&Cw20HookMsg::Purchase {
+                    token_id: "TNT".to_string(),
+                    cw721_contract: "contract_addr".to_string(),
+                    referrer: Some(referrer.to_string()),
+                })
+                .unwrap(),
+            })
+        };
+        let info = mock_info("cw20addr", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), purchase("buyer"));
+        match res {
+            Err(ContractError::SelfReferral {}) => {}
+            _ => panic!("should error here"),
+        }
+
+        // the referrer earns half of the 10% fee
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), purchase("partner")).unwrap();
+        let events = MarketplaceEvent::parse_all(&res.events).unwrap();
+        assert!(matches!(
+            &events[0],
+            MarketplaceEvent::Sale { fee, referrer: Some(referrer), referral_fee, .. }
+                if *fee == Uint128::new(10) && referrer == "partner" && *referral_fee == Uint128::new(5)
+        ));
+        for (address, amount) in [(SENDER, 90), ("fee_collector", 5), ("partner", 5)] {
+            let msg = QueryMsg::GetCw20Deposit {
+                address: address.to_string(),
+            };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            let res: Cw20DepositResponse = from_binary(&res).unwrap();
+            assert_eq!(res.deposits[0].amount, amount);
+        }
+
+        // an unregistered referrer does not block the sale, the whole fee goes to the marketplace
+        let _res = execute_listing(deps.as_mut(), SENDER, "contract_addr", "TNT", 100).unwrap();
+        let _res = execute(deps.as_mut(), mock_env(), info, purchase("stranger")).unwrap();
+        let msg = QueryMsg::GetCw20Deposit {
+            address: "fee_collector".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: Cw20DepositResponse = from_binary(&res).unwrap();
+        assert_eq!(res.deposits[0].amount, 15);
+        let msg = QueryMsg::GetCw20Deposit {
+            address: "stranger".to_string(),
+        };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+
+        // earnings outlive the registration
+        let info = mock_info(SENDER, &[]);
+        let msg = ExecuteMsg::RemoveReferrer {
+            address: "partner".to_string(),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = QueryMsg::ReferralEarnings {
+            referrer: "partner".to_string(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ReferralEarningsResponse = from_binary(&res).unwrap();
+        assert_eq!(res.share_bps, None);
+        assert_eq!(
+            res.earnings,
+            vec![Asset {
+                info: AssetInfo::Cw20 {
+                    contract: "cw20addr".to_string(),
+                },
+                amount: 5,
+            }]
+        );
+        let msg = QueryMsg::Referrers {
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res: ReferrersResponse = from_binary(&res).unwrap();
+        assert!(res.referrers.is_empty());
+    }
+
+    #[test]
+    fn test_soulbound_deposit_refused() {
+        let mut deps = mock_dependencies();
+        let _res = proper_instantiate(deps.as_mut()).unwrap();
+
+        // the collection reports every token as soulbound
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == "contract_addr" => {
+                let res = nft::contract::SoulboundResponse { soulbound: true };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "wasm".to_string(),
+            }),
+        });
+        match execute_cw721_deposit(deps.as_mut()) {
+            Err(ContractError::SoulboundToken {}) => {}
+            _ => panic!("should error here"),
+        }
+    }
 }
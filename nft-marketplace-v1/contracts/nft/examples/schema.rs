@@ -7,7 +7,10 @@ use cw721::{
     AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, NftInfoResponse,
     NumTokensResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
 };
-use nft::contract::{ExecuteMsg, Extension, InstantiateMsg, MinterResponse, QueryMsg};
+use nft::contract::{
+    CheckRoyaltiesResponse, ExecuteMsg, Extension, InstantiateMsg, MetadataConfigResponse,
+    MinterResponse, QueryMsg, RoyaltiesInfoResponse, RoyaltyConfigResponse, SoulboundResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -28,6 +31,11 @@ fn main() {
     export_schema(&schema_for!(OperatorsResponse), &out_dir);
     export_schema(&schema_for!(ContractInfoResponse), &out_dir);
     export_schema(&schema_for!(MinterResponse), &out_dir);
+    export_schema(&schema_for!(MetadataConfigResponse), &out_dir);
+    export_schema(&schema_for!(RoyaltyConfigResponse), &out_dir);
+    export_schema(&schema_for!(RoyaltiesInfoResponse), &out_dir);
+    export_schema(&schema_for!(CheckRoyaltiesResponse), &out_dir);
+    export_schema(&schema_for!(SoulboundResponse), &out_dir);
     export_schema_with_title(
         &schema_for!(NftInfoResponse<Extension>),
         &out_dir,
@@ -1,9 +1,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Empty;
+use cosmwasm_std::{Binary, Empty, Uint128};
 use cw2::set_contract_version;
-pub use cw721_base::{ContractError, InstantiateMsg, MintMsg, MinterResponse};
+use cw721::Expiration;
+pub use cw721_base::{MintMsg, MinterResponse};
+
+pub use crate::error::ContractError;
 
 // Version info for migration
 const CONTRACT_NAME: &str = "crates.io:cw721-metadata-onchain";
@@ -28,20 +31,195 @@ pub struct Metadata {
     pub background_color: Option<String>,
     pub animation_url: Option<String>,
     pub youtube_url: Option<String>,
+    /// Receives the CW2981 royalties of the token, defaults to the collection's address
+    pub royalty_payment_address: Option<String>,
+    /// Royalty in percent of the sale price, defaults to the collection's percentage
+    pub royalty_percentage: Option<u64>,
+    /// Set at mint, makes the token non-transferable. Metadata updates keep it.
+    #[serde(default)]
+    pub soulbound: bool,
 }
 
 pub type Extension = Option<Metadata>;
 
 pub type Cw721MetadataContract<'a> = cw721_base::Cw721Contract<'a, Extension, Empty>;
-pub type ExecuteMsg = cw721_base::ExecuteMsg<Extension>;
-pub type QueryMsg = cw721_base::QueryMsg;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct InstantiateMsg {
+    pub name: String,
+    pub symbol: String,
+    /// The only address that can mint new tokens
+    pub minter: String,
+    /// Makes every token of the collection non-transferable
+    #[serde(default)]
+    pub soulbound: bool,
+}
+
+/// The cw721 messages, see `cw721_base::ExecuteMsg`, and the metadata ones of this collection
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    TransferNft {
+        recipient: String,
+        token_id: String,
+    },
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    RevokeAll {
+        operator: String,
+    },
+    Mint(MintMsg<Extension>),
+    Burn {
+        token_id: String,
+    },
+    /// Replaces the metadata of a token, can only be called by the minter or the metadata editor
+    UpdateMetadata {
+        token_id: String,
+        metadata: Metadata,
+    },
+    /// Sets or removes the metadata editor, can only be called by the minter
+    SetMetadataEditor {
+        editor: Option<String>,
+    },
+    /// Permanently prevents metadata updates, can only be called by the minter
+    FreezeMetadata {},
+    /// Sets the royalty defaults of tokens that name none and the highest royalty percentage
+    /// tokens may set, can only be called by the minter
+    UpdateRoyaltyConfig {
+        payment_address: Option<String>,
+        percentage: Option<u64>,
+        max_percentage: u64,
+    },
+}
+
+/// The cw721 queries, see `cw721_base::QueryMsg`, and the metadata ones of this collection
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    Approval {
+        token_id: String,
+        spender: String,
+        include_expired: Option<bool>,
+    },
+    Approvals {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    AllOperators {
+        owner: String,
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    NumTokens {},
+    ContractInfo {},
+    NftInfo {
+        token_id: String,
+    },
+    AllNftInfo {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    AllTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Minter {},
+    /// Return type: `MetadataConfigResponse`
+    MetadataConfig {},
+    /// Return type: `RoyaltyConfigResponse`
+    RoyaltyConfig {},
+    /// Whether the token can never be transferred
+    /// Return type: `SoulboundResponse`
+    Soulbound {
+        token_id: String,
+    },
+    /// The CW2981 royalty queries
+    Extension {
+        msg: Cw2981QueryMsg,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw2981QueryMsg {
+    /// The royalty owed on a sale of the token for `sale_price`
+    /// Return type: `RoyaltiesInfoResponse`
+    RoyaltyInfo {
+        token_id: String,
+        sale_price: Uint128,
+    },
+    /// Whether the contract implements CW2981
+    /// Return type: `CheckRoyaltiesResponse`
+    CheckRoyalties {},
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MetadataConfigResponse {
+    pub editor: Option<String>,
+    pub frozen: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SoulboundResponse {
+    pub soulbound: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RoyaltyConfigResponse {
+    pub payment_address: Option<String>,
+    pub percentage: Option<u64>,
+    pub max_percentage: u64,
+}
+
+/// An empty address and amount mean that no royalty is owed
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct CheckRoyaltiesResponse {
+    pub royalty_payments: bool,
+}
 
 #[cfg(not(feature = "library"))]
 pub mod entry {
     use super::*;
 
+    use crate::state::{RoyaltyConfig, METADATA_CONFIG, ROYALTY_CONFIG, SOULBOUND};
     use cosmwasm_std::entry_point;
-    use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+    use cosmwasm_std::{to_binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+    use cw721_base::{
+        ExecuteMsg as Cw721ExecuteMsg, InstantiateMsg as Cw721InstantiateMsg,
+        QueryMsg as Cw721QueryMsg,
+    };
 
     // This makes a conscious choice on the various generics used by the contract
     #[entry_point]
@@ -51,10 +229,15 @@ pub mod entry {
         info: MessageInfo,
         msg: InstantiateMsg,
     ) -> Result<Response, ContractError> {
+        SOULBOUND.save(deps.storage, &msg.soulbound)?;
+        let msg = Cw721InstantiateMsg {
+            name: msg.name,
+            symbol: msg.symbol,
+            minter: msg.minter,
+        };
         let res = Cw721MetadataContract::default().instantiate(deps.branch(), env, info, msg)?;
         // Explicitly set contract name and version, otherwise set to cw721-base info
-        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)
-            .map_err(ContractError::Std)?;
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
         Ok(res)
     }
 
@@ -65,11 +248,374 @@ pub mod entry {
         info: MessageInfo,
         msg: ExecuteMsg,
     ) -> Result<Response, ContractError> {
-        Cw721MetadataContract::default().execute(deps, env, info, msg)
+        let msg = match msg {
+            ExecuteMsg::TransferNft {
+                recipient,
+                token_id,
+            } => {
+                ensure_transferable(deps.as_ref(), &token_id)?;
+                Cw721ExecuteMsg::TransferNft {
+                    recipient,
+                    token_id,
+                }
+            }
+            ExecuteMsg::SendNft {
+                contract,
+                token_id,
+                msg,
+            } => {
+                ensure_transferable(deps.as_ref(), &token_id)?;
+                Cw721ExecuteMsg::SendNft {
+                    contract,
+                    token_id,
+                    msg,
+                }
+            }
+            ExecuteMsg::Approve {
+                spender,
+                token_id,
+                expires,
+            } => {
+                ensure_transferable(deps.as_ref(), &token_id)?;
+                Cw721ExecuteMsg::Approve {
+                    spender,
+                    token_id,
+                    expires,
+                }
+            }
+            ExecuteMsg::Revoke { spender, token_id } => {
+                Cw721ExecuteMsg::Revoke { spender, token_id }
+            }
+            // operators of a soulbound collection could move none of its tokens. Soulbound tokens
+            // of other collections are guarded on transfer instead.
+            ExecuteMsg::ApproveAll { operator, expires } => {
+                if SOULBOUND.may_load(deps.storage)?.unwrap_or_default() {
+                    return Err(ContractError::Soulbound {});
+                }
+                Cw721ExecuteMsg::ApproveAll { operator, expires }
+            }
+            ExecuteMsg::RevokeAll { operator } => Cw721ExecuteMsg::RevokeAll { operator },
+            ExecuteMsg::Mint(msg) => {
+                if let Some(metadata) = &msg.extension {
+                    validate_royalty(deps.as_ref(), metadata)?;
+                }
+                Cw721ExecuteMsg::Mint(msg)
+            }
+            ExecuteMsg::Burn { token_id } => {
+                if is_soulbound(deps.as_ref(), &token_id)? {
+                    return execute_burn_soulbound(deps, info, token_id);
+                }
+                Cw721ExecuteMsg::Burn { token_id }
+            }
+            ExecuteMsg::UpdateMetadata { token_id, metadata } => {
+                return execute_update_metadata(deps, info, token_id, metadata)
+            }
+            ExecuteMsg::SetMetadataEditor { editor } => {
+                return execute_set_metadata_editor(deps, info, editor)
+            }
+            ExecuteMsg::FreezeMetadata {} => return execute_freeze_metadata(deps, info),
+            ExecuteMsg::UpdateRoyaltyConfig {
+                payment_address,
+                percentage,
+                max_percentage,
+            } => {
+                return execute_update_royalty_config(
+                    deps,
+                    info,
+                    payment_address,
+                    percentage,
+                    max_percentage,
+                )
+            }
+        };
+        Ok(Cw721MetadataContract::default().execute(deps, env, info, msg)?)
+    }
+
+    pub fn execute_update_metadata(
+        deps: DepsMut,
+        info: MessageInfo,
+        token_id: String,
+        metadata: Metadata,
+    ) -> Result<Response, ContractError> {
+        let contract = Cw721MetadataContract::default();
+        let config = METADATA_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+        if config.frozen {
+            return Err(ContractError::MetadataFrozen {});
+        }
+        if info.sender != contract.minter.load(deps.storage)?
+            && Some(&info.sender) != config.editor.as_ref()
+        {
+            return Err(ContractError::Unauthorized {});
+        }
+        validate_royalty(deps.as_ref(), &metadata)?;
+
+        let mut token = contract.tokens.load(deps.storage, &token_id)?;
+        let soulbound = token
+            .extension
+            .as_ref()
+            .is_some_and(|metadata| metadata.soulbound);
+        token.extension = Some(Metadata {
+            soulbound,
+            ..metadata
+        });
+        contract.tokens.save(deps.storage, &token_id, &token)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "update_metadata")
+            .add_attribute("sender", info.sender)
+            .add_attribute("token_id", token_id))
+    }
+
+    pub fn execute_set_metadata_editor(
+        deps: DepsMut,
+        info: MessageInfo,
+        editor: Option<String>,
+    ) -> Result<Response, ContractError> {
+        ensure_minter(deps.as_ref(), &info)?;
+        let mut config = METADATA_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+        config.editor = editor
+            .map(|editor| deps.api.addr_validate(&editor))
+            .transpose()?;
+        METADATA_CONFIG.save(deps.storage, &config)?;
+
+        let mut res = Response::new().add_attribute("action", "set_metadata_editor");
+        if let Some(editor) = config.editor {
+            res = res.add_attribute("editor", editor);
+        }
+        Ok(res)
+    }
+
+    pub fn execute_freeze_metadata(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        ensure_minter(deps.as_ref(), &info)?;
+        let mut config = METADATA_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+        config.frozen = true;
+        METADATA_CONFIG.save(deps.storage, &config)?;
+
+        Ok(Response::new().add_attribute("action", "freeze_metadata"))
+    }
+
+    pub fn execute_update_royalty_config(
+        deps: DepsMut,
+        info: MessageInfo,
+        payment_address: Option<String>,
+        percentage: Option<u64>,
+        max_percentage: u64,
+    ) -> Result<Response, ContractError> {
+        ensure_minter(deps.as_ref(), &info)?;
+        if max_percentage > 100 {
+            return Err(ContractError::RoyaltyTooHigh { max: 100 });
+        }
+        if percentage.unwrap_or_default() > max_percentage {
+            return Err(ContractError::RoyaltyTooHigh {
+                max: max_percentage,
+            });
+        }
+        let config = RoyaltyConfig {
+            payment_address: payment_address
+                .map(|address| deps.api.addr_validate(&address))
+                .transpose()?,
+            percentage,
+            max_percentage,
+        };
+        ROYALTY_CONFIG.save(deps.storage, &config)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "update_royalty_config")
+            .add_attribute("max_percentage", max_percentage.to_string()))
+    }
+
+    /// Checks the royalty a token sets against the collection's maximum.
+    fn validate_royalty(deps: Deps, metadata: &Metadata) -> Result<(), ContractError> {
+        let config = ROYALTY_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+        if metadata.royalty_percentage.unwrap_or_default() > config.max_percentage {
+            return Err(ContractError::RoyaltyTooHigh {
+                max: config.max_percentage,
+            });
+        }
+        if let Some(address) = &metadata.royalty_payment_address {
+            deps.api.addr_validate(address)?;
+        }
+        Ok(())
+    }
+
+    /// The royalty of a token, falling back to the collection's defaults. A percentage above the
+    /// maximum, which may have been lowered after the mint, is capped at it.
+    fn query_royalty_info(
+        deps: Deps,
+        token_id: String,
+        sale_price: Uint128,
+    ) -> StdResult<RoyaltiesInfoResponse> {
+        let config = ROYALTY_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+        let token = Cw721MetadataContract::default()
+            .tokens
+            .load(deps.storage, &token_id)?;
+        let metadata = token.extension.unwrap_or_default();
+        let percentage = metadata
+            .royalty_percentage
+            .or(config.percentage)
+            .unwrap_or_default()
+            .min(config.max_percentage);
+
+        match metadata
+            .royalty_payment_address
+            .or_else(|| config.payment_address.map(String::from))
+        {
+            Some(address) if percentage > 0 => Ok(RoyaltiesInfoResponse {
+                address,
+                royalty_amount: sale_price.multiply_ratio(percentage, 100u128),
+            }),
+            _ => Ok(RoyaltiesInfoResponse {
+                address: String::new(),
+                royalty_amount: Uint128::zero(),
+            }),
+        }
+    }
+
+    /// Burns a soulbound token, which its owner or the minter may do. The minter revokes
+    /// credentials this way.
+    pub fn execute_burn_soulbound(
+        deps: DepsMut,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response, ContractError> {
+        let contract = Cw721MetadataContract::default();
+        let token = contract.tokens.load(deps.storage, &token_id)?;
+        if info.sender != token.owner && info.sender != contract.minter.load(deps.storage)? {
+            return Err(ContractError::Unauthorized {});
+        }
+        contract.tokens.remove(deps.storage, &token_id)?;
+        contract.decrement_tokens(deps.storage)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "burn")
+            .add_attribute("sender", info.sender)
+            .add_attribute("token_id", token_id))
+    }
+
+    /// Whether the collection or the token is soulbound. Unknown tokens are left to the cw721
+    /// handlers to reject.
+    fn is_soulbound(deps: Deps, token_id: &str) -> StdResult<bool> {
+        if SOULBOUND.may_load(deps.storage)?.unwrap_or_default() {
+            return Ok(true);
+        }
+        let token = Cw721MetadataContract::default()
+            .tokens
+            .may_load(deps.storage, token_id)?;
+        Ok(token
+            .and_then(|token| token.extension)
+            .is_some_and(|metadata| metadata.soulbound))
+    }
+
+    fn ensure_transferable(deps: Deps, token_id: &str) -> Result<(), ContractError> {
+        if is_soulbound(deps, token_id)? {
+            return Err(ContractError::Soulbound {});
+        }
+        Ok(())
+    }
+
+    fn ensure_minter(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+        let minter = Cw721MetadataContract::default().minter.load(deps.storage)?;
+        if info.sender != minter {
+            return Err(ContractError::Unauthorized {});
+        }
+        Ok(())
     }
 
     #[entry_point]
     pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+        let msg = match msg {
+            QueryMsg::OwnerOf {
+                token_id,
+                include_expired,
+            } => Cw721QueryMsg::OwnerOf {
+                token_id,
+                include_expired,
+            },
+            QueryMsg::Approval {
+                token_id,
+                spender,
+                include_expired,
+            } => Cw721QueryMsg::Approval {
+                token_id,
+                spender,
+                include_expired,
+            },
+            QueryMsg::Approvals {
+                token_id,
+                include_expired,
+            } => Cw721QueryMsg::Approvals {
+                token_id,
+                include_expired,
+            },
+            QueryMsg::AllOperators {
+                owner,
+                include_expired,
+                start_after,
+                limit,
+            } => Cw721QueryMsg::AllOperators {
+                owner,
+                include_expired,
+                start_after,
+                limit,
+            },
+            QueryMsg::NumTokens {} => Cw721QueryMsg::NumTokens {},
+            QueryMsg::ContractInfo {} => Cw721QueryMsg::ContractInfo {},
+            QueryMsg::NftInfo { token_id } => Cw721QueryMsg::NftInfo { token_id },
+            QueryMsg::AllNftInfo {
+                token_id,
+                include_expired,
+            } => Cw721QueryMsg::AllNftInfo {
+                token_id,
+                include_expired,
+            },
+            QueryMsg::Tokens {
+                owner,
+                start_after,
+                limit,
+            } => Cw721QueryMsg::Tokens {
+                owner,
+                start_after,
+                limit,
+            },
+            QueryMsg::AllTokens { start_after, limit } => {
+                Cw721QueryMsg::AllTokens { start_after, limit }
+            }
+            QueryMsg::Minter {} => Cw721QueryMsg::Minter {},
+            QueryMsg::MetadataConfig {} => {
+                let config = METADATA_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+                return to_binary(&MetadataConfigResponse {
+                    editor: config.editor.map(String::from),
+                    frozen: config.frozen,
+                });
+            }
+            QueryMsg::Soulbound { token_id } => {
+                return to_binary(&SoulboundResponse {
+                    soulbound: is_soulbound(deps, &token_id)?,
+                });
+            }
+            QueryMsg::RoyaltyConfig {} => {
+                let config = ROYALTY_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+                return to_binary(&RoyaltyConfigResponse {
+                    payment_address: config.payment_address.map(String::from),
+                    percentage: config.percentage,
+                    max_percentage: config.max_percentage,
+                });
+            }
+            QueryMsg::Extension { msg } => {
+                return match msg {
+                    Cw2981QueryMsg::RoyaltyInfo {
+                        token_id,
+                        sale_price,
+                    } => to_binary(&query_royalty_info(deps, token_id, sale_price)?),
+                    Cw2981QueryMsg::CheckRoyalties {} => to_binary(&CheckRoyaltiesResponse {
+                        royalty_payments: true,
+                    }),
+                };
+            }
+        };
         Cw721MetadataContract::default().query(deps, env, msg)
     }
 }
@@ -89,7 +635,7 @@ mod tests {
         let contract = Cw721MetadataContract::default();
 
         let info = mock_info(CREATOR, &[]);
-        let init_msg = InstantiateMsg {
+        let init_msg = cw721_base::InstantiateMsg {
             name: "SpaceShips".to_string(),
             symbol: "SPACE".to_string(),
             minter: CREATOR.to_string(),
@@ -109,7 +655,7 @@ mod tests {
                 ..Metadata::default()
             }),
         };
-        let exec_msg = ExecuteMsg::Mint(mint_msg.clone());
+        let exec_msg = cw721_base::ExecuteMsg::Mint(mint_msg.clone());
         contract
             .execute(deps.as_mut(), mock_env(), info, exec_msg)
             .unwrap();
@@ -118,4 +664,300 @@ mod tests {
         assert_eq!(res.token_uri, mint_msg.token_uri);
         assert_eq!(res.extension, mint_msg.extension);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn update_metadata() {
+        let mut deps = mock_dependencies();
+        let contract = Cw721MetadataContract::default();
+
+        let init_msg = InstantiateMsg {
+            name: "SpaceShips".to_string(),
+            symbol: "SPACE".to_string(),
+            minter: CREATOR.to_string(),
+            soulbound: false,
+        };
+        entry::instantiate(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), init_msg).unwrap();
+        let mint_msg = ExecuteMsg::Mint(MintMsg {
+            token_id: "Enterprise".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: None,
+        });
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), mint_msg).unwrap();
+
+        let update = |name: &str| ExecuteMsg::UpdateMetadata {
+            token_id: "Enterprise".to_string(),
+            metadata: Metadata {
+                name: Some(name.to_string()),
+                ..Metadata::default()
+            },
+        };
+
+        // only the minter and the editor may update metadata
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("john", &[]),
+            update("NCC-1701"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = ExecuteMsg::SetMetadataEditor {
+            editor: Some("editor".to_string()),
+        };
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), msg).unwrap();
+        let res = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("editor", &[]),
+            update("NCC-1701"),
+        )
+        .unwrap();
+        assert_eq!(res.attributes[0].value, "update_metadata");
+        let res = contract
+            .nft_info(deps.as_ref(), "Enterprise".into())
+            .unwrap();
+        assert_eq!(res.extension.unwrap().name, Some("NCC-1701".to_string()));
+
+        // once frozen, nobody can update metadata again
+        let msg = ExecuteMsg::FreezeMetadata {};
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("editor", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), msg).unwrap();
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(CREATOR, &[]),
+            update("NCC-1701-A"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::MetadataFrozen {});
+        let res: MetadataConfigResponse = cosmwasm_std::from_binary(
+            &entry::query(deps.as_ref(), mock_env(), QueryMsg::MetadataConfig {}).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            MetadataConfigResponse {
+                editor: Some("editor".to_string()),
+                frozen: true,
+            }
+        );
+    }
+
+    #[test]
+    fn cw2981_royalties() {
+        let mut deps = mock_dependencies();
+
+        let init_msg = InstantiateMsg {
+            name: "SpaceShips".to_string(),
+            symbol: "SPACE".to_string(),
+            minter: CREATOR.to_string(),
+            soulbound: false,
+        };
+        entry::instantiate(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), init_msg).unwrap();
+        let msg = ExecuteMsg::UpdateRoyaltyConfig {
+            payment_address: Some("studio".to_string()),
+            percentage: Some(2),
+            max_percentage: 10,
+        };
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), msg).unwrap();
+
+        let mint = |token_id: &str, royalty_percentage| {
+            ExecuteMsg::Mint(MintMsg {
+                token_id: token_id.to_string(),
+                owner: "john".to_string(),
+                token_uri: None,
+                extension: Some(Metadata {
+                    royalty_payment_address: Some("artist".to_string()),
+                    royalty_percentage,
+                    ..Metadata::default()
+                }),
+            })
+        };
+
+        // mints above the maximum percentage are rejected
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(CREATOR, &[]),
+            mint("Enterprise", Some(11)),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::RoyaltyTooHigh { max: 10 });
+        entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(CREATOR, &[]),
+            mint("Enterprise", Some(5)),
+        )
+        .unwrap();
+        entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(CREATOR, &[]),
+            mint("Defiant", None),
+        )
+        .unwrap();
+
+        // tokens without a percentage fall back to the collection's default
+        let royalty_info = |token_id: &str| -> RoyaltiesInfoResponse {
+            let msg = QueryMsg::Extension {
+                msg: Cw2981QueryMsg::RoyaltyInfo {
+                    token_id: token_id.to_string(),
+                    sale_price: Uint128::new(1000),
+                },
+            };
+            cosmwasm_std::from_binary(&entry::query(deps.as_ref(), mock_env(), msg).unwrap())
+                .unwrap()
+        };
+        let res = royalty_info("Enterprise");
+        assert_eq!(
+            (res.address.as_str(), res.royalty_amount),
+            ("artist", Uint128::new(50))
+        );
+        let res = royalty_info("Defiant");
+        assert_eq!(
+            (res.address.as_str(), res.royalty_amount),
+            ("artist", Uint128::new(20))
+        );
+
+        let msg = QueryMsg::Extension {
+            msg: Cw2981QueryMsg::CheckRoyalties {},
+        };
+        let res: CheckRoyaltiesResponse =
+            cosmwasm_std::from_binary(&entry::query(deps.as_ref(), mock_env(), msg).unwrap())
+                .unwrap();
+        assert!(res.royalty_payments);
+    }
+
+    #[test]
+    fn soulbound_tokens() {
+        let mut deps = mock_dependencies();
+
+        let init_msg = InstantiateMsg {
+            name: "Badges".to_string(),
+            symbol: "BDG".to_string(),
+            minter: CREATOR.to_string(),
+            soulbound: false,
+        };
+        entry::instantiate(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), init_msg).unwrap();
+        let mint = |token_id: &str, soulbound| {
+            ExecuteMsg::Mint(MintMsg {
+                token_id: token_id.to_string(),
+                owner: "john".to_string(),
+                token_uri: None,
+                extension: Some(Metadata {
+                    soulbound,
+                    ..Metadata::default()
+                }),
+            })
+        };
+        for (token_id, soulbound) in [("badge", true), ("ticket", false)] {
+            let msg = mint(token_id, soulbound);
+            entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), msg).unwrap();
+        }
+        let transfer = |token_id: &str| ExecuteMsg::TransferNft {
+            recipient: "jane".to_string(),
+            token_id: token_id.to_string(),
+        };
+        let approve = |token_id: &str| ExecuteMsg::Approve {
+            spender: "jane".to_string(),
+            token_id: token_id.to_string(),
+            expires: None,
+        };
+
+        // a soulbound token can be neither transferred nor approved, its metadata keeps the flag
+        for msg in [transfer("badge"), approve("badge")] {
+            let err =
+                entry::execute(deps.as_mut(), mock_env(), mock_info("john", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::Soulbound {});
+        }
+        let msg = ExecuteMsg::UpdateMetadata {
+            token_id: "badge".to_string(),
+            metadata: Metadata::default(),
+        };
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), msg).unwrap();
+        let msg = QueryMsg::Soulbound {
+            token_id: "badge".to_string(),
+        };
+        let res: SoulboundResponse =
+            cosmwasm_std::from_binary(&entry::query(deps.as_ref(), mock_env(), msg).unwrap())
+                .unwrap();
+        assert!(res.soulbound);
+
+        // other tokens of the collection stay transferable
+        entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("john", &[]),
+            transfer("ticket"),
+        )
+        .unwrap();
+
+        // the minter can still burn a soulbound token
+        let burn = ExecuteMsg::Burn {
+            token_id: "badge".to_string(),
+        };
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("jane", &[]),
+            burn.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), burn).unwrap();
+        let contract = Cw721MetadataContract::default();
+        assert!(contract.nft_info(deps.as_ref(), "badge".into()).is_err());
+    }
+
+    #[test]
+    fn soulbound_collection() {
+        let mut deps = mock_dependencies();
+
+        let init_msg = InstantiateMsg {
+            name: "Badges".to_string(),
+            symbol: "BDG".to_string(),
+            minter: CREATOR.to_string(),
+            soulbound: true,
+        };
+        entry::instantiate(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), init_msg).unwrap();
+        let msg = ExecuteMsg::Mint(MintMsg {
+            token_id: "badge".to_string(),
+            owner: "john".to_string(),
+            token_uri: None,
+            extension: None,
+        });
+        entry::execute(deps.as_mut(), mock_env(), mock_info(CREATOR, &[]), msg).unwrap();
+
+        let msgs = [
+            ExecuteMsg::SendNft {
+                contract: "market".to_string(),
+                token_id: "badge".to_string(),
+                msg: Binary::default(),
+            },
+            ExecuteMsg::ApproveAll {
+                operator: "jane".to_string(),
+                expires: None,
+            },
+        ];
+        for msg in msgs {
+            let err =
+                entry::execute(deps.as_mut(), mock_env(), mock_info("john", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::Soulbound {});
+        }
+
+        // revoking approvals is still allowed
+        let msg = ExecuteMsg::RevokeAll {
+            operator: "jane".to_string(),
+        };
+        entry::execute(deps.as_mut(), mock_env(), mock_info("john", &[]), msg).unwrap();
+    }
+}
@@ -0,0 +1,23 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Base(#[from] cw721_base::ContractError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Token metadata is frozen")]
+    MetadataFrozen {},
+
+    #[error("Royalty percentage may not exceed {max}")]
+    RoyaltyTooHigh { max: u64 },
+
+    #[error("Soulbound tokens cannot be transferred or approved")]
+    Soulbound {},
+}
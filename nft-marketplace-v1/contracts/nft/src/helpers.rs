@@ -39,7 +39,7 @@ impl NftContract {
         T: Into<String>,
         CQ: CustomQuery,
     {
-        let msg = QueryMsg::OwnerOf { token_id:token_id, include_expired:None };
+        let msg = QueryMsg::OwnerOf { token_id, include_expired:None };
         let query = WasmQuery::Smart { contract_addr: self.addr().into(), msg: to_binary(&msg)? }.into();
         let res: OwnerOfResponse = QuerierWrapper::<CQ>::new(querier).query(&query)?;
         Ok(res)
@@ -0,0 +1,40 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::Addr;
+use cw_storage_plus::Item;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct MetadataConfig {
+    /// May update token metadata besides the minter
+    pub editor: Option<Addr>,
+    /// Once set, token metadata can never change again
+    pub frozen: bool,
+}
+
+/// Makes every token of the collection non-transferable
+pub const SOULBOUND: Item<bool> = Item::new("soulbound");
+
+pub const METADATA_CONFIG: Item<MetadataConfig> = Item::new("metadata_config");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltyConfig {
+    /// Receives the royalties of tokens that name no payment address
+    pub payment_address: Option<Addr>,
+    /// Royalty percentage of tokens that set none
+    pub percentage: Option<u64>,
+    /// Highest royalty percentage a token may set
+    pub max_percentage: u64,
+}
+
+impl Default for RoyaltyConfig {
+    fn default() -> Self {
+        RoyaltyConfig {
+            payment_address: None,
+            percentage: None,
+            max_percentage: 100,
+        }
+    }
+}
+
+pub const ROYALTY_CONFIG: Item<RoyaltyConfig> = Item::new("royalty_config");